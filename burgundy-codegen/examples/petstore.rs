@@ -0,0 +1,39 @@
+//! The client generated from `fixtures/petstore.yaml`.
+//!
+//! Regenerate it with
+//! `cargo run -p burgundy-codegen -- fixtures/petstore.yaml -o fixtures/petstore.rs`,
+//! and run with `cargo run -p burgundy-codegen --example petstore`.
+
+extern crate burgundy;
+extern crate serde;
+extern crate serde_json;
+
+// Not every operation is used by the example.
+#[allow(dead_code)]
+mod petstore {
+    include!("../fixtures/petstore.rs");
+}
+
+use petstore::ListPetsError;
+use petstore::PetStatus;
+use petstore::SwaggerPetstoreClient;
+use std::env;
+
+fn main() {
+    let mut client = SwaggerPetstoreClient::new(SwaggerPetstoreClient::DEFAULT_URL);
+    if let Ok(token) = env::var("PETSTORE_TOKEN") {
+        client.set_bearer_auth(token);
+    }
+
+    match client.list_pets(Some(10), None, Some(PetStatus::Available)) {
+        Ok(pets) => {
+            for pet in pets {
+                println!("{} ({})", pet.name, pet.id);
+            }
+        },
+        Err(ListPetsError::Default(error)) => {
+            println!("The store said no, {} {}", error.code, error.message)
+        },
+        Err(ListPetsError::Request(error)) => println!("{}", error),
+    }
+}
@@ -0,0 +1,462 @@
+// Generated by burgundy-codegen from Swagger Petstore 1.0.0. Do not edit by hand.
+// Needs burgundy, serde (with the derive feature) and serde_json.
+
+/// A client for Swagger Petstore.
+#[derive(Debug)]
+pub struct SwaggerPetstoreClient {
+    domain: ::burgundy::Domain,
+}
+
+impl SwaggerPetstoreClient {
+    /// The url of the first server in the document.
+    pub const DEFAULT_URL: &'static str = "https://petstore.example.com/v1";
+
+    pub fn new(url: &str) -> Self {
+        Self::with_domain(::burgundy::Domain::new(url))
+    }
+
+    /// A client sending its requests through the domain given.
+    pub fn with_domain(domain: ::burgundy::Domain) -> Self {
+        Self {
+            domain,
+        }
+    }
+
+    pub fn domain(&self) -> &::burgundy::Domain {
+        &self.domain
+    }
+
+    /// The domain requests are sent through, to set headers, middleware,
+    /// and such on.
+    pub fn domain_mut(&mut self) -> &mut ::burgundy::Domain {
+        &mut self.domain
+    }
+
+    /// Authenticates using the `apiKey` scheme, with an API key header.
+    pub fn set_api_key(
+        &mut self,
+        key: &str,
+    ) {
+        self.domain.header("X-Api-Key", &key);
+    }
+
+    /// Authenticates using the `basic` scheme, with a user and password.
+    pub fn set_basic(
+        &mut self,
+        user: &str,
+        password: impl Into<::burgundy::Secret>,
+    ) {
+        self.domain.basic_auth(user, password);
+    }
+
+    /// Authenticates using the `bearerAuth` scheme, with a bearer token.
+    pub fn set_bearer_auth(
+        &mut self,
+        token: impl Into<::burgundy::Secret>,
+    ) {
+        self.domain.bearer_auth(token);
+    }
+
+    /// Authenticates using the `oauth` scheme, with tokens from an auth provider, such as `burgundy::oauth2::OAuth2`.
+    pub fn set_oauth(
+        &mut self,
+        provider: impl ::burgundy::AuthProvider + 'static,
+    ) {
+        self.domain.auth_provider(provider);
+    }
+
+    /// `GET /health`
+    pub fn health(
+        &self,
+    ) -> Result<String, ::burgundy::Error> {
+        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();
+
+        let path = self.domain.get().expand("/health", &variables)?;
+
+        accept_success(path.execute_as_string::<()>(None), |body| Some(body.to_string()))
+    }
+
+    /// Lists all pets.
+    ///
+    /// `GET /pets`
+    pub fn list_pets(
+        &self,
+        limit: Option<i32>,
+        tags: Option<&[String]>,
+        status: Option<PetStatus>,
+    ) -> Result<Vec<Pet>, ListPetsError> {
+        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();
+
+        let mut query = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit".to_string(), limit.to_string()));
+        }
+        if let Some(tags) = tags {
+            for value in tags.iter() {
+                query.push(("tags".to_string(), value.to_string()));
+            }
+        }
+        if let Some(status) = status {
+            query.push(("status".to_string(), status.to_string()));
+        }
+
+        let mut path = self.domain.get().expand("/pets", &variables)?;
+        for (key, value) in &query {
+            path = path.query(&encode_query(key), &encode_query(value));
+        }
+
+        accept_success(path.execute_as_json::<(), _>(None), read_json).map_err(ListPetsError::from)
+    }
+
+    /// Adds a pet to the store.
+    ///
+    /// `POST /pets`
+    pub fn create_pet(
+        &self,
+        x_request_id: Option<&str>,
+        body: &NewPet,
+    ) -> Result<Pet, CreatePetError> {
+        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();
+
+        let mut path = self.domain.post().expand("/pets", &variables)?;
+        if let Some(x_request_id) = x_request_id {
+            path.header("X-Request-ID", &x_request_id.to_string());
+        }
+
+        accept_success(path.execute_as_json::<_, _>(Some(body)), read_json).map_err(CreatePetError::from)
+    }
+
+    /// Finds a pet by its id.
+    ///
+    /// `GET /pets/{petId}`
+    pub fn show_pet_by_id(
+        &self,
+        pet_id: i64,
+    ) -> Result<Pet, ShowPetByIdError> {
+        let variables: ::std::collections::BTreeMap<&str, String> = vec![
+            ("pet_id", pet_id.to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        let path = self.domain.get().expand("/pets/{pet_id}", &variables)?;
+
+        accept_success(path.execute_as_json::<(), _>(None), read_json).map_err(ShowPetByIdError::from)
+    }
+
+    /// `DELETE /pets/{petId}`
+    #[deprecated]
+    pub fn delete_pet(
+        &self,
+        pet_id: i64,
+    ) -> Result<(), ::burgundy::Error> {
+        let variables: ::std::collections::BTreeMap<&str, String> = vec![
+            ("pet_id", pet_id.to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        let path = self.domain.delete().expand("/pets/{pet_id}", &variables)?;
+
+        accept_success(path.execute_as_string::<()>(None).map(|_| ()), |_| Some(()))
+    }
+
+    // `POST /pets/{petId}/photo` is left out, as request bodies of `image/png` are not supported.
+
+    /// Counts the pets of each status.
+    ///
+    /// `GET /store/inventory`
+    pub fn get_store_inventory(
+        &self,
+    ) -> Result<::std::collections::BTreeMap<String, i32>, ::burgundy::Error> {
+        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();
+
+        let path = self.domain.get().expand("/store/inventory", &variables)?;
+
+        accept_success(path.execute_as_json::<(), _>(None), read_json)
+    }
+
+    /// `POST /store/orders`
+    pub fn place_order(
+        &self,
+        body: &PlaceOrderBody,
+    ) -> Result<Order, ::burgundy::Error> {
+        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();
+
+        let path = self.domain.post().expand("/store/orders", &variables)?.format(::burgundy::Format::Form);
+
+        accept_success(path.execute_as::<_, _>(Some(body)), read_json)
+    }
+}
+
+/// Burgundy only treats `200` as ok, and returns other `2xx` statuses,
+/// such as `201 Created` or `204 No Content`, as `RequestNotOk`. Those are
+/// read here as successes, using `read` on their body.
+fn accept_success<R>(
+    result: Result<R, ::burgundy::Error>,
+    read: impl FnOnce(&str) -> Option<R>,
+) -> Result<R, ::burgundy::Error> {
+    let error = match result {
+        Err(error) => error,
+        ok => return ok,
+    };
+
+    let value = match &error {
+        ::burgundy::Error::RequestNotOk {
+            status: 201..=299,
+            body,
+            ..
+        } => read(body),
+        _ => None,
+    };
+
+    value.ok_or(error)
+}
+
+/// Reads the JSON body of a success response. An empty body is read as
+/// `null`.
+fn read_json<R: ::serde::de::DeserializeOwned>(body: &str) -> Option<R> {
+    let body = if body.trim().is_empty() { "null" } else { body };
+
+    ::serde_json::from_str(body).ok()
+}
+
+/// Percent encodes a query parameter key or value.
+fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            },
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// The errors of `list_pets`, for `GET /pets`.
+#[derive(Debug)]
+pub enum ListPetsError {
+    /// Something went wrong.
+    Default(Error),
+
+    /// The request failed, without a response body which could be read.
+    Request(::burgundy::Error),
+}
+
+impl From<::burgundy::Error> for ListPetsError {
+    fn from(error: ::burgundy::Error) -> Self {
+        let body = match &error {
+            ::burgundy::Error::RequestNotOk {
+                body,
+                ..
+            }
+            | ::burgundy::Error::PreconditionFailed {
+                body,
+                ..
+            } => body.as_str(),
+            _ => return ListPetsError::Request(error),
+        };
+
+        let typed = match error.status() {
+            Some(_) => ::serde_json::from_str(body).ok().map(ListPetsError::Default),
+            None => None,
+        };
+
+        typed.unwrap_or(ListPetsError::Request(error))
+    }
+}
+
+impl ::std::fmt::Display for ListPetsError {
+    fn fmt(
+        &self,
+        f: &mut ::std::fmt::Formatter,
+    ) -> ::std::fmt::Result {
+        match self {
+            ListPetsError::Default(body) => write!(f, "Request failed with status default, {:?}", body),
+            ListPetsError::Request(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl ::std::error::Error for ListPetsError {}
+
+/// The errors of `create_pet`, for `POST /pets`.
+#[derive(Debug)]
+pub enum CreatePetError {
+    /// The pet was not valid.
+    Status4xx(Error),
+
+    /// The request failed, without a response body which could be read.
+    Request(::burgundy::Error),
+}
+
+impl From<::burgundy::Error> for CreatePetError {
+    fn from(error: ::burgundy::Error) -> Self {
+        let body = match &error {
+            ::burgundy::Error::RequestNotOk {
+                body,
+                ..
+            }
+            | ::burgundy::Error::PreconditionFailed {
+                body,
+                ..
+            } => body.as_str(),
+            _ => return CreatePetError::Request(error),
+        };
+
+        let typed = match error.status() {
+            Some(400..=499) => ::serde_json::from_str(body).ok().map(CreatePetError::Status4xx),
+            _ => None,
+        };
+
+        typed.unwrap_or(CreatePetError::Request(error))
+    }
+}
+
+impl ::std::fmt::Display for CreatePetError {
+    fn fmt(
+        &self,
+        f: &mut ::std::fmt::Formatter,
+    ) -> ::std::fmt::Result {
+        match self {
+            CreatePetError::Status4xx(body) => write!(f, "Request failed with status 4XX, {:?}", body),
+            CreatePetError::Request(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl ::std::error::Error for CreatePetError {}
+
+/// The errors of `show_pet_by_id`, for `GET /pets/{petId}`.
+#[derive(Debug)]
+pub enum ShowPetByIdError {
+    /// There is no pet with the id.
+    Status404(Error),
+
+    /// The request failed, without a response body which could be read.
+    Request(::burgundy::Error),
+}
+
+impl From<::burgundy::Error> for ShowPetByIdError {
+    fn from(error: ::burgundy::Error) -> Self {
+        let body = match &error {
+            ::burgundy::Error::RequestNotOk {
+                body,
+                ..
+            }
+            | ::burgundy::Error::PreconditionFailed {
+                body,
+                ..
+            } => body.as_str(),
+            _ => return ShowPetByIdError::Request(error),
+        };
+
+        let typed = match error.status() {
+            Some(404) => ::serde_json::from_str(body).ok().map(ShowPetByIdError::Status404),
+            _ => None,
+        };
+
+        typed.unwrap_or(ShowPetByIdError::Request(error))
+    }
+}
+
+impl ::std::fmt::Display for ShowPetByIdError {
+    fn fmt(
+        &self,
+        f: &mut ::std::fmt::Formatter,
+    ) -> ::std::fmt::Result {
+        match self {
+            ShowPetByIdError::Status404(body) => write!(f, "Request failed with status 404, {:?}", body),
+            ShowPetByIdError::Request(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl ::std::error::Error for ShowPetByIdError {}
+
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct Error {
+    pub code: i32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct NewPet {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// The kind of animal, such as `dog`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct Order {
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<::serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shipping: Option<OrderShipping>,
+}
+
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct OrderShipping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub express: Option<bool>,
+}
+
+/// A pet in the store.
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct Pet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<::std::collections::BTreeMap<String, String>>,
+    pub id: i64,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Box<Pet>>,
+    pub status: PetStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// The kind of animal, such as `dog`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+}
+
+/// Where the pet is in being sold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::serde::Serialize, ::serde::Deserialize)]
+pub enum PetStatus {
+    #[serde(rename = "available")]
+    Available,
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "sold")]
+    Sold,
+}
+
+impl ::std::fmt::Display for PetStatus {
+    fn fmt(
+        &self,
+        f: &mut ::std::fmt::Formatter,
+    ) -> ::std::fmt::Result {
+        let value = match self {
+            PetStatus::Available => "available",
+            PetStatus::Pending => "pending",
+            PetStatus::Sold => "sold",
+        };
+
+        f.write_str(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+pub struct PlaceOrderBody {
+    #[serde(rename = "petId")]
+    pub pet_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<i32>,
+}
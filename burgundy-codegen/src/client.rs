@@ -0,0 +1,961 @@
+use error;
+use names;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use types;
+
+/// The HTTP methods of a path item, in the order they are generated.
+const HTTP_METHODS: &[&str] =
+    &["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// The generated client, and the error types of its operations.
+#[derive(Debug)]
+pub(crate) struct Client {
+    pub(crate) code: String,
+    pub(crate) errors: Vec<String>,
+}
+
+/// The private functions which generated operations call. Only those used
+/// are written, so the generated code has no dead code.
+#[derive(Debug, Default)]
+struct Helpers {
+    accept_success: bool,
+    read_json: bool,
+    encode_query: bool,
+}
+
+/// Where a parameter goes in the request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Location {
+    Path,
+    Query,
+    Header,
+}
+
+#[derive(Debug)]
+struct Parameter {
+    name: String,
+    field: String,
+    location: Location,
+    is_required: bool,
+    ty: String,
+    argument_type: String,
+}
+
+#[derive(Debug)]
+struct Body {
+    field: String,
+    is_required: bool,
+    is_form: bool,
+    argument_type: String,
+}
+
+/// What the body of a successful response is read as.
+#[derive(Debug)]
+enum Success {
+    Json(String),
+    Text,
+    Empty,
+}
+
+impl Success {
+    fn rust_type(&self) -> &str {
+        match self {
+            Success::Json(ty) => ty,
+            Success::Text => "String",
+            Success::Empty => "()",
+        }
+    }
+}
+
+/// A status of an error response, with a schema for its body.
+#[derive(Debug)]
+struct ErrorResponse {
+    variant: String,
+    status: String,
+    description: Option<String>,
+    ty: String,
+}
+
+pub(crate) fn generate<'a>(
+    spec: &'a Value,
+    types: &mut types::Types<'a>,
+) -> error::Result<Client> {
+    let title = spec.pointer("/info/title").and_then(Value::as_str).unwrap_or("Api");
+    let client_name = format!("{}Client", names::pascal_case(title));
+
+    let mut code = String::new();
+    let mut errors = Vec::new();
+
+    writeln!(code, "/// A client for {}.", title).unwrap();
+    writeln!(code, "#[derive(Debug)]").unwrap();
+    writeln!(code, "pub struct {} {{", client_name).unwrap();
+    writeln!(code, "    domain: ::burgundy::Domain,").unwrap();
+    writeln!(code, "}}").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "impl {} {{", client_name).unwrap();
+
+    if let Some(url) = spec.pointer("/servers/0/url").and_then(Value::as_str) {
+        writeln!(code, "    /// The url of the first server in the document.").unwrap();
+        writeln!(code, "    pub const DEFAULT_URL: &'static str = {:?};", url).unwrap();
+        writeln!(code).unwrap();
+    }
+
+    code.push_str(CONSTRUCTORS);
+    write_security_schemes(&mut code, spec)?;
+
+    let mut helpers = Helpers::default();
+    let mut method_names = BTreeSet::new();
+    for (path, path_item) in sorted_entries(spec.get("paths")) {
+        let path_pointer = format!("/paths/{}", types::escape_pointer(path));
+
+        for &http_method in HTTP_METHODS {
+            let operation = match path_item.get(http_method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+            let pointer = format!("{}/{}", path_pointer, http_method);
+            let method_name = match operation.get("operationId").and_then(Value::as_str) {
+                Some(operation_id) => names::snake_case(operation_id),
+                None => names::snake_case(&format!("{} {}", http_method, path)),
+            };
+            if !method_names.insert(method_name.clone()) {
+                return Err(error::Error::new_invalid_spec(
+                    &pointer,
+                    format!("more than one operation is named `{}`", method_name),
+                ));
+            }
+
+            let operation = Operation {
+                spec,
+                path,
+                http_method,
+                path_item,
+                operation,
+                pointer: &pointer,
+                method_name: &method_name,
+            };
+            writeln!(code).unwrap();
+            if let Some(error) = operation.write(&mut code, types, &mut helpers)? {
+                errors.push(error);
+            }
+        }
+    }
+
+    writeln!(code, "}}").unwrap();
+    for (is_used, helper) in [
+        (helpers.accept_success, ACCEPT_SUCCESS),
+        (helpers.read_json, READ_JSON),
+        (helpers.encode_query, ENCODE_QUERY),
+    ] {
+        if is_used {
+            writeln!(code).unwrap();
+            code.push_str(helper);
+        }
+    }
+
+    Ok(Client {
+        code,
+        errors,
+    })
+}
+
+const CONSTRUCTORS: &str = "    pub fn new(url: &str) -> Self {
+        Self::with_domain(::burgundy::Domain::new(url))
+    }
+
+    /// A client sending its requests through the domain given.
+    pub fn with_domain(domain: ::burgundy::Domain) -> Self {
+        Self {
+            domain,
+        }
+    }
+
+    pub fn domain(&self) -> &::burgundy::Domain {
+        &self.domain
+    }
+
+    /// The domain requests are sent through, to set headers, middleware,
+    /// and such on.
+    pub fn domain_mut(&mut self) -> &mut ::burgundy::Domain {
+        &mut self.domain
+    }
+";
+
+const ACCEPT_SUCCESS: &str =
+    "/// Burgundy only treats `200` as ok, and returns other `2xx` statuses,
+/// such as `201 Created` or `204 No Content`, as `RequestNotOk`. Those are
+/// read here as successes, using `read` on their body.
+fn accept_success<R>(
+    result: Result<R, ::burgundy::Error>,
+    read: impl FnOnce(&str) -> Option<R>,
+) -> Result<R, ::burgundy::Error> {
+    let error = match result {
+        Err(error) => error,
+        ok => return ok,
+    };
+
+    let value = match &error {
+        ::burgundy::Error::RequestNotOk {
+            status: 201..=299,
+            body,
+            ..
+        } => read(body),
+        _ => None,
+    };
+
+    value.ok_or(error)
+}
+";
+
+const READ_JSON: &str = "/// Reads the JSON body of a success response. An empty body is read as
+/// `null`.
+fn read_json<R: ::serde::de::DeserializeOwned>(body: &str) -> Option<R> {
+    let body = if body.trim().is_empty() { \"null\" } else { body };
+
+    ::serde_json::from_str(body).ok()
+}
+";
+
+const ENCODE_QUERY: &str = "/// Percent encodes a query parameter key or value.
+fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            },
+            _ => encoded.push_str(&format!(\"%{:02X}\", byte)),
+        }
+    }
+
+    encoded
+}
+";
+
+/// Writes a setter for each of the `securitySchemes` of the document.
+/// Credentials set are sent with every request.
+fn write_security_schemes(
+    code: &mut String,
+    spec: &Value,
+) -> error::Result<()> {
+    for (name, scheme) in sorted_entries(spec.pointer("/components/securitySchemes")) {
+        let pointer = format!("/components/securitySchemes/{}", types::escape_pointer(name));
+        let setter = format!("set_{}", names::unescaped(&names::snake_case(name)));
+        let field = |key: &str| scheme.get(key).and_then(Value::as_str).unwrap_or("");
+
+        let (doc, arguments, body) = match (field("type"), field("scheme"), field("in")) {
+            ("http", "bearer", _) | ("http", "Bearer", _) => (
+                "a bearer token",
+                "token: impl Into<::burgundy::Secret>".to_string(),
+                "self.domain.bearer_auth(token);".to_string(),
+            ),
+            ("http", "basic", _) | ("http", "Basic", _) => (
+                "a user and password",
+                "user: &str,\n        password: impl Into<::burgundy::Secret>".to_string(),
+                "self.domain.basic_auth(user, password);".to_string(),
+            ),
+            ("apiKey", _, "header") => (
+                "an API key header",
+                "key: &str".to_string(),
+                format!("self.domain.header({:?}, &key);", field("name")),
+            ),
+            ("apiKey", _, "query") => (
+                "an API key query parameter",
+                "key: impl Into<::burgundy::Secret>".to_string(),
+                format!("self.domain.secret_query({:?}, key);", field("name")),
+            ),
+            ("apiKey", _, "cookie") => (
+                "an API key cookie",
+                "key: &str".to_string(),
+                format!(
+                    "self.domain.header(\"Cookie\", &format!(\"{}={{}}\", key));",
+                    field("name")
+                ),
+            ),
+            ("oauth2", _, _) | ("openIdConnect", _, _) => (
+                "tokens from an auth provider, such as `burgundy::oauth2::OAuth2`",
+                "provider: impl ::burgundy::AuthProvider + 'static".to_string(),
+                "self.domain.auth_provider(provider);".to_string(),
+            ),
+            (ty, _, _) => {
+                return Err(error::Error::new_invalid_spec(
+                    &pointer,
+                    format!("security schemes of type '{}' are not supported", ty),
+                ))
+            },
+        };
+
+        writeln!(code).unwrap();
+        writeln!(code, "    /// Authenticates using the `{}` scheme, with {}.", name, doc).unwrap();
+        writeln!(code, "    pub fn {}(", setter).unwrap();
+        writeln!(code, "        &mut self,").unwrap();
+        writeln!(code, "        {},", arguments).unwrap();
+        writeln!(code, "    ) {{").unwrap();
+        writeln!(code, "        {}", body).unwrap();
+        writeln!(code, "    }}").unwrap();
+    }
+
+    Ok(())
+}
+
+struct Operation<'a, 'b> {
+    spec: &'a Value,
+    path: &'b str,
+    http_method: &'b str,
+    path_item: &'a Value,
+    operation: &'a Value,
+    pointer: &'b str,
+    method_name: &'b str,
+}
+
+impl<'a, 'b> Operation<'a, 'b> {
+    /// Writes the method for the operation, and returns the code of its
+    /// error type, if it has one.
+    fn write(
+        &self,
+        code: &mut String,
+        types: &mut types::Types<'a>,
+        helpers: &mut Helpers,
+    ) -> error::Result<Option<String>> {
+        let type_prefix = names::pascal_case(names::unescaped(self.method_name));
+
+        let parameters = self.parameters(types, &type_prefix)?;
+        let body = match self.body(types, &type_prefix, &parameters)? {
+            Ok(body) => body,
+            Err(content_type) => {
+                writeln!(
+                    code,
+                    "    // `{} {}` is left out, as request bodies of `{}` are not supported.",
+                    self.http_method.to_uppercase(),
+                    self.path,
+                    content_type
+                )
+                .unwrap();
+                return Ok(None);
+            },
+        };
+        let (success, error_responses) = self.responses(types, &type_prefix)?;
+        let error_type = if error_responses.is_empty() {
+            "::burgundy::Error".to_string()
+        } else {
+            format!("{}Error", type_prefix)
+        };
+
+        self.write_doc(code);
+        writeln!(code, "    pub fn {}(", self.method_name).unwrap();
+        writeln!(code, "        &self,").unwrap();
+        for parameter in &parameters {
+            writeln!(code, "        {}: {},", parameter.field, parameter.argument_type).unwrap();
+        }
+        if let Some(body) = &body {
+            writeln!(code, "        {}: {},", body.field, body.argument_type).unwrap();
+        }
+        writeln!(code, "    ) -> Result<{}, {}> {{", success.rust_type(), error_type).unwrap();
+
+        self.write_body(code, &parameters, body.as_ref(), &success, &error_type, helpers);
+        writeln!(code, "    }}").unwrap();
+
+        if error_responses.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(write_error_type(&error_type, self, &error_responses)))
+        }
+    }
+
+    fn write_doc(
+        &self,
+        code: &mut String,
+    ) {
+        let summary = self.operation.get("summary");
+        let description = self.operation.get("description");
+
+        types::write_doc(code, summary, "    ");
+        if summary.is_some() && description.is_some() {
+            writeln!(code, "    ///").unwrap();
+        }
+        types::write_doc(code, description, "    ");
+        if summary.is_some() || description.is_some() {
+            writeln!(code, "    ///").unwrap();
+        }
+        writeln!(code, "    /// `{} {}`", self.http_method.to_uppercase(), self.path).unwrap();
+        if self.operation.get("deprecated") == Some(&Value::Bool(true)) {
+            writeln!(code, "    #[deprecated]").unwrap();
+        }
+    }
+
+    fn write_body(
+        &self,
+        code: &mut String,
+        parameters: &[Parameter],
+        body: Option<&Body>,
+        success: &Success,
+        error_type: &str,
+        helpers: &mut Helpers,
+    ) {
+        let mut template = self.path.to_string();
+        let path_parameters =
+            parameters.iter().filter(|parameter| parameter.location == Location::Path);
+        let mut variables = Vec::new();
+        for parameter in path_parameters {
+            let key = names::unescaped(&parameter.field).to_string();
+            template =
+                template.replace(&format!("{{{}}}", parameter.name), &format!("{{{}}}", key));
+            variables.push(format!("({:?}, {})", key, to_string(parameter)));
+        }
+
+        if variables.is_empty() {
+            writeln!(
+                code,
+                "        let variables: ::std::collections::BTreeMap<&str, String> = ::std::collections::BTreeMap::new();"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                code,
+                "        let variables: ::std::collections::BTreeMap<&str, String> = vec!["
+            )
+            .unwrap();
+            for variable in &variables {
+                writeln!(code, "            {},", variable).unwrap();
+            }
+            writeln!(code, "        ]").unwrap();
+            writeln!(code, "        .into_iter()").unwrap();
+            writeln!(code, "        .collect();").unwrap();
+        }
+
+        let query_parameters = parameters
+            .iter()
+            .filter(|parameter| parameter.location == Location::Query)
+            .collect::<Vec<_>>();
+        if !query_parameters.is_empty() {
+            writeln!(code).unwrap();
+            writeln!(code, "        let mut query = Vec::new();").unwrap();
+            for parameter in &query_parameters {
+                write_query_parameter(code, parameter);
+            }
+        }
+
+        let header_parameters = parameters
+            .iter()
+            .filter(|parameter| parameter.location == Location::Header)
+            .collect::<Vec<_>>();
+        let is_form = body.is_some_and(|body| body.is_form);
+        let is_mut = !header_parameters.is_empty() || !query_parameters.is_empty();
+
+        writeln!(code).unwrap();
+        writeln!(
+            code,
+            "        let {}path = self.domain.{}().expand({:?}, &variables)?{};",
+            if is_mut {
+                "mut "
+            } else {
+                ""
+            },
+            self.http_method,
+            template,
+            if is_form {
+                ".format(::burgundy::Format::Form)"
+            } else {
+                ""
+            },
+        )
+        .unwrap();
+        if !query_parameters.is_empty() {
+            helpers.encode_query = true;
+            writeln!(code, "        for (key, value) in &query {{").unwrap();
+            writeln!(
+                code,
+                "            path = path.query(&encode_query(key), &encode_query(value));"
+            )
+            .unwrap();
+            writeln!(code, "        }}").unwrap();
+        }
+        for parameter in &header_parameters {
+            if parameter.is_required {
+                writeln!(
+                    code,
+                    "        path.header({:?}, &{});",
+                    parameter.name,
+                    to_string(parameter)
+                )
+                .unwrap();
+            } else {
+                writeln!(code, "        if let Some({}) = {} {{", parameter.field, parameter.field)
+                    .unwrap();
+                writeln!(
+                    code,
+                    "            path.header({:?}, &{});",
+                    parameter.name,
+                    to_string(parameter)
+                )
+                .unwrap();
+                writeln!(code, "        }}").unwrap();
+            }
+        }
+
+        let body_argument = match body {
+            Some(body) if body.is_required => format!("Some({})", body.field),
+            Some(body) => body.field.clone(),
+            None => "None".to_string(),
+        };
+        let body_type = if body.is_some() {
+            "_"
+        } else {
+            "()"
+        };
+        helpers.accept_success = true;
+        let execute = match success {
+            Success::Json(_) => {
+                helpers.read_json = true;
+                format!(
+                    "accept_success(path.{}::<{}, _>({}), read_json)",
+                    if is_form {
+                        "execute_as"
+                    } else {
+                        "execute_as_json"
+                    },
+                    body_type,
+                    body_argument
+                )
+            },
+            Success::Text => format!(
+                "accept_success(path.execute_as_string::<{}>({}), |body| Some(body.to_string()))",
+                body_type, body_argument
+            ),
+            Success::Empty => format!(
+                "accept_success(path.execute_as_string::<{}>({}).map(|_| ()), |_| Some(()))",
+                body_type, body_argument
+            ),
+        };
+
+        writeln!(code).unwrap();
+        if error_type == "::burgundy::Error" {
+            writeln!(code, "        {}", execute).unwrap();
+        } else {
+            writeln!(code, "        {}.map_err({}::from)", execute, error_type).unwrap();
+        }
+    }
+
+    /// The parameters of the operation, with those of the path item
+    /// included. Cookie parameters are not supported, and are left out.
+    fn parameters(
+        &self,
+        types: &mut types::Types<'a>,
+        type_prefix: &str,
+    ) -> error::Result<Vec<Parameter>> {
+        let mut found = Vec::<(&'a Value, String)>::new();
+        let sources = [
+            (self.path_item, self.pointer.rsplit_once('/').map_or("", |(path, _)| path)),
+            (self.operation, self.pointer),
+        ];
+
+        for (source, source_pointer) in sources.iter() {
+            let list = source.get("parameters").and_then(Value::as_array);
+            for (i, parameter) in list.into_iter().flatten().enumerate() {
+                let pointer = format!("{}/parameters/{}", source_pointer, i);
+                let parameter = resolve(self.spec, parameter, &pointer)?;
+                let key = |parameter: &Value| {
+                    (parameter.get("name").cloned(), parameter.get("in").cloned())
+                };
+
+                found.retain(|(existing, _)| key(existing) != key(parameter));
+                found.push((parameter, pointer));
+            }
+        }
+
+        let mut parameters = Vec::new();
+        for (parameter, pointer) in found {
+            let name = parameter.get("name").and_then(Value::as_str).ok_or_else(|| {
+                error::Error::new_invalid_spec(&pointer, "parameters must have a `name`")
+            })?;
+            let location = match parameter.get("in").and_then(Value::as_str) {
+                Some("path") => Location::Path,
+                Some("query") => Location::Query,
+                Some("header") => Location::Header,
+                Some("cookie") => continue,
+                _ => {
+                    return Err(error::Error::new_invalid_spec(
+                        &pointer,
+                        "parameters must be `in` the path, query, header or a cookie",
+                    ))
+                },
+            };
+            let is_required =
+                location == Location::Path || parameter.get("required") == Some(&Value::Bool(true));
+
+            let schema = parameter.get("schema").unwrap_or(&Value::Null);
+            let hint = format!("{}{}", type_prefix, names::pascal_case(name));
+            let mut ty = types.rust_type(schema, &hint, &format!("{}/schema", pointer))?;
+            if ty.starts_with("Option<") {
+                ty = ty["Option<".len()..ty.len() - 1].to_string();
+            }
+            if !is_displayed(&ty, types) {
+                ty = "::serde_json::Value".to_string();
+            }
+
+            let argument_type = argument_type(&ty, types);
+            let argument_type = if is_required {
+                argument_type
+            } else {
+                format!("Option<{}>", argument_type)
+            };
+
+            parameters.push(Parameter {
+                name: name.to_string(),
+                field: names::snake_case(name),
+                location,
+                is_required,
+                ty,
+                argument_type,
+            });
+        }
+
+        Ok(parameters)
+    }
+
+    /// The request body. Returns the content type as an error when it is
+    /// not one which can be sent.
+    fn body(
+        &self,
+        types: &mut types::Types<'a>,
+        type_prefix: &str,
+        parameters: &[Parameter],
+    ) -> error::Result<Result<Option<Body>, String>> {
+        let pointer = format!("{}/requestBody", self.pointer);
+        let request_body = match self.operation.get("requestBody") {
+            Some(request_body) => resolve(self.spec, request_body, &pointer)?,
+            None => return Ok(Ok(None)),
+        };
+
+        let content = sorted_entries(request_body.get("content"));
+        let json = content.iter().find(|(content_type, _)| is_json(content_type));
+        let form = content
+            .iter()
+            .find(|(content_type, _)| *content_type == "application/x-www-form-urlencoded");
+        let (content_type, media_type, is_form) = match (json, form, content.first()) {
+            (Some((content_type, media_type)), _, _) => (content_type, media_type, false),
+            (None, Some((content_type, media_type)), _) => (content_type, media_type, true),
+            (None, None, Some((content_type, _))) => return Ok(Err(content_type.to_string())),
+            (None, None, None) => return Ok(Ok(None)),
+        };
+
+        let schema = media_type.get("schema").unwrap_or(&Value::Null);
+        let schema_pointer =
+            format!("{}/content/{}/schema", pointer, types::escape_pointer(content_type));
+        let ty = types.rust_type(schema, &format!("{}Body", type_prefix), &schema_pointer)?;
+        let is_required = request_body.get("required") == Some(&Value::Bool(true));
+        let argument_type = format!("&{}", ty);
+
+        let is_taken = parameters.iter().any(|parameter| parameter.field == "body");
+        Ok(Ok(Some(Body {
+            field: if is_taken {
+                "request_body"
+            } else {
+                "body"
+            }
+            .to_string(),
+            is_required,
+            is_form,
+            argument_type: if is_required {
+                argument_type
+            } else {
+                format!("Option<{}>", argument_type)
+            },
+        })))
+    }
+
+    /// What a successful response is read as, and the error responses
+    /// which have a body to read.
+    fn responses(
+        &self,
+        types: &mut types::Types<'a>,
+        type_prefix: &str,
+    ) -> error::Result<(Success, Vec<ErrorResponse>)> {
+        let mut success = None;
+        let mut errors = Vec::new();
+        let mut statuses = sorted_entries(self.operation.get("responses"));
+        statuses.sort_by_key(|(status, _)| *status == "default");
+
+        for (status, response) in statuses {
+            let pointer = format!("{}/responses/{}", self.pointer, status);
+            let response = resolve(self.spec, response, &pointer)?;
+            let content = sorted_entries(response.get("content"));
+            let json = content.iter().find(|(content_type, _)| is_json(content_type));
+
+            if status.starts_with('2') {
+                if success.is_some() {
+                    continue;
+                }
+
+                success = Some(match (json, content.first()) {
+                    (Some((content_type, media_type)), _) => {
+                        let schema = media_type.get("schema").unwrap_or(&Value::Null);
+                        let schema_pointer = format!(
+                            "{}/content/{}/schema",
+                            pointer,
+                            types::escape_pointer(content_type)
+                        );
+                        Success::Json(types.rust_type(
+                            schema,
+                            &format!("{}Response", type_prefix),
+                            &schema_pointer,
+                        )?)
+                    },
+                    (None, Some(_)) => Success::Text,
+                    (None, None) => Success::Empty,
+                });
+            } else if status.starts_with(['4', '5']) || status == "default" {
+                let (content_type, media_type) = match json {
+                    Some(json) => json,
+                    None => continue,
+                };
+                let schema = match media_type.get("schema") {
+                    Some(schema) => schema,
+                    None => continue,
+                };
+
+                let variant = if status == "default" {
+                    "Default".to_string()
+                } else {
+                    format!("Status{}", status.to_lowercase())
+                };
+                let schema_pointer =
+                    format!("{}/content/{}/schema", pointer, types::escape_pointer(content_type));
+                let ty = types.rust_type(
+                    schema,
+                    &format!("{}{}", type_prefix, variant),
+                    &schema_pointer,
+                )?;
+
+                errors.push(ErrorResponse {
+                    variant,
+                    status: status.to_string(),
+                    description: response
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    ty,
+                });
+            }
+        }
+
+        Ok((success.unwrap_or(Success::Empty), errors))
+    }
+}
+
+/// The error type of an operation, which reads the bodies of the error
+/// responses it describes.
+fn write_error_type(
+    name: &str,
+    operation: &Operation,
+    responses: &[ErrorResponse],
+) -> String {
+    let mut code = String::new();
+
+    writeln!(
+        code,
+        "/// The errors of `{}`, for `{} {}`.",
+        operation.method_name,
+        operation.http_method.to_uppercase(),
+        operation.path
+    )
+    .unwrap();
+    writeln!(code, "#[derive(Debug)]").unwrap();
+    writeln!(code, "pub enum {} {{", name).unwrap();
+    for response in responses {
+        let description =
+            response.description.as_ref().map(|description| Value::String(description.clone()));
+        types::write_doc(&mut code, description.as_ref(), "    ");
+        writeln!(code, "    {}({}),", response.variant, response.ty).unwrap();
+    }
+    writeln!(code).unwrap();
+    writeln!(code, "    /// The request failed, without a response body which could be read.")
+        .unwrap();
+    writeln!(code, "    Request(::burgundy::Error),").unwrap();
+    writeln!(code, "}}").unwrap();
+    writeln!(code).unwrap();
+
+    writeln!(code, "impl From<::burgundy::Error> for {} {{", name).unwrap();
+    writeln!(code, "    fn from(error: ::burgundy::Error) -> Self {{").unwrap();
+    writeln!(code, "        let body = match &error {{").unwrap();
+    writeln!(code, "            ::burgundy::Error::RequestNotOk {{").unwrap();
+    writeln!(code, "                body,").unwrap();
+    writeln!(code, "                ..").unwrap();
+    writeln!(code, "            }}").unwrap();
+    writeln!(code, "            | ::burgundy::Error::PreconditionFailed {{").unwrap();
+    writeln!(code, "                body,").unwrap();
+    writeln!(code, "                ..").unwrap();
+    writeln!(code, "            }} => body.as_str(),").unwrap();
+    writeln!(code, "            _ => return {}::Request(error),", name).unwrap();
+    writeln!(code, "        }};").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "        let typed = match error.status() {{").unwrap();
+    for response in responses {
+        let pattern = match response.status.as_bytes() {
+            b"default" => "Some(_)".to_string(),
+            [class, b'X', b'X'] | [class, b'x', b'x'] => {
+                let class = (class - b'0') as u32 * 100;
+                format!("Some({}..={})", class, class + 99)
+            },
+            _ => format!("Some({})", response.status),
+        };
+        writeln!(
+            code,
+            "            {} => ::serde_json::from_str(body).ok().map({}::{}),",
+            pattern, name, response.variant
+        )
+        .unwrap();
+    }
+    if !responses.iter().any(|response| response.status == "default") {
+        writeln!(code, "            _ => None,").unwrap();
+    } else {
+        writeln!(code, "            None => None,").unwrap();
+    }
+    writeln!(code, "        }};").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "        typed.unwrap_or({}::Request(error))", name).unwrap();
+    writeln!(code, "    }}").unwrap();
+    writeln!(code, "}}").unwrap();
+    writeln!(code).unwrap();
+
+    writeln!(code, "impl ::std::fmt::Display for {} {{", name).unwrap();
+    writeln!(code, "    fn fmt(").unwrap();
+    writeln!(code, "        &self,").unwrap();
+    writeln!(code, "        f: &mut ::std::fmt::Formatter,").unwrap();
+    writeln!(code, "    ) -> ::std::fmt::Result {{").unwrap();
+    writeln!(code, "        match self {{").unwrap();
+    for response in responses {
+        writeln!(
+            code,
+            "            {}::{}(body) => write!(f, \"Request failed with status {}, {{:?}}\", body),",
+            name, response.variant, response.status
+        )
+        .unwrap();
+    }
+    writeln!(code, "            {}::Request(error) => write!(f, \"{{}}\", error),", name).unwrap();
+    writeln!(code, "        }}").unwrap();
+    writeln!(code, "    }}").unwrap();
+    writeln!(code, "}}").unwrap();
+    writeln!(code).unwrap();
+    writeln!(code, "impl ::std::error::Error for {} {{}}", name).unwrap();
+
+    code
+}
+
+fn write_query_parameter(
+    code: &mut String,
+    parameter: &Parameter,
+) {
+    let (indent, value) = if parameter.is_required {
+        ("        ", parameter.field.clone())
+    } else {
+        writeln!(code, "        if let Some({}) = {} {{", parameter.field, parameter.field)
+            .unwrap();
+        ("            ", parameter.field.clone())
+    };
+
+    if parameter.ty.starts_with("Vec<") {
+        writeln!(code, "{}for value in {}.iter() {{", indent, value).unwrap();
+        writeln!(
+            code,
+            "{}    query.push(({:?}.to_string(), value.to_string()));",
+            indent, parameter.name
+        )
+        .unwrap();
+        writeln!(code, "{}}}", indent).unwrap();
+    } else {
+        writeln!(
+            code,
+            "{}query.push(({:?}.to_string(), {}.to_string()));",
+            indent, parameter.name, value
+        )
+        .unwrap();
+    }
+
+    if !parameter.is_required {
+        writeln!(code, "        }}").unwrap();
+    }
+}
+
+/// The parameter as a string, for the path or a header. Lists are joined
+/// with commas.
+fn to_string(parameter: &Parameter) -> String {
+    if parameter.ty.starts_with("Vec<") {
+        format!(
+            "{}.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(\",\")",
+            parameter.field
+        )
+    } else {
+        format!("{}.to_string()", parameter.field)
+    }
+}
+
+/// How a value of the type is taken as an argument.
+fn argument_type(
+    ty: &str,
+    types: &types::Types,
+) -> String {
+    if ty == "String" {
+        "&str".to_string()
+    } else if let Some(item) = ty.strip_prefix("Vec<").and_then(|ty| ty.strip_suffix('>')) {
+        format!("&[{}]", item)
+    } else if types.is_copy(ty) {
+        ty.to_string()
+    } else {
+        format!("&{}", ty)
+    }
+}
+
+/// True if the type can be turned into a string for a parameter. Other
+/// parameters are taken as JSON values.
+fn is_displayed(
+    ty: &str,
+    types: &types::Types,
+) -> bool {
+    let item = ty.strip_prefix("Vec<").and_then(|ty| ty.strip_suffix('>')).unwrap_or(ty);
+
+    item == "String" || item == "::serde_json::Value" || types.is_copy(item)
+}
+
+fn is_json(content_type: &str) -> bool {
+    let content_type = content_type.split(';').next().unwrap_or("").trim();
+
+    content_type == "application/json" || content_type.ends_with("+json")
+}
+
+/// Follows a `$ref` to what it points to, within the document.
+fn resolve<'a>(
+    spec: &'a Value,
+    value: &'a Value,
+    pointer: &str,
+) -> error::Result<&'a Value> {
+    let mut value = value;
+    let mut depth = 0;
+
+    while let Some(reference) = value.get("$ref").and_then(Value::as_str) {
+        let target = reference.strip_prefix('#').and_then(|target| spec.pointer(target));
+        value = target.ok_or_else(|| {
+            error::Error::new_invalid_spec(
+                pointer,
+                format!("reference '{}' is not found", reference),
+            )
+        })?;
+
+        depth += 1;
+        if depth > 32 {
+            return Err(error::Error::new_invalid_spec(pointer, "references go around in a loop"));
+        }
+    }
+
+    Ok(value)
+}
+
+/// The entries of an object, sorted by key.
+fn sorted_entries(value: Option<&Value>) -> Vec<(&String, &Value)> {
+    let mut entries = value.and_then(Value::as_object).into_iter().flatten().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
@@ -0,0 +1,110 @@
+use serde_json;
+use serde_yaml;
+use std::error;
+use std::fmt;
+use std::io;
+use std::path;
+use std::result;
+
+pub type Result<T> = result::Result<T, self::Error>;
+
+/// The errors possible when generating bindings.
+#[derive(Debug)]
+pub enum Error {
+    /// The document could not be read from disk.
+    IoError {
+        path: path::PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// The document is not valid JSON or YAML.
+    ParseError {
+        reason: String,
+    },
+
+    /// The document is not a valid OpenAPI 3 document, or uses something
+    /// which cannot be generated.
+    InvalidSpec {
+        /// The JSON pointer to where in the document the problem is.
+        pointer: String,
+        reason: String,
+    },
+}
+
+impl Error {
+    pub(crate) fn new_io_error(
+        path: &path::Path,
+        error: io::Error,
+    ) -> Self {
+        Error::IoError {
+            path: path.to_path_buf(),
+            error,
+        }
+    }
+
+    pub(crate) fn new_invalid_spec(
+        pointer: &str,
+        reason: impl Into<String>,
+    ) -> Self {
+        Error::InvalidSpec {
+            pointer: pointer.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Error::IoError {
+                path,
+                error,
+            } => write!(f, "Failed to read {}, {}", path.display(), error),
+            Error::ParseError {
+                reason,
+            } => write!(f, "Failed to parse the document, {}", reason),
+            Error::InvalidSpec {
+                pointer,
+                reason,
+            } => write!(f, "Invalid document at '{}', {}", pointer, reason),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::IoError {
+                error,
+                ..
+            } => Some(error),
+            Error::ParseError {
+                ..
+            }
+            | Error::InvalidSpec {
+                ..
+            } => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::ParseError {
+            reason: error.to_string(),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(error: serde_yaml::Error) -> Self {
+        Error::ParseError {
+            reason: error.to_string(),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Generates API bindings for Burgundy from an OpenAPI 3 document.
+//!
+//! Structs and enums are generated for the schemas of the document, and a
+//! client with a method for each operation. Methods take the path, query and
+//! header parameters, and the request body, of their operation. Operations
+//! describing error responses return an error enum with those bodies.
+//!
+//! The output is deterministic, so it can be checked in and diffed.
+
+#![allow(missing_docs)]
+#![warn(unreachable_pub)]
+#![deny(trivial_casts, trivial_numeric_casts, unused_extern_crates, unused_import_braces)]
+
+#[cfg(test)]
+extern crate burgundy;
+#[cfg(test)]
+extern crate serde;
+extern crate serde_json;
+extern crate serde_yaml;
+
+mod client;
+mod error;
+mod names;
+mod types;
+
+pub use error::Error;
+pub use error::Result;
+
+use serde_json::Value;
+use std::fs;
+use std::path;
+
+/// Reads a document from disk. Files ending in `.json` are read as JSON,
+/// and everything else as YAML.
+pub fn load(path: &path::Path) -> Result<Value> {
+    let text = fs::read_to_string(path).map_err(|error| Error::new_io_error(path, error))?;
+    let is_json = path.extension().is_some_and(|extension| extension == "json");
+
+    if is_json {
+        Ok(serde_json::from_str(&text)?)
+    } else {
+        Ok(serde_yaml::from_str(&text)?)
+    }
+}
+
+/// Generates the Rust source of the bindings for a document.
+pub fn generate(spec: &Value) -> Result<String> {
+    let version = spec.get("openapi").and_then(Value::as_str).unwrap_or("");
+    if !version.starts_with("3.") {
+        return Err(Error::new_invalid_spec("/openapi", "only OpenAPI 3 documents are supported"));
+    }
+
+    let mut types = types::Types::new(spec);
+    types.add_components()?;
+    let client = client::generate(spec, &mut types)?;
+
+    let title = spec.pointer("/info/title").and_then(Value::as_str).unwrap_or("an API");
+    let api_version = spec.pointer("/info/version").and_then(Value::as_str).unwrap_or("");
+    let mut code = format!(
+        "// Generated by burgundy-codegen from {} {}. Do not edit by hand.\n\
+         // Needs burgundy, serde (with the derive feature) and serde_json.\n",
+        title, api_version
+    );
+
+    let sections = Some(client.code).into_iter().chain(client.errors).chain(types.into_code());
+    for section in sections {
+        code.push('\n');
+        code.push_str(&section);
+    }
+
+    Ok(code)
+}
+
+/// Reads a document from disk, and generates the bindings for it.
+pub fn generate_file(path: &path::Path) -> Result<String> {
+    generate(&load(path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net;
+    use std::thread;
+
+    #[allow(dead_code, unreachable_pub)]
+    mod petstore {
+        include!("../fixtures/petstore.rs");
+    }
+
+    /// Answers one request with the status and body given. Returns the url
+    /// of the server, and a handle giving the request line it received.
+    fn serve_once(
+        status: u32,
+        body: &'static str,
+    ) -> (String, thread::JoinHandle<String>) {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((key, value)) = line.split_once(':') {
+                    if key.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            reader.read_exact(&mut vec![0; content_length]).unwrap();
+
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            request_line.trim().to_string()
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn generates_petstore() {
+        let path = path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/petstore.yaml"));
+        let code = generate_file(path).unwrap();
+
+        assert_eq!(code, include_str!("../fixtures/petstore.rs"));
+    }
+
+    #[test]
+    fn json_and_yaml_generate_the_same() {
+        let path = path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/petstore.yaml"));
+        let spec = load(path).unwrap();
+        let json = serde_json::to_string(&spec).unwrap();
+
+        assert_eq!(
+            generate(&serde_json::from_str(&json).unwrap()).unwrap(),
+            generate(&spec).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_swagger_2() {
+        let spec = serde_json::json!({ "swagger": "2.0", "paths": {} });
+
+        assert!(generate(&spec).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_operation_ids() {
+        let spec = serde_json::json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Test", "version": "1" },
+            "paths": {
+                "/a": { "get": { "operationId": "list", "responses": {} } },
+                "/b": { "get": { "operationId": "list", "responses": {} } }
+            }
+        });
+
+        match generate(&spec) {
+            Err(Error::InvalidSpec {
+                pointer,
+                ..
+            }) => assert_eq!(pointer, "/paths/~1b/get"),
+            other => panic!("expected an invalid spec, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn generated_client_accepts_any_success_status() {
+        let (url, server) = serve_once(201, r#"{"id":7,"name":"rex","status":"available"}"#);
+        let client = petstore::SwaggerPetstoreClient::new(&url);
+        let new_pet = petstore::NewPet {
+            name: "rex".to_string(),
+            tag: None,
+            r#type: None,
+        };
+
+        let pet = client.create_pet(None, &new_pet).unwrap();
+        assert_eq!(pet.id, 7);
+        assert_eq!(server.join().unwrap(), "POST /pets HTTP/1.1");
+
+        let (url, server) = serve_once(204, "");
+        let client = petstore::SwaggerPetstoreClient::new(&url);
+
+        #[allow(deprecated)]
+        client.delete_pet(7).unwrap();
+        assert_eq!(server.join().unwrap(), "DELETE /pets/7 HTTP/1.1");
+    }
+
+    #[test]
+    fn generated_client_encodes_query_parameters() {
+        let (url, server) = serve_once(200, "[]");
+        let client = petstore::SwaggerPetstoreClient::new(&url);
+        let tags = vec!["a&b".to_string(), "c d".to_string()];
+
+        let pets = client.list_pets(Some(2), Some(&tags), None).unwrap();
+
+        assert!(pets.is_empty());
+        assert_eq!(server.join().unwrap(), "GET /pets?limit=2&tags=a%26b&tags=c%20d HTTP/1.1");
+    }
+}
@@ -0,0 +1,44 @@
+extern crate burgundy_codegen;
+
+use std::env;
+use std::fs;
+use std::path;
+use std::process;
+
+const USAGE: &str = "usage: burgundy-codegen <openapi.yaml|openapi.json> [-o <output.rs>]";
+
+fn main() {
+    let mut input = None;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = args.next(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if input.is_none() => input = Some(arg),
+            _ => exit_with(USAGE),
+        }
+    }
+
+    let input = input.unwrap_or_else(|| exit_with(USAGE));
+    let code = burgundy_codegen::generate_file(path::Path::new(&input))
+        .unwrap_or_else(|error| exit_with(&error.to_string()));
+
+    match output {
+        Some(output) => {
+            fs::write(&output, code).unwrap_or_else(|error| {
+                exit_with(&format!("Failed to write {}, {}", output, error))
+            });
+        },
+        None => print!("{}", code),
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
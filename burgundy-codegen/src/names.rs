@@ -0,0 +1,124 @@
+/// Words which cannot be used as identifiers without being escaped.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Keywords which cannot be escaped as raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+/// Splits a name into lowercase words, such as `petId`, `pet-id` and
+/// `PetID` all into `pet` and `id`.
+fn words(name: &str) -> Vec<String> {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(word.to_lowercase());
+                word.clear();
+            }
+            continue;
+        }
+
+        let previous = if i > 0 {
+            Some(chars[i - 1])
+        } else {
+            None
+        };
+        let next = chars.get(i + 1).cloned();
+        let is_boundary = c.is_ascii_uppercase()
+            && previous.is_some_and(|previous| {
+                previous.is_ascii_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_ascii_uppercase()
+                        && next.is_some_and(|next| next.is_ascii_lowercase()))
+            });
+        if is_boundary && !word.is_empty() {
+            words.push(word.to_lowercase());
+            word.clear();
+        }
+
+        word.push(c);
+    }
+
+    if !word.is_empty() {
+        words.push(word.to_lowercase());
+    }
+
+    words
+}
+
+/// The name as a field, argument or method, such as `pet_id`.
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut snake = words(name).join("_");
+    if snake.is_empty() {
+        snake.push_str("value");
+    }
+    if snake.starts_with(|c: char| c.is_ascii_digit()) {
+        snake.insert(0, '_');
+    }
+
+    escape(snake)
+}
+
+/// The name as a type or variant, such as `PetId`.
+pub(crate) fn pascal_case(name: &str) -> String {
+    let mut pascal = words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    if pascal.is_empty() {
+        pascal.push_str("Value");
+    }
+    if pascal.starts_with(|c: char| c.is_ascii_digit()) {
+        pascal.insert(0, 'V');
+    }
+
+    escape(pascal)
+}
+
+/// Escapes keywords, such as `type` into `r#type`.
+fn escape(name: String) -> String {
+    if RESERVED.contains(&name.as_str()) {
+        name + "_"
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+/// The name without any raw identifier prefix, as serde sees it.
+pub(crate) fn unescaped(name: &str) -> &str {
+    name.trim_start_matches("r#")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_case() {
+        assert_eq!(snake_case("petId"), "pet_id");
+        assert_eq!(snake_case("X-Request-ID"), "x_request_id");
+        assert_eq!(snake_case("HTTPResponse"), "http_response");
+        assert_eq!(snake_case("type"), "r#type");
+        assert_eq!(snake_case("self"), "self_");
+        assert_eq!(snake_case("2fa"), "_2fa");
+        assert_eq!(pascal_case("pet_store"), "PetStore");
+        assert_eq!(pascal_case("Swagger Petstore"), "SwaggerPetstore");
+        assert_eq!(pascal_case("4XX"), "V4Xx");
+    }
+}
@@ -0,0 +1,442 @@
+use error;
+use names;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Types which are cheap to copy, and so are passed by value.
+const COPY_TYPES: &[&str] = &["bool", "i32", "i64", "f32", "f64"];
+
+/// What a schema which is left out is read as, such as the `items` of an
+/// array which does not give them.
+static NO_SCHEMA: Value = Value::Null;
+
+/// The schemas of the properties of an object, with the pointers to them,
+/// by property name.
+type Properties<'a> = BTreeMap<String, (&'a Value, String)>;
+
+/// The Rust types generated for the schemas of a document.
+///
+/// Types are kept sorted by name, so the output is the same every time.
+#[derive(Debug)]
+pub(crate) struct Types<'a> {
+    spec: &'a Value,
+    definitions: BTreeMap<String, String>,
+
+    /// Names which are taken, including by schemas yet to be defined.
+    taken: BTreeSet<String>,
+
+    /// String enums, which are passed by value.
+    enums: BTreeSet<String>,
+}
+
+impl<'a> Types<'a> {
+    pub(crate) fn new(spec: &'a Value) -> Self {
+        let taken = component_schemas(spec).map(|(name, _)| names::pascal_case(name)).collect();
+
+        Self {
+            spec,
+            definitions: BTreeMap::new(),
+            taken,
+            enums: BTreeSet::new(),
+        }
+    }
+
+    /// Defines a type for every schema under `components/schemas`.
+    pub(crate) fn add_components(&mut self) -> error::Result<()> {
+        for (name, schema) in component_schemas(self.spec) {
+            let pointer = format!("/components/schemas/{}", escape_pointer(name));
+            let type_name = names::pascal_case(name);
+            let ty = self.define(&type_name, schema, &pointer, true)?;
+
+            if ty != type_name {
+                let mut code = String::new();
+                write_doc(&mut code, schema.get("description"), "");
+                writeln!(code, "pub type {} = {};", type_name, ty).unwrap();
+                self.definitions.insert(type_name, code);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The Rust type for a schema. Objects and string enums found inline
+    /// are defined, and named after `hint`.
+    pub(crate) fn rust_type(
+        &mut self,
+        schema: &'a Value,
+        hint: &str,
+        pointer: &str,
+    ) -> error::Result<String> {
+        self.define(hint, schema, pointer, false)
+    }
+
+    /// True if values of the type are passed by value, rather than by
+    /// reference.
+    pub(crate) fn is_copy(
+        &self,
+        ty: &str,
+    ) -> bool {
+        COPY_TYPES.contains(&ty) || self.enums.contains(ty)
+    }
+
+    /// The code of every type defined, sorted by name.
+    pub(crate) fn into_code(self) -> Vec<String> {
+        self.definitions.into_values().collect()
+    }
+
+    fn define(
+        &mut self,
+        name: &str,
+        schema: &'a Value,
+        pointer: &str,
+        is_component: bool,
+    ) -> error::Result<String> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return self.reference_type(reference, pointer);
+        }
+
+        let (ty, is_nullable) = schema_type(schema);
+        let rust_type = if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+            if parts.len() == 1 {
+                self.define(name, &parts[0], &format!("{}/allOf/0", pointer), is_component)?
+            } else {
+                self.define_struct(name, schema, pointer, is_component)?
+            }
+        } else if schema.get("oneOf").is_some() || schema.get("anyOf").is_some() {
+            "::serde_json::Value".to_string()
+        } else {
+            match ty {
+                Some("string") if schema.get("enum").is_some() => {
+                    self.define_enum(name, schema, pointer, is_component)?
+                },
+                Some("string") => "String".to_string(),
+                Some("integer") if format_of(schema) == Some("int32") => "i32".to_string(),
+                Some("integer") => "i64".to_string(),
+                Some("number") if format_of(schema) == Some("float") => "f32".to_string(),
+                Some("number") => "f64".to_string(),
+                Some("boolean") => "bool".to_string(),
+                Some("array") => {
+                    let items = schema.get("items").unwrap_or(&NO_SCHEMA);
+                    let item_type = self.rust_type(
+                        items,
+                        &format!("{}Item", name),
+                        &format!("{}/items", pointer),
+                    )?;
+
+                    format!("Vec<{}>", item_type)
+                },
+                Some("object") | None if has_properties(schema) => {
+                    self.define_struct(name, schema, pointer, is_component)?
+                },
+                Some("object") | None => match schema.get("additionalProperties") {
+                    Some(values) if values.is_object() => {
+                        let value_type = self.rust_type(
+                            values,
+                            &format!("{}Value", name),
+                            &format!("{}/additionalProperties", pointer),
+                        )?;
+
+                        format!("::std::collections::BTreeMap<String, {}>", value_type)
+                    },
+                    _ => "::serde_json::Value".to_string(),
+                },
+                Some(_) => "::serde_json::Value".to_string(),
+            }
+        };
+
+        if is_nullable && !rust_type.starts_with("Option<") {
+            Ok(format!("Option<{}>", rust_type))
+        } else {
+            Ok(rust_type)
+        }
+    }
+
+    /// The type a `$ref` points to. Component schemas are referred to by
+    /// name, and anything else is looked up and defined in place.
+    fn reference_type(
+        &mut self,
+        reference: &str,
+        pointer: &str,
+    ) -> error::Result<String> {
+        let target = reference.strip_prefix('#').ok_or_else(|| {
+            error::Error::new_invalid_spec(
+                pointer,
+                "only references within the document are supported",
+            )
+        })?;
+
+        if let Some(name) = target.strip_prefix("/components/schemas/") {
+            if !name.contains('/') {
+                return Ok(names::pascal_case(&unescape_pointer(name)));
+            }
+        }
+
+        let schema = self.spec.pointer(target).ok_or_else(|| {
+            error::Error::new_invalid_spec(
+                pointer,
+                format!("reference '{}' is not found", reference),
+            )
+        })?;
+        let hint = names::pascal_case(&unescape_pointer(target.rsplit('/').next().unwrap_or("")));
+
+        self.rust_type(schema, &hint, target)
+    }
+
+    fn define_struct(
+        &mut self,
+        name: &str,
+        schema: &'a Value,
+        pointer: &str,
+        is_component: bool,
+    ) -> error::Result<String> {
+        let name = self.claim(name, is_component);
+        let (properties, required) = self.properties(schema, pointer)?;
+
+        let mut code = String::new();
+        write_doc(&mut code, schema.get("description"), "");
+        writeln!(
+            code,
+            "#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]"
+        )
+        .unwrap();
+        writeln!(code, "pub struct {} {{", name).unwrap();
+
+        for (property, (property_schema, property_pointer)) in &properties {
+            let field = names::snake_case(property);
+            let hint = format!("{}{}", name, names::pascal_case(property));
+            let mut ty = self.rust_type(property_schema, &hint, property_pointer)?;
+            if ty == name || ty == format!("Option<{}>", name) {
+                ty = ty.replace(&name, &format!("Box<{}>", name));
+            }
+
+            let is_optional = !required.contains(property.as_str()) || ty.starts_with("Option<");
+            if is_optional && !ty.starts_with("Option<") {
+                ty = format!("Option<{}>", ty);
+            }
+
+            write_doc(&mut code, property_schema.get("description"), "    ");
+            if names::unescaped(&field) != property {
+                writeln!(code, "    #[serde(rename = {:?})]", property).unwrap();
+            }
+            if is_optional {
+                writeln!(code, "    #[serde(default, skip_serializing_if = \"Option::is_none\")]")
+                    .unwrap();
+            }
+            writeln!(code, "    pub {}: {},", field, ty).unwrap();
+        }
+
+        writeln!(code, "}}").unwrap();
+        self.definitions.insert(name.clone(), code);
+
+        Ok(name)
+    }
+
+    /// The properties of an object, and which are required. For `allOf`
+    /// the properties of every part are merged.
+    fn properties(
+        &self,
+        schema: &'a Value,
+        pointer: &str,
+    ) -> error::Result<(Properties<'a>, BTreeSet<&'a str>)> {
+        let mut properties = BTreeMap::new();
+        let mut required = BTreeSet::new();
+        let mut parts = vec![(schema, pointer.to_string())];
+
+        while let Some((part, part_pointer)) = parts.pop() {
+            if let Some(reference) = part.get("$ref").and_then(Value::as_str) {
+                let target =
+                    reference.strip_prefix('#').and_then(|target| self.spec.pointer(target));
+                let target = target.ok_or_else(|| {
+                    error::Error::new_invalid_spec(
+                        &part_pointer,
+                        format!("reference '{}' is not found", reference),
+                    )
+                })?;
+                parts.push((target, reference[1..].to_string()));
+                continue;
+            }
+
+            if let Some(all_of) = part.get("allOf").and_then(Value::as_array) {
+                for (i, item) in all_of.iter().enumerate().rev() {
+                    parts.push((item, format!("{}/allOf/{}", part_pointer, i)));
+                }
+            }
+
+            if let Some(part_properties) = part.get("properties").and_then(Value::as_object) {
+                for (property, property_schema) in part_properties {
+                    let property_pointer =
+                        format!("{}/properties/{}", part_pointer, escape_pointer(property));
+                    properties.insert(property.clone(), (property_schema, property_pointer));
+                }
+            }
+
+            if let Some(part_required) = part.get("required").and_then(Value::as_array) {
+                required.extend(part_required.iter().filter_map(Value::as_str));
+            }
+        }
+
+        Ok((properties, required))
+    }
+
+    fn define_enum(
+        &mut self,
+        name: &str,
+        schema: &Value,
+        pointer: &str,
+        is_component: bool,
+    ) -> error::Result<String> {
+        let values = schema.get("enum").and_then(Value::as_array).ok_or_else(|| {
+            error::Error::new_invalid_spec(pointer, "`enum` must be a list of values")
+        })?;
+        let values = values.iter().filter_map(Value::as_str).collect::<Vec<_>>();
+        if values.is_empty() {
+            return Ok("String".to_string());
+        }
+
+        let name = self.claim(name, is_component);
+        let mut variants = Vec::<(String, &str)>::new();
+        for value in values {
+            let mut variant = names::pascal_case(value);
+            let mut i = 2;
+            while variants.iter().any(|(existing, _)| *existing == variant) {
+                variant = format!("{}{}", names::pascal_case(value), i);
+                i += 1;
+            }
+            variants.push((variant, value));
+        }
+
+        let mut code = String::new();
+        write_doc(&mut code, schema.get("description"), "");
+        writeln!(
+            code,
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::serde::Serialize, ::serde::Deserialize)]"
+        )
+        .unwrap();
+        writeln!(code, "pub enum {} {{", name).unwrap();
+        for (variant, value) in &variants {
+            writeln!(code, "    #[serde(rename = {:?})]", value).unwrap();
+            writeln!(code, "    {},", variant).unwrap();
+        }
+        writeln!(code, "}}").unwrap();
+        writeln!(code).unwrap();
+        writeln!(code, "impl ::std::fmt::Display for {} {{", name).unwrap();
+        writeln!(code, "    fn fmt(").unwrap();
+        writeln!(code, "        &self,").unwrap();
+        writeln!(code, "        f: &mut ::std::fmt::Formatter,").unwrap();
+        writeln!(code, "    ) -> ::std::fmt::Result {{").unwrap();
+        writeln!(code, "        let value = match self {{").unwrap();
+        for (variant, value) in &variants {
+            writeln!(code, "            {}::{} => {:?},", name, variant, value).unwrap();
+        }
+        writeln!(code, "        }};").unwrap();
+        writeln!(code).unwrap();
+        writeln!(code, "        f.write_str(value)").unwrap();
+        writeln!(code, "    }}").unwrap();
+        writeln!(code, "}}").unwrap();
+
+        self.enums.insert(name.clone());
+        self.definitions.insert(name.clone(), code);
+
+        Ok(name)
+    }
+
+    /// Takes the name for a new type. Types found inline get a number added
+    /// when their name is already taken.
+    fn claim(
+        &mut self,
+        name: &str,
+        is_component: bool,
+    ) -> String {
+        if is_component {
+            return name.to_string();
+        }
+
+        let mut claimed = name.to_string();
+        let mut i = 2;
+        while self.taken.contains(&claimed) {
+            claimed = format!("{}{}", name, i);
+            i += 1;
+        }
+
+        self.taken.insert(claimed.clone());
+        claimed
+    }
+}
+
+/// The schemas under `components/schemas`, sorted by name.
+fn component_schemas(spec: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    let schemas = spec.pointer("/components/schemas").and_then(Value::as_object);
+    let mut schemas = schemas.into_iter().flatten().collect::<Vec<_>>();
+    schemas.sort_by(|a, b| a.0.cmp(b.0));
+
+    schemas.into_iter()
+}
+
+/// The `type` of a schema, and if it can be null. In OpenAPI 3.1 the type
+/// can be a list, such as `["string", "null"]`.
+fn schema_type(schema: &Value) -> (Option<&str>, bool) {
+    let is_nullable = schema.get("nullable") == Some(&Value::Bool(true));
+
+    match schema.get("type") {
+        Some(Value::String(ty)) => (Some(ty.as_str()), is_nullable),
+        Some(Value::Array(types)) => {
+            let ty = types.iter().filter_map(Value::as_str).find(|ty| *ty != "null");
+            let has_null = types.iter().any(|ty| ty == "null");
+
+            (ty, is_nullable || has_null)
+        },
+        _ => (None, is_nullable),
+    }
+}
+
+fn format_of(schema: &Value) -> Option<&str> {
+    schema.get("format").and_then(Value::as_str)
+}
+
+fn has_properties(schema: &Value) -> bool {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| !properties.is_empty())
+}
+
+/// Writes a description as a doc comment. Lines are trimmed, so they are
+/// not read as code blocks, and fenced code is marked as text so it is not
+/// run as a doc test.
+pub(crate) fn write_doc(
+    code: &mut String,
+    description: Option<&Value>,
+    indent: &str,
+) {
+    let description = match description.and_then(Value::as_str).map(str::trim) {
+        Some(description) if !description.is_empty() => description,
+        _ => return,
+    };
+
+    let mut is_in_fence = false;
+    for line in description.lines().map(str::trim) {
+        if line.starts_with("```") {
+            let fence = if is_in_fence {
+                "```"
+            } else {
+                "```text"
+            };
+            writeln!(code, "{}/// {}", indent, fence).unwrap();
+            is_in_fence = !is_in_fence;
+        } else if line.is_empty() {
+            writeln!(code, "{}///", indent).unwrap();
+        } else {
+            writeln!(code, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape_pointer(key: &str) -> String {
+    key.replace("~1", "/").replace("~0", "~")
+}
@@ -0,0 +1,422 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn;
+use syn::spanned::Spanned;
+
+/// The attributes which mark a method as an endpoint, and the `Domain`
+/// method each one calls.
+const HTTP_METHODS: &[&str] = &["get", "post", "put", "delete", "patch", "head", "options"];
+
+/// A method of the trait, which sends a request.
+pub(crate) struct Endpoint {
+    signature: syn::Signature,
+    http_method: syn::Ident,
+    template: syn::LitStr,
+    arguments: Vec<Argument>,
+    response: Response,
+}
+
+/// How an argument of the method is added to the request.
+struct Argument {
+    name: syn::Ident,
+    kind: ArgumentKind,
+    is_option: bool,
+}
+
+enum ArgumentKind {
+    Path,
+    Query(String),
+    Header(syn::LitStr),
+    Body,
+}
+
+/// What the response body is read as, taken from the `Ok` type of the
+/// `Result` returned.
+enum Response {
+    Json(Box<syn::Type>),
+    String,
+    Unit,
+}
+
+impl Endpoint {
+    /// Reads the endpoint from the method, and strips the attributes used
+    /// from it. Methods which are not endpoints, and have a default body,
+    /// return `None`.
+    pub(crate) fn parse(method: &mut syn::TraitItemFn) -> syn::Result<Option<Self>> {
+        let mut http_attrs = Vec::new();
+        method.attrs.retain(|attr| {
+            let is_http = HTTP_METHODS.iter().any(|name| attr.path().is_ident(name));
+            if is_http {
+                http_attrs.push(attr.clone());
+            }
+
+            !is_http
+        });
+
+        let attr = match (http_attrs.pop(), http_attrs.is_empty()) {
+            (Some(attr), true) => attr,
+            (Some(attr), false) => {
+                return Err(syn::Error::new_spanned(attr, "only one HTTP method can be given"))
+            },
+            (None, _) if method.default.is_some() => return Ok(None),
+            (None, _) => {
+                return Err(syn::Error::new_spanned(
+                    &method.sig,
+                    "endpoints need an HTTP method, such as `#[get(\"/path\")]`",
+                ))
+            },
+        };
+
+        let http_method = attr.path().get_ident().cloned().expect("checked to be an HTTP method");
+        let template = attr.parse_args::<syn::LitStr>()?;
+        if method.default.is_some() {
+            return Err(syn::Error::new_spanned(&method.sig, "endpoints cannot have a body"));
+        }
+
+        match method.sig.receiver() {
+            Some(receiver) if receiver.reference.is_some() && receiver.mutability.is_none() => {},
+            _ => return Err(syn::Error::new_spanned(&method.sig, "endpoints must take `&self`")),
+        }
+
+        let variables = template_variables(&template.value());
+        let mut arguments = Vec::new();
+        for input in &mut method.sig.inputs {
+            if let syn::FnArg::Typed(input) = input {
+                arguments.push(Argument::parse(input, &variables)?);
+            }
+        }
+
+        for variable in &variables {
+            if !arguments.iter().any(|argument| argument.name == variable) {
+                return Err(syn::Error::new_spanned(
+                    &template,
+                    format!("no argument named `{}`", variable),
+                ));
+            }
+        }
+
+        if arguments.iter().filter(|argument| matches!(argument.kind, ArgumentKind::Body)).count()
+            > 1
+        {
+            return Err(syn::Error::new_spanned(&method.sig, "only one argument can be the body"));
+        }
+
+        let response = Response::parse(&method.sig.output)?;
+
+        Ok(Some(Self {
+            signature: method.sig.clone(),
+            http_method,
+            template,
+            arguments,
+            response,
+        }))
+    }
+
+    /// The method, as implemented by the client.
+    pub(crate) fn to_tokens(&self) -> TokenStream {
+        let signature = &self.signature;
+        let http_method = &self.http_method;
+        let template = &self.template;
+
+        let variables =
+            self.arguments.iter().filter(|argument| argument.is_path()).map(|argument| {
+                let name = &argument.name;
+                let key = name.to_string();
+
+                quote! { (#key, ::std::string::ToString::to_string(&#name)) }
+            });
+
+        let additions = self.arguments.iter().filter_map(Argument::to_tokens);
+
+        let body =
+            self.arguments.iter().find(|argument| matches!(argument.kind, ArgumentKind::Body));
+        let body = match body {
+            Some(argument) => {
+                let name = &argument.name;
+                quote! { ::std::option::Option::Some(&#name) }
+            },
+            None => quote! { ::std::option::Option::None::<&()> },
+        };
+
+        let execute = match &self.response {
+            Response::Json(ty) => quote! { path.execute_as_json::<_, #ty>(#body) },
+            Response::String => quote! { path.execute_as_string(#body) },
+            Response::Unit => quote! { path.execute_as_string(#body).map(|_| ()) },
+        };
+
+        quote! {
+            #signature {
+                let variables: ::std::collections::BTreeMap<&str, ::std::string::String> =
+                    ::std::vec![#(#variables),*].into_iter().collect();
+
+                #[allow(unused_mut)]
+                let mut path = self.domain.#http_method().expand(#template, &variables)?;
+                #(#additions)*
+
+                #execute.map_err(::std::convert::From::from)
+            }
+        }
+    }
+}
+
+impl Argument {
+    fn parse(
+        input: &mut syn::PatType,
+        variables: &[String],
+    ) -> syn::Result<Self> {
+        let name = match &*input.pat {
+            syn::Pat::Ident(pat) => pat.ident.clone(),
+            pat => return Err(syn::Error::new_spanned(pat, "arguments must be named")),
+        };
+
+        let mut kinds = Vec::new();
+        let mut error = None;
+        input.attrs.retain(|attr| match ArgumentKind::parse(attr, &name) {
+            Ok(Some(kind)) => {
+                kinds.push(kind);
+                false
+            },
+            Ok(None) => true,
+            Err(err) => {
+                error.get_or_insert(err);
+                false
+            },
+        });
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        let is_variable = variables.iter().any(|variable| name == variable);
+        let kind = match (kinds.pop(), kinds.is_empty(), is_variable) {
+            (Some(_), false, _) => {
+                return Err(syn::Error::new(input.span(), "arguments can only be used once"))
+            },
+            (Some(_), true, true) => {
+                return Err(syn::Error::new(input.span(), "arguments in the path cannot be marked"))
+            },
+            (Some(kind), true, false) => kind,
+            (None, _, true) => ArgumentKind::Path,
+            (None, _, false) => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    format!("`{}` is not in the path, mark it as a query, header or body", name),
+                ))
+            },
+        };
+
+        Ok(Self {
+            name,
+            kind,
+            is_option: is_option(&input.ty),
+        })
+    }
+
+    fn is_path(&self) -> bool {
+        matches!(self.kind, ArgumentKind::Path)
+    }
+
+    /// Adds the argument to the `path`, if it is a query or header.
+    /// Arguments which are an `Option` are only added when they are `Some`.
+    fn to_tokens(&self) -> Option<TokenStream> {
+        let name = &self.name;
+        let value = if self.is_option {
+            quote! { value }
+        } else {
+            quote! { &#name }
+        };
+
+        let addition = match &self.kind {
+            ArgumentKind::Query(key) => quote! { path = path.query(#key, #value); },
+            ArgumentKind::Header(key) => quote! { path.header(#key, #value); },
+            ArgumentKind::Path | ArgumentKind::Body => return None,
+        };
+
+        if self.is_option {
+            Some(quote! {
+                if let ::std::option::Option::Some(value) = &#name {
+                    #addition
+                }
+            })
+        } else {
+            Some(addition)
+        }
+    }
+}
+
+impl ArgumentKind {
+    fn parse(
+        attr: &syn::Attribute,
+        name: &syn::Ident,
+    ) -> syn::Result<Option<Self>> {
+        if attr.path().is_ident("query") {
+            let key = match &attr.meta {
+                syn::Meta::Path(_) => name.to_string(),
+                _ => attr.parse_args::<syn::LitStr>()?.value(),
+            };
+
+            Ok(Some(ArgumentKind::Query(key)))
+        } else if attr.path().is_ident("header") {
+            Ok(Some(ArgumentKind::Header(attr.parse_args()?)))
+        } else if attr.path().is_ident("body") {
+            attr.meta.require_path_only()?;
+
+            Ok(Some(ArgumentKind::Body))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Response {
+    /// Reads `T` from a return type of `Result<T, E>`.
+    fn parse(output: &syn::ReturnType) -> syn::Result<Self> {
+        let error = || {
+            syn::Error::new_spanned(
+                output,
+                "endpoints must return a `Result`, with an error which is `From<burgundy::Error>`",
+            )
+        };
+
+        let ty = match output {
+            syn::ReturnType::Type(_, ty) => ty,
+            syn::ReturnType::Default => return Err(error()),
+        };
+        let segment = match &**ty {
+            syn::Type::Path(ty) => ty.path.segments.last().ok_or_else(error)?,
+            _ => return Err(error()),
+        };
+        if segment.ident != "Result" {
+            return Err(error());
+        }
+
+        let ok = match &segment.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => arguments.args.first(),
+            _ => None,
+        };
+        match ok {
+            Some(syn::GenericArgument::Type(syn::Type::Tuple(tuple))) if tuple.elems.is_empty() => {
+                Ok(Response::Unit)
+            },
+            Some(syn::GenericArgument::Type(syn::Type::Path(ty))) if ty.path.is_ident("String") => {
+                Ok(Response::String)
+            },
+            Some(syn::GenericArgument::Type(ty)) => Ok(Response::Json(Box::new(ty.clone()))),
+            _ => Err(error()),
+        }
+    }
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(ty) => {
+            ty.path.segments.last().is_some_and(|segment| segment.ident == "Option")
+        },
+        _ => false,
+    }
+}
+
+/// The names of the variables in a URI template, such as `org` in
+/// `/orgs/{org}/repos`.
+fn template_variables(template: &str) -> Vec<String> {
+    let mut variables = Vec::new();
+
+    for expression in template.split('{').skip(1) {
+        let expression = expression.split('}').next().unwrap_or("");
+        let expression = expression.trim_start_matches(['+', '#', '.', '/', ';', '?', '&']);
+
+        for variable in expression.split(',') {
+            let name = variable.trim_end_matches('*').split(':').next().unwrap_or("");
+            if !name.is_empty() && !variables.iter().any(|variable| variable == name) {
+                variables.push(name.to_string());
+            }
+        }
+    }
+
+    variables
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(method: syn::TraitItemFn) -> syn::Result<Endpoint> {
+        let mut method = method;
+        Endpoint::parse(&mut method).map(|endpoint| endpoint.unwrap())
+    }
+
+    #[test]
+    fn finds_template_variables() {
+        let variables = template_variables("/repos/{owner}/{repo}/issues{?state,labels*}{/page:3}");
+
+        assert_eq!(variables, vec!["owner", "repo", "state", "labels", "page"]);
+    }
+
+    #[test]
+    fn parses_arguments_and_strips_attributes() {
+        let mut method: syn::TraitItemFn = syn::parse_quote! {
+            #[get("/orgs/{org}/repos")]
+            fn repos(
+                &self,
+                org: &str,
+                #[query("per_page")] limit: Option<u32>,
+                #[header("X-Request-Id")] request_id: &str,
+            ) -> Result<Vec<Repo>, burgundy::Error>;
+        };
+
+        let endpoint = Endpoint::parse(&mut method).unwrap().unwrap();
+        let kinds = endpoint.arguments.iter().map(|argument| &argument.kind).collect::<Vec<_>>();
+
+        assert_eq!(endpoint.http_method, "get");
+        assert!(matches!(kinds[0], ArgumentKind::Path));
+        assert!(matches!(kinds[1], ArgumentKind::Query(key) if key == "per_page"));
+        assert!(matches!(kinds[2], ArgumentKind::Header(key) if key.value() == "X-Request-Id"));
+        assert!(endpoint.arguments[1].is_option);
+        assert!(matches!(endpoint.response, Response::Json(_)));
+        assert!(method.attrs.is_empty());
+        assert!(endpoint.signature.inputs.iter().all(|input| match input {
+            syn::FnArg::Typed(input) => input.attrs.is_empty(),
+            syn::FnArg::Receiver(_) => true,
+        }));
+    }
+
+    #[test]
+    fn methods_with_a_default_are_left_alone() {
+        let mut method: syn::TraitItemFn = syn::parse_quote! {
+            fn name(&self) -> &str {
+                "github"
+            }
+        };
+
+        assert!(Endpoint::parse(&mut method).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_arguments_not_in_the_path() {
+        let result = parse(syn::parse_quote! {
+            #[post("/issues")]
+            fn create(&self, issue: &Issue) -> Result<(), burgundy::Error>;
+        });
+
+        assert!(result.err().unwrap().to_string().contains("`issue` is not in the path"));
+    }
+
+    #[test]
+    fn rejects_missing_path_arguments() {
+        let result = parse(syn::parse_quote! {
+            #[get("/repos/{owner}/{repo}")]
+            fn repo(&self, owner: &str) -> Result<String, burgundy::Error>;
+        });
+
+        assert!(result.err().unwrap().to_string().contains("no argument named `repo`"));
+    }
+
+    #[test]
+    fn rejects_results_which_are_not_results() {
+        let result = parse(syn::parse_quote! {
+            #[delete("/repos/{owner}")]
+            fn delete(&self, owner: &str) -> Option<String>;
+        });
+
+        assert!(result.is_err());
+    }
+}
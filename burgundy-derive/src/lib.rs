@@ -0,0 +1,96 @@
+//! Generates API bindings for Burgundy from an annotated trait.
+//!
+//! Each method of the trait is annotated with its HTTP method and a URI
+//! template, such as `#[get("/orgs/{org}/repos")]`. Arguments named in the
+//! template fill it in. Other arguments are marked as `#[query]`,
+//! `#[header("Name")]`, or `#[body]`.
+//!
+//! A `{Trait}Client` struct is generated, which wraps a `burgundy::Domain`
+//! and implements the trait.
+
+#![allow(missing_docs)]
+#![warn(unreachable_pub)]
+#![deny(trivial_casts, trivial_numeric_casts, unused_extern_crates, unused_import_braces)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+mod endpoint;
+
+use proc_macro::TokenStream;
+use quote::format_ident;
+use quote::quote;
+
+/// Generates a client for the trait it annotates.
+#[proc_macro_attribute]
+pub fn api(
+    attr: TokenStream,
+    item: TokenStream,
+) -> TokenStream {
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return syn::Error::new_spanned(attr, "`api` does not take any arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let item_trait = syn::parse_macro_input!(item as syn::ItemTrait);
+    match expand(item_trait) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(mut item_trait: syn::ItemTrait) -> syn::Result<proc_macro2::TokenStream> {
+    if !item_trait.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_trait.generics,
+            "`api` does not support generic traits",
+        ));
+    }
+
+    let mut endpoints = Vec::new();
+    for item in &mut item_trait.items {
+        if let syn::TraitItem::Fn(method) = item {
+            if let Some(endpoint) = endpoint::Endpoint::parse(method)? {
+                endpoints.push(endpoint);
+            }
+        }
+    }
+
+    let vis = &item_trait.vis;
+    let trait_name = &item_trait.ident;
+    let client_name = format_ident!("{}Client", trait_name);
+    let client_doc =
+        format!("Sends the requests of `{}` through a `burgundy::Domain`.", trait_name);
+    let methods = endpoints.iter().map(endpoint::Endpoint::to_tokens);
+
+    Ok(quote! {
+        #item_trait
+
+        #[doc = #client_doc]
+        #[derive(Debug)]
+        #vis struct #client_name {
+            domain: ::burgundy::Domain,
+        }
+
+        impl #client_name {
+            #vis fn new(domain: ::burgundy::Domain) -> Self {
+                Self {
+                    domain,
+                }
+            }
+
+            /// The domain requests are sent through.
+            #vis fn domain(&self) -> &::burgundy::Domain {
+                &self.domain
+            }
+        }
+
+        impl #trait_name for #client_name {
+            #(#methods)*
+        }
+    })
+}
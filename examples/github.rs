@@ -11,7 +11,7 @@ impl Github {
     pub fn new() -> Self {
         let mut domain = burgundy::Domain::new("https://api.github.com");
         domain.header(
-            &"User-Agent",
+            "User-Agent",
             &format!("github burgundy example / {}", env!("CARGO_PKG_VERSION")),
         );
 
@@ -27,6 +27,12 @@ impl Github {
     }
 }
 
+impl Default for Github {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct GithubGet {
     url: burgundy::Path,
 }
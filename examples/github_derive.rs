@@ -0,0 +1,63 @@
+//! The Github example, with the bindings generated by `burgundy::api`.
+//!
+//! Run with `cargo run --example github_derive --features derive`.
+
+extern crate burgundy;
+#[macro_use]
+extern crate serde_derive;
+
+use burgundy::api;
+
+#[derive(Debug, Deserialize)]
+pub struct Repo {
+    pub name: String,
+    pub stargazers_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewIssue {
+    pub title: String,
+    pub body: String,
+}
+
+#[api]
+pub trait Github {
+    #[get("/orgs/{org}/repos")]
+    fn org_repos(
+        &self,
+        org: &str,
+        #[query] per_page: Option<u32>,
+    ) -> Result<Vec<Repo>, burgundy::Error>;
+
+    #[get("/repos/{owner}/{repo}/readme")]
+    fn readme(
+        &self,
+        owner: &str,
+        repo: &str,
+        #[header("Accept")] accept: &str,
+    ) -> Result<String, burgundy::Error>;
+
+    #[post("/repos/{owner}/{repo}/issues")]
+    fn create_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        #[body] issue: &NewIssue,
+    ) -> Result<(), burgundy::Error>;
+}
+
+fn main() -> Result<(), burgundy::Error> {
+    let mut domain = burgundy::Domain::new("https://api.github.com");
+    domain
+        .header("User-Agent", &format!("github burgundy example / {}", env!("CARGO_PKG_VERSION")));
+    let github = GithubClient::new(domain);
+
+    for repo in github.org_repos("Microsoft", Some(10))? {
+        println!("{} ({} stars)", repo.name, repo.stargazers_count);
+    }
+
+    let readme = github.readme("Microsoft", "vscode", "application/vnd.github.raw")?;
+    println!("{}", readme);
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use crate::error;
+use crate::secret;
+
+/// Supplies the credentials for requests, such as tokens which expire and
+/// need to be fetched again.
+///
+/// This is set on a `Domain` using `Domain::auth_provider`, and is asked
+/// for the `Authorization` header before each request is sent.
+pub trait AuthProvider {
+    /// The value for the `Authorization` header, such as `Bearer abc123`.
+    /// Returning `None` sends no header.
+    fn authorization(&mut self) -> Result<Option<secret::Secret>, error::Error>;
+
+    /// Called when a request comes back as `401 Unauthorized`.
+    ///
+    /// Returns true if the credentials have been refreshed, and the request
+    /// should be tried again. This is only called when retrying has been
+    /// turned on with `Domain::retry_unauthorized`.
+    fn refresh(&mut self) -> Result<bool, error::Error> {
+        Ok(false)
+    }
+}
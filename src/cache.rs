@@ -0,0 +1,695 @@
+//! Caching of `GET` responses, following the HTTP caching rules.
+//!
+//! A cache is added to a `Domain` using `Domain::cache`, with a store to
+//! keep the responses in. This is either a `MemoryCache`, or a `DiskCache`
+//! which lasts between runs.
+//!
+//! Responses are kept for as long as `Cache-Control: max-age` or `Expires`
+//! allows, and `Vary` is honoured. Once they are stale they are
+//! revalidated using `If-None-Match` or `If-Modified-Since`, and a
+//! `304 Not Modified` hands back the cached body as though it were sent
+//! again.
+//!
+//! Streamed requests, such as `Path::download_to`, are served from the
+//! cache and revalidated, but their responses are not stored, as the body
+//! is never read in full.
+
+use crate::error;
+use crate::method;
+use crate::middleware;
+use crate::request;
+use crate::response;
+use crate::response_headers;
+use base64;
+use hex;
+use httpdate;
+use serde_json;
+use sha2;
+use sha2::Digest;
+use std::cell;
+use std::collections;
+use std::fs;
+use std::io;
+use std::path;
+use std::time;
+
+/// A response kept in a cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub status: u32,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+
+    /// When the response was received, or last revalidated.
+    pub stored_at: time::SystemTime,
+
+    /// The values the request had for each header named in `Vary`.
+    pub vary: Vec<(String, Option<String>)>,
+}
+
+impl CachedResponse {
+    fn header(
+        &self,
+        key: &str,
+    ) -> Option<&str> {
+        find_header(&self.headers, key)
+    }
+
+    fn to_response(&self) -> response::Response {
+        response::Response {
+            body: self.body.clone(),
+            status: self.status,
+            headers: response_headers::ResponseHeaders::new(self.headers.clone()),
+        }
+    }
+
+    /// True if the request asks for the same variant as this was stored for.
+    fn matches_vary(
+        &self,
+        request: &request::Request,
+    ) -> bool {
+        self.vary.iter().all(|(key, value)| request.header(key) == value.as_deref())
+    }
+
+    /// True if the response can still be used without asking the server.
+    fn is_fresh(
+        &self,
+        now: time::SystemTime,
+    ) -> bool {
+        let directives = CacheControl::parse(self.header("Cache-Control"));
+        if directives.no_cache {
+            return false;
+        }
+
+        let lifetime = directives.max_age.map(time::Duration::from_secs).or_else(|| {
+            let expires = httpdate::parse_http_date(self.header("Expires")?).ok()?;
+            let date = self
+                .header("Date")
+                .and_then(|date| httpdate::parse_http_date(date).ok())
+                .unwrap_or(self.stored_at);
+
+            Some(expires.duration_since(date).unwrap_or_default())
+        });
+        let initial_age = self
+            .header("Age")
+            .and_then(|age| age.trim().parse().ok())
+            .map(time::Duration::from_secs)
+            .unwrap_or_default();
+        let age = now.duration_since(self.stored_at).unwrap_or_default() + initial_age;
+
+        lifetime.is_some_and(|lifetime| age < lifetime)
+    }
+
+    /// Takes on the headers from a `304 Not Modified`, which replace those
+    /// stored.
+    fn refresh(
+        &mut self,
+        not_modified: &response::Response,
+        now: time::SystemTime,
+    ) {
+        for (key, value) in not_modified.headers.iter() {
+            if key.eq_ignore_ascii_case("Content-Length") {
+                continue;
+            }
+
+            self.headers.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+            self.headers.push((key.to_string(), value.to_string()));
+        }
+
+        self.stored_at = now;
+    }
+}
+
+/// Where a cache keeps its responses.
+///
+/// Stores are shared through `&self`, so they handle their own locking or
+/// interior mutability.
+pub trait CacheStore {
+    fn get(
+        &self,
+        key: &str,
+    ) -> Option<CachedResponse>;
+
+    fn put(
+        &self,
+        key: &str,
+        response: CachedResponse,
+    );
+
+    fn remove(
+        &self,
+        key: &str,
+    );
+}
+
+/// Keeps responses in memory, up to a maximum number. The least recently
+/// used are thrown away first.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    entries: cell::RefCell<collections::HashMap<String, CachedResponse>>,
+
+    /// Keys from least to most recently used.
+    order: cell::RefCell<collections::VecDeque<String>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: cell::RefCell::new(collections::HashMap::new()),
+            order: cell::RefCell::new(collections::VecDeque::new()),
+        }
+    }
+
+    fn touch(
+        &self,
+        key: &str,
+    ) {
+        let mut order = self.order.borrow_mut();
+        order.retain(|existing| existing != key);
+        order.push_back(key.to_string());
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get(
+        &self,
+        key: &str,
+    ) -> Option<CachedResponse> {
+        let response = self.entries.borrow().get(key).cloned()?;
+        self.touch(key);
+
+        Some(response)
+    }
+
+    fn put(
+        &self,
+        key: &str,
+        response: CachedResponse,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.entries.borrow_mut().insert(key.to_string(), response);
+        self.touch(key);
+
+        while self.order.borrow().len() > self.capacity {
+            if let Some(oldest) = self.order.borrow_mut().pop_front() {
+                self.entries.borrow_mut().remove(&oldest);
+            }
+        }
+    }
+
+    fn remove(
+        &self,
+        key: &str,
+    ) {
+        self.entries.borrow_mut().remove(key);
+        self.order.borrow_mut().retain(|existing| existing != key);
+    }
+}
+
+/// Keeps responses as files in a directory, so they last between runs.
+///
+/// Each response is a JSON file named after a hash of its key. Files which
+/// cannot be read are treated as missing.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    directory: path::PathBuf,
+}
+
+impl DiskCache {
+    /// Uses the directory given, creating it if needed.
+    pub fn new(directory: impl AsRef<path::Path>) -> Result<Self, error::Error> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+        })
+    }
+
+    fn path_for(
+        &self,
+        key: &str,
+    ) -> path::PathBuf {
+        let name = hex::encode(sha2::Sha256::digest(key.as_bytes()));
+
+        self.directory.join(format!("{}.json", name))
+    }
+
+    fn write(
+        &self,
+        key: &str,
+        response: &CachedResponse,
+    ) -> io::Result<()> {
+        let stored_at =
+            response.stored_at.duration_since(time::UNIX_EPOCH).unwrap_or_default().as_millis()
+                as u64;
+        let json = serde_json::json!({
+            "key": key,
+            "status": response.status,
+            "headers": response.headers,
+            "body": base64::encode(&response.body),
+            "stored_at": stored_at,
+            "vary": response.vary,
+        });
+
+        let path = self.path_for(key);
+        let temp_path = path.with_extension("json.part");
+        fs::write(&temp_path, serde_json::to_vec(&json)?)?;
+        fs::rename(&temp_path, &path)
+    }
+}
+
+impl CacheStore for DiskCache {
+    fn get(
+        &self,
+        key: &str,
+    ) -> Option<CachedResponse> {
+        let bytes = fs::read(self.path_for(key)).ok()?;
+        let json: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+
+        if json["key"] != key {
+            return None;
+        }
+
+        Some(CachedResponse {
+            status: json["status"].as_u64()? as u32,
+            headers: serde_json::from_value(json["headers"].clone()).ok()?,
+            body: base64::decode(json["body"].as_str()?).ok()?,
+            stored_at: time::UNIX_EPOCH + time::Duration::from_millis(json["stored_at"].as_u64()?),
+            vary: serde_json::from_value(json["vary"].clone()).ok()?,
+        })
+    }
+
+    /// Failing to write to the cache is not an error, the response is just
+    /// not cached.
+    fn put(
+        &self,
+        key: &str,
+        response: CachedResponse,
+    ) {
+        let _ = self.write(key, &response);
+    }
+
+    fn remove(
+        &self,
+        key: &str,
+    ) {
+        let _ = fs::remove_file(self.path_for(key));
+    }
+}
+
+/// Middleware which caches `GET` responses in the store given.
+///
+/// One response is kept per url and set of credentials. When a request has different values for
+/// the headers named in `Vary`, it is fetched again and replaces the one
+/// stored.
+#[derive(Debug)]
+pub struct HttpCache<S: CacheStore> {
+    store: S,
+}
+
+impl<S: CacheStore> HttpCache<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+        }
+    }
+
+    fn store_response(
+        &self,
+        key: &str,
+        request: &request::Request,
+        response: &response::Response,
+        now: time::SystemTime,
+    ) {
+        let directives = CacheControl::parse(response.headers.get("Cache-Control"));
+        let vary = response.headers.get_all("Vary").flat_map(|vary| vary.split(','));
+        let vary = vary.map(str::trim).filter(|name| !name.is_empty()).collect::<Vec<_>>();
+
+        let has_validator =
+            response.headers.contains("ETag") || response.headers.contains("Last-Modified");
+        let has_lifetime = directives.max_age.is_some() || response.headers.contains("Expires");
+        let is_cacheable = response.status == 200
+            && !directives.no_store
+            && !vary.contains(&"*")
+            && (has_validator || has_lifetime);
+
+        if !is_cacheable {
+            self.store.remove(key);
+            return;
+        }
+
+        let headers = response.headers.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        let vary =
+            vary.iter().map(|name| (name.to_string(), request.header(name).map(str::to_string)));
+
+        self.store.put(
+            key,
+            CachedResponse {
+                status: response.status,
+                headers: headers.collect(),
+                body: response.body.clone(),
+                stored_at: now,
+                vary: vary.collect(),
+            },
+        );
+    }
+}
+
+impl<S: CacheStore> middleware::Middleware for HttpCache<S> {
+    fn handle(
+        &self,
+        mut request: request::Request,
+        next: middleware::Next,
+    ) -> Result<response::Response, error::Error> {
+        let request_directives = CacheControl::parse(request.header("Cache-Control"));
+        if request.method != method::Method::Get || request_directives.no_store {
+            return next.run(request);
+        }
+
+        // Requests which are already conditional are left to the caller.
+        if request.header("If-None-Match").is_some()
+            || request.header("If-Modified-Since").is_some()
+        {
+            return next.run(request);
+        }
+
+        let key = cache_key(&request);
+        let now = time::SystemTime::now();
+        let cached = self.store.get(&key).filter(|cached| cached.matches_vary(&request));
+
+        if let Some(ref cached) = cached {
+            if !request_directives.no_cache && cached.is_fresh(now) {
+                return Ok(cached.to_response());
+            }
+
+            if let Some(etag) = cached.header("ETag") {
+                request.set_header("If-None-Match", &etag);
+            }
+            if let Some(last_modified) = cached.header("Last-Modified") {
+                request.set_header("If-Modified-Since", &last_modified);
+            }
+        }
+
+        let stored_request = request.clone();
+        let response = next.run(request)?;
+
+        match cached {
+            Some(mut cached) if response.status == 304 => {
+                cached.refresh(&response, now);
+                let refreshed = cached.to_response();
+                self.store.put(&key, cached);
+
+                Ok(refreshed)
+            },
+            // The body of a streamed response has not been read, so there
+            // is nothing to store.
+            _ if stored_request.is_streamed() => Ok(response),
+            _ => {
+                self.store_response(&key, &stored_request, &response, now);
+
+                Ok(response)
+            },
+        }
+    }
+}
+
+/// The parts of a `Cache-Control` header which matter to a client cache.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(header: Option<&str>) -> Self {
+        let mut directives = Self::default();
+
+        for directive in header.unwrap_or("").split(',') {
+            let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+            let value = value.trim().trim_matches('"');
+
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => directives.no_store = true,
+                "no-cache" => directives.no_cache = true,
+                "max-age" => directives.max_age = value.parse().ok(),
+                _ => {},
+            }
+        }
+
+        directives
+    }
+}
+
+/// The key a request is cached under.
+///
+/// This is the url with secret query parameters masked, followed by a hash
+/// of the full url and the credential headers. Secrets are kept out of the
+/// key, as a `DiskCache` writes it to disk, whilst requests made with
+/// different credentials are still cached apart.
+fn cache_key(request: &request::Request) -> String {
+    let mut credentials = request
+        .headers
+        .iter()
+        .filter(|(key, _)| request::is_sensitive_header(key))
+        .map(|(key, value)| format!("{}:{}", key.to_ascii_lowercase(), value))
+        .collect::<Vec<_>>();
+    credentials.sort();
+
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(request.url.as_bytes());
+    for credential in &credentials {
+        hasher.update(b"\n");
+        hasher.update(credential.as_bytes());
+    }
+
+    format!("{} {} {}", request.method, request.display_url(), hex::encode(hasher.finalize()))
+}
+
+fn find_header<'a>(
+    headers: &'a [(String, String)],
+    key: &str,
+) -> Option<&'a str> {
+    headers.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain;
+    use crate::test_dir;
+    use crate::test_server;
+    use std::io::Read;
+
+    fn cached(headers: &[(&str, &str)]) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            body: b"[1]".to_vec(),
+            stored_at: time::UNIX_EPOCH + time::Duration::from_secs(1_000_000),
+            vary: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn freshness_from_max_age_and_expires() {
+        let stored_at = time::UNIX_EPOCH + time::Duration::from_secs(1_000_000);
+        let later = |seconds| stored_at + time::Duration::from_secs(seconds);
+
+        let max_age = cached(&[("Cache-Control", "public, max-age=60"), ("Age", "10")]);
+        assert!(max_age.is_fresh(later(49)));
+        assert!(!max_age.is_fresh(later(50)));
+
+        let expires = cached(&[
+            ("Date", &httpdate::fmt_http_date(stored_at)),
+            ("Expires", &httpdate::fmt_http_date(later(30))),
+        ]);
+        assert!(expires.is_fresh(later(29)));
+        assert!(!expires.is_fresh(later(30)));
+
+        assert!(!cached(&[("Cache-Control", "no-cache, max-age=60")]).is_fresh(later(1)));
+        assert!(!cached(&[("ETag", "\"v1\"")]).is_fresh(later(0)));
+    }
+
+    #[test]
+    fn memory_cache_drops_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.put("a", cached(&[]));
+        cache.put("b", cached(&[]));
+        cache.get("a");
+        cache.put("c", cached(&[]));
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn disk_cache_round_trip() {
+        let directory = test_dir::TestDir::new("disk_cache_round_trip");
+        let directory = directory.path();
+        let response = cached(&[("ETag", "\"v1\"")]);
+
+        DiskCache::new(directory).unwrap().put("GET http://example.com", response.clone());
+        let cache = DiskCache::new(directory).unwrap();
+
+        assert_eq!(cache.get("GET http://example.com"), Some(response));
+        assert_eq!(cache.get("GET http://example.com/other"), None);
+
+        cache.remove("GET http://example.com");
+        assert_eq!(cache.get("GET http://example.com"), None);
+    }
+
+    #[test]
+    fn secret_query_values_are_not_written_to_disk() {
+        let directory = test_dir::TestDir::new("secret_query_values_are_not_written_to_disk");
+        let directory = directory.path();
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("Cache-Control", "max-age=600")], b"[1]")
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.secret_query("api_key", "abc123");
+        domain.cache(DiskCache::new(directory).unwrap());
+
+        for _ in 0..2 {
+            domain.get().push(&"items").execute_as_string::<()>(None).unwrap();
+        }
+
+        let files = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(files.len(), 1);
+        assert!(!files[0].contains("abc123"));
+    }
+
+    #[test]
+    fn requests_with_different_credentials_are_cached_apart() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("Cache-Control", "max-age=600")], b"[1]")
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.cache(MemoryCache::new(10));
+
+        for token in &["one", "two", "one"] {
+            domain.get().bearer_auth(*token).execute_as_string::<()>(None).unwrap();
+        }
+
+        let authorizations = server
+            .requests()
+            .into_iter()
+            .map(|request| request.header("Authorization").map(str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            authorizations,
+            vec![Some("Bearer one".to_string()), Some("Bearer two".to_string())]
+        );
+    }
+
+    #[test]
+    fn fresh_responses_are_not_fetched_again() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("Cache-Control", "max-age=600")], b"[1,2]")
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.cache(MemoryCache::new(10));
+
+        let first: Vec<u32> = domain.get().execute_as_json::<(), _>(None).unwrap();
+        let second: Vec<u32> = domain.get().execute_as_json::<(), _>(None).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn stale_responses_are_revalidated() {
+        let server = test_server::TestServer::new(|request| {
+            if request.header("If-None-Match") == Some("\"v1\"") {
+                test_server::response(304, &[("ETag", "\"v1\"")], b"")
+            } else {
+                test_server::response(
+                    200,
+                    &[("ETag", "\"v1\""), ("Cache-Control", "no-cache")],
+                    b"[3]",
+                )
+            }
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.cache(MemoryCache::new(10));
+
+        let first: Vec<u32> = domain.get().execute_as_json::<(), _>(None).unwrap();
+        let second: Vec<u32> = domain.get().execute_as_json::<(), _>(None).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(first, vec![3]);
+        assert_eq!(second, vec![3]);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].header("If-None-Match"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn vary_and_no_store_are_honoured() {
+        let server = test_server::TestServer::new(|request| {
+            let headers: &[(&str, &str)] = match request.path.as_str() {
+                "/private" => &[("Cache-Control", "no-store, max-age=600")],
+                _ => &[("Cache-Control", "max-age=600"), ("Vary", "Accept-Language")],
+            };
+
+            test_server::response(200, headers, b"[1]")
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.cache(MemoryCache::new(10));
+
+        for language in &["en", "en", "fr"] {
+            let mut path = domain.get().push(&"greeting");
+            path.header("Accept-Language", language);
+            path.execute_as_string::<()>(None).unwrap();
+        }
+        for _ in 0..2 {
+            domain.get().push(&"private").execute_as_string::<()>(None).unwrap();
+        }
+
+        let paths = server.requests().into_iter().map(|request| request.path).collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/greeting", "/greeting", "/private", "/private"]);
+    }
+
+    #[test]
+    fn streamed_responses_are_served_but_not_stored() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("Cache-Control", "max-age=600")], b"[1]")
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.cache(MemoryCache::new(10));
+
+        let mut body = String::new();
+        domain
+            .get()
+            .push(&"a")
+            .execute_as_reader::<()>(None)
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        domain.get().push(&"a").execute_as_string::<()>(None).unwrap();
+        assert_eq!(body, "[1]");
+        assert_eq!(server.requests().len(), 2);
+
+        let mut body = String::new();
+        domain
+            .get()
+            .push(&"a")
+            .execute_as_reader::<()>(None)
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "[1]");
+        assert_eq!(server.requests().len(), 2);
+    }
+}
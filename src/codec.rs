@@ -0,0 +1,876 @@
+use erased_serde;
+use serde;
+use serde_json;
+use serde_path_to_error;
+use serde_urlencoded;
+use std::error;
+use std::fmt;
+use std::rc;
+use url;
+
+#[cfg(feature = "msgpack")]
+use rmp_serde;
+
+#[cfg(feature = "cbor")]
+use serde_cbor;
+
+#[cfg(feature = "xml")]
+use quick_xml;
+
+/// Turns request bodies into bytes, and response bodies back into values.
+///
+/// JSON is always available. MessagePack, CBOR, and XML are available
+/// behind the `msgpack`, `cbor`, and `xml` features.
+///
+/// This can be used as a trait object, so a codec of your own can be set
+/// on a `Domain` or a `Path` using `codec`. Only `content_type`,
+/// `serialize_erased`, and `deserialize_erased` need to be written, which
+/// work with the type erased traits from `erased_serde`.
+pub trait Codec {
+    /// The `Content-Type` sent along with request bodies.
+    fn content_type(&self) -> &'static str;
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError>;
+
+    /// Deserializes the bytes by handing a deserializer for them to
+    /// `visit`, and returning any error it gives back.
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError>;
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError>
+    where
+        Self: Sized,
+    {
+        self.serialize_erased(&body)
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError>
+    where
+        Self: Sized,
+    {
+        deserialize_erased(self, bytes)
+    }
+}
+
+/// What a `Codec` hands its deserializer to, when deserializing.
+pub type CodecVisit<'a> = dyn for<'de> FnMut(&mut dyn erased_serde::Deserializer<'de>) -> Result<(), erased_serde::Error>
+    + 'a;
+
+impl<C: Codec + ?Sized> Codec for rc::Rc<C> {
+    fn content_type(&self) -> &'static str {
+        (**self).content_type()
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        (**self).serialize_erased(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        (**self).deserialize_erased(bytes, visit)
+    }
+}
+
+impl<C: Codec + ?Sized> Codec for Box<C> {
+    fn content_type(&self) -> &'static str {
+        (**self).content_type()
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        (**self).serialize_erased(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        (**self).deserialize_erased(bytes, visit)
+    }
+}
+
+/// Deserializes using the type erased side of the codec, keeping the path
+/// to the field which failed.
+fn deserialize_erased<C: Codec + ?Sized, R: serde::de::DeserializeOwned>(
+    codec: &C,
+    bytes: &[u8],
+) -> Result<R, CodecError> {
+    let mut value = None;
+    let mut path = None;
+
+    let mut visit = |deserializer: &mut dyn erased_serde::Deserializer<'_>| {
+        match serde_path_to_error::deserialize(deserializer) {
+            Ok(deserialized) => {
+                value = Some(deserialized);
+                Ok(())
+            },
+            Err(error) => {
+                path = Some(error.path().to_string());
+                Err(error.into_inner())
+            },
+        }
+    };
+
+    codec.deserialize_erased(bytes, &mut visit).map_err(|error| error.with_path(path))?;
+
+    value.ok_or_else(|| CodecError::new("the codec did not deserialize anything"))
+}
+
+/// The codec set on a `Domain` or a `Path`.
+#[derive(Clone)]
+pub(crate) enum BodyCodec {
+    Format(Format),
+    Custom(rc::Rc<dyn Codec>),
+}
+
+impl BodyCodec {
+    /// The codec to read a response with the `Content-Type` given.
+    ///
+    /// A custom codec is used when its content type matches, or when none
+    /// of the built in formats do.
+    pub(crate) fn for_response(
+        &self,
+        content_type: Option<&str>,
+    ) -> BodyCodec {
+        let mime =
+            content_type.map(|content_type| content_type.split(';').next().unwrap_or("").trim());
+
+        match self {
+            BodyCodec::Custom(ref codec)
+                if mime.is_none_or(|mime| mime.eq_ignore_ascii_case(codec.content_type())) =>
+            {
+                self.clone()
+            },
+            _ => content_type
+                .and_then(Format::from_content_type)
+                .map(BodyCodec::Format)
+                .unwrap_or_else(|| self.clone()),
+        }
+    }
+}
+
+impl Default for BodyCodec {
+    fn default() -> Self {
+        BodyCodec::Format(Format::default())
+    }
+}
+
+impl fmt::Debug for BodyCodec {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            BodyCodec::Format(format) => write!(f, "{:?}", format),
+            BodyCodec::Custom(codec) => write!(f, "Custom({:?})", codec.content_type()),
+        }
+    }
+}
+
+impl Codec for BodyCodec {
+    fn content_type(&self) -> &'static str {
+        match self {
+            BodyCodec::Format(format) => format.content_type(),
+            BodyCodec::Custom(codec) => codec.content_type(),
+        }
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        match self {
+            BodyCodec::Format(format) => format.serialize_erased(body),
+            BodyCodec::Custom(codec) => codec.serialize_erased(body),
+        }
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        match self {
+            BodyCodec::Format(format) => format.deserialize_erased(bytes, visit),
+            BodyCodec::Custom(codec) => codec.deserialize_erased(bytes, visit),
+        }
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        match self {
+            BodyCodec::Format(format) => format.serialize(body),
+            BodyCodec::Custom(codec) => codec.serialize(body),
+        }
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        match self {
+            BodyCodec::Format(format) => format.deserialize(bytes),
+            BodyCodec::Custom(codec) => codec.deserialize(bytes),
+        }
+    }
+}
+
+/// The error from serializing or deserializing with a `Codec`.
+///
+/// When deserializing, this also holds where the failure happened, where
+/// the codec is able to say.
+#[derive(Debug)]
+pub struct CodecError {
+    // Boxed to keep `Error` small.
+    details: Box<CodecErrorDetails>,
+}
+
+#[derive(Debug)]
+struct CodecErrorDetails {
+    error: Box<dyn error::Error + Send + Sync>,
+    path: Option<String>,
+    position: Option<(usize, usize)>,
+}
+
+impl CodecError {
+    pub fn new(error: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+        Self {
+            details: Box::new(CodecErrorDetails {
+                error: error.into(),
+                path: None,
+                position: None,
+            }),
+        }
+    }
+
+    /// Builds an error from a failure found using `serde_path_to_error`,
+    /// keeping the path to the field which failed.
+    pub fn from_path_error<E: error::Error + Send + Sync + 'static>(
+        error: serde_path_to_error::Error<E>
+    ) -> Self {
+        let path = error.path().to_string();
+
+        Self::new(error.into_inner()).with_path(Some(path))
+    }
+
+    /// Sets the path to the field which failed, unless one is already set.
+    fn with_path(
+        mut self,
+        path: Option<String>,
+    ) -> Self {
+        // The root of the document is written as a lone '.'.
+        if self.details.path.is_none() {
+            self.details.path = path.filter(|path| path != ".");
+        }
+
+        self
+    }
+
+    /// Sets the line and column the error happened at.
+    /// These start from 1.
+    pub fn with_position(
+        mut self,
+        line: usize,
+        column: usize,
+    ) -> Self {
+        if line > 0 {
+            self.details.position = Some((line, column));
+        }
+
+        self
+    }
+
+    /// The error from the underlying serialization library.
+    pub fn inner(&self) -> &(dyn error::Error + Send + Sync + 'static) {
+        &*self.details.error
+    }
+
+    /// The path to the field which failed to deserialize.
+    /// i.e. `items[3].owner.id`.
+    pub fn path(&self) -> Option<&str> {
+        self.details.path.as_deref()
+    }
+
+    /// The line the error happened on, for text formats.
+    pub fn line(&self) -> Option<usize> {
+        self.details.position.map(|(line, _)| line)
+    }
+
+    /// The column the error happened on, for text formats.
+    pub fn column(&self) -> Option<usize> {
+        self.details.position.map(|(_, column)| column)
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        if let Some(ref path) = self.details.path {
+            write!(f, "at {}: ", path)?;
+        }
+
+        write!(f, "{}", self.details.error)
+    }
+}
+
+impl error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.details.error.source()
+    }
+}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(error: serde_json::Error) -> Self {
+        let (line, column) = (error.line(), error.column());
+
+        Self::new(error).with_position(line, column)
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for CodecError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let (line, column) = (error.inner().line(), error.inner().column());
+
+        Self::from_path_error(error).with_position(line, column)
+    }
+}
+
+/// The formats built into Burgundy.
+///
+/// This is used to pick the format for a `Domain` or a `Path`. When a
+/// response comes back, the format is picked from its `Content-Type` where
+/// possible, and falls back to the one set.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Format {
+    #[default]
+    Json,
+    Form,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+impl Format {
+    /// Finds the format for the `Content-Type` given.
+    ///
+    /// Parameters such as `charset` are ignored, and structured syntax
+    /// suffixes such as `application/vnd.github+json` are understood.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        let (kind, subtype) = mime.split_once('/')?;
+        let suffix = subtype.rsplit('+').next().unwrap_or(subtype);
+
+        match (kind, suffix) {
+            ("application", "json") | ("text", "json") => Some(Format::Json),
+            ("application", "x-www-form-urlencoded") => Some(Format::Form),
+            #[cfg(feature = "msgpack")]
+            ("application", "msgpack") | ("application", "x-msgpack") => Some(Format::MessagePack),
+            #[cfg(feature = "cbor")]
+            ("application", "cbor") => Some(Format::Cbor),
+            #[cfg(feature = "xml")]
+            ("application", "xml") | ("text", "xml") => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    fn codec(&self) -> &'static dyn Codec {
+        match self {
+            Format::Json => &JsonCodec,
+            Format::Form => &FormCodec,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => &MessagePackCodec,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => &CborCodec,
+            #[cfg(feature = "xml")]
+            Format::Xml => &XmlCodec,
+        }
+    }
+}
+
+impl Codec for Format {
+    fn content_type(&self) -> &'static str {
+        self.codec().content_type()
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.codec().serialize_erased(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        self.codec().deserialize_erased(bytes, visit)
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Format::Json => JsonCodec.serialize(body),
+            Format::Form => FormCodec.serialize(body),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => MessagePackCodec.serialize(body),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => CborCodec.serialize(body),
+            #[cfg(feature = "xml")]
+            Format::Xml => XmlCodec.serialize(body),
+        }
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        match self {
+            Format::Json => JsonCodec.deserialize(bytes),
+            Format::Form => FormCodec.deserialize(bytes),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => MessagePackCodec.deserialize(bytes),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => CborCodec.deserialize(bytes),
+            #[cfg(feature = "xml")]
+            Format::Xml => XmlCodec.deserialize(bytes),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.serialize(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(&mut deserializer))
+            .map_err(CodecError::new)?;
+        deserializer.end()?;
+
+        Ok(())
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(body)?)
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        let value = serde_path_to_error::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+
+        Ok(value)
+    }
+}
+
+/// HTML form encoding, `application/x-www-form-urlencoded`.
+///
+/// Only flat structures, such as structs of strings and numbers, or
+/// sequences of key/value pairs, can be used.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FormCodec;
+
+impl Codec for FormCodec {
+    fn content_type(&self) -> &'static str {
+        "application/x-www-form-urlencoded"
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.serialize(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        let deserializer = serde_urlencoded::Deserializer::new(url::form_urlencoded::parse(bytes));
+
+        visit(&mut <dyn erased_serde::Deserializer>::erase(deserializer)).map_err(CodecError::new)
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        let text = serde_urlencoded::to_string(body).map_err(CodecError::new)?;
+
+        Ok(text.into_bytes())
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        serde_urlencoded::from_bytes(bytes).map_err(CodecError::new)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.serialize(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = rmp_serde::Deserializer::new(bytes);
+
+        visit(&mut <dyn erased_serde::Deserializer>::erase(&mut deserializer))
+            .map_err(CodecError::new)
+    }
+
+    /// Structs are written as maps, so fields can be matched by name.
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(body).map_err(CodecError::new)
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        let mut deserializer = rmp_serde::Deserializer::new(bytes);
+
+        serde_path_to_error::deserialize(&mut deserializer).map_err(CodecError::from_path_error)
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.serialize(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(&mut deserializer))
+            .map_err(CodecError::new)?;
+        deserializer.end().map_err(CodecError::new)?;
+
+        Ok(())
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        serde_cbor::to_vec(&body).map_err(CodecError::new)
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
+        let value = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(CodecError::from_path_error)?;
+        deserializer.end().map_err(CodecError::new)?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(feature = "xml")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct XmlCodec;
+
+#[cfg(feature = "xml")]
+impl Codec for XmlCodec {
+    fn content_type(&self) -> &'static str {
+        "application/xml"
+    }
+
+    fn serialize_erased(
+        &self,
+        body: &dyn erased_serde::Serialize,
+    ) -> Result<Vec<u8>, CodecError> {
+        self.serialize(body)
+    }
+
+    fn deserialize_erased(
+        &self,
+        bytes: &[u8],
+        visit: &mut CodecVisit<'_>,
+    ) -> Result<(), CodecError> {
+        let text = ::std::str::from_utf8(bytes).map_err(CodecError::new)?;
+        let mut deserializer = quick_xml::de::Deserializer::from_str(text);
+
+        visit(&mut <dyn erased_serde::Deserializer>::erase(&mut deserializer))
+            .map_err(CodecError::new)
+    }
+
+    fn serialize<B: serde::ser::Serialize + ?Sized>(
+        &self,
+        body: &B,
+    ) -> Result<Vec<u8>, CodecError> {
+        let text = quick_xml::se::to_string(body).map_err(CodecError::new)?;
+
+        Ok(text.into_bytes())
+    }
+
+    fn deserialize<R: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<R, CodecError> {
+        let text = ::std::str::from_utf8(bytes).map_err(CodecError::new)?;
+
+        let mut deserializer = quick_xml::de::Deserializer::from_str(text);
+
+        serde_path_to_error::deserialize(&mut deserializer).map_err(CodecError::from_path_error)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// JSON written back to front, as a codec the library doesn't know.
+    pub(crate) struct ReversedJsonCodec;
+
+    impl Codec for ReversedJsonCodec {
+        fn content_type(&self) -> &'static str {
+            "application/x-reversed-json"
+        }
+
+        fn serialize_erased(
+            &self,
+            body: &dyn erased_serde::Serialize,
+        ) -> Result<Vec<u8>, CodecError> {
+            let mut bytes = serde_json::to_vec(body).map_err(CodecError::new)?;
+            bytes.reverse();
+
+            Ok(bytes)
+        }
+
+        fn deserialize_erased(
+            &self,
+            bytes: &[u8],
+            visit: &mut CodecVisit<'_>,
+        ) -> Result<(), CodecError> {
+            let bytes = bytes.iter().rev().cloned().collect::<Vec<u8>>();
+            let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+
+            visit(&mut <dyn erased_serde::Deserializer>::erase(&mut deserializer))
+                .map_err(CodecError::new)
+        }
+    }
+
+    #[test]
+    fn custom_codec_works_as_a_trait_object() {
+        #[derive(Deserialize, Debug)]
+        struct Item {
+            #[allow(dead_code)]
+            id: u32,
+        }
+
+        let codec: Box<dyn Codec> = Box::new(ReversedJsonCodec);
+        let bytes = codec.serialize(&vec![1, 2, 3]).unwrap();
+        let value: Vec<u32> = codec.deserialize(&bytes).unwrap();
+        let error = codec.deserialize::<Item>(br#"}"owt":"di"{"#).unwrap_err();
+
+        assert_eq!(bytes, b"]3,2,1[".to_vec());
+        assert_eq!(value, vec![1, 2, 3]);
+        assert_eq!(error.path(), Some("id"));
+    }
+
+    #[test]
+    fn format_from_content_type() {
+        assert_eq!(Format::from_content_type("application/json"), Some(Format::Json));
+        assert_eq!(
+            Format::from_content_type("application/json; charset=utf-8"),
+            Some(Format::Json)
+        );
+        assert_eq!(Format::from_content_type("application/vnd.github.v3+json"), Some(Format::Json));
+        assert_eq!(Format::from_content_type("text/html"), None);
+        assert_eq!(Format::from_content_type("nonsense"), None);
+    }
+
+    #[test]
+    fn json_error_has_path_and_position() {
+        #[derive(Deserialize, Debug)]
+        struct Owner {
+            #[allow(dead_code)]
+            id: u32,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Item {
+            #[allow(dead_code)]
+            owner: Owner,
+        }
+
+        let text = "{\"items\": [\n{\"owner\": {\"id\": 1}},\n{\"owner\": {\"id\": \"two\"}}\n]}";
+        let error = Format::Json
+            .deserialize::<::std::collections::HashMap<String, Vec<Item>>>(text.as_bytes())
+            .unwrap_err();
+
+        assert_eq!(error.path(), Some("items[1].owner.id"));
+        assert_eq!(error.line(), Some(3));
+        assert_eq!(error.column(), Some(22));
+    }
+
+    #[test]
+    fn built_in_formats_work_as_trait_objects() {
+        let body = vec![("name".to_string(), "box".to_string())];
+
+        for format in [Format::Json, Format::Form] {
+            let codec: Box<dyn Codec> = Box::new(format);
+            let bytes = codec.serialize(&body).unwrap();
+            let value: Vec<(String, String)> = codec.deserialize(&bytes).unwrap();
+
+            assert_eq!(bytes, format.serialize(&body).unwrap());
+            assert_eq!(value, body);
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let bytes = Format::Json.serialize(&vec![1, 2, 3]).unwrap();
+        let value: Vec<u32> = Format::Json.deserialize(&bytes).unwrap();
+
+        assert_eq!(bytes, b"[1,2,3]".to_vec());
+        assert_eq!(value, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn form_round_trip() {
+        let body = vec![("grant_type", "client_credentials"), ("scope", "read write")];
+        let bytes = Format::Form.serialize(&body).unwrap();
+        let value: Vec<(String, String)> = Format::Form.deserialize(&bytes).unwrap();
+
+        assert_eq!(bytes, b"grant_type=client_credentials&scope=read+write".to_vec());
+        assert_eq!(value[1], ("scope".to_string(), "read write".to_string()));
+        assert_eq!(
+            Format::from_content_type("application/x-www-form-urlencoded"),
+            Some(Format::Form)
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trip() {
+        let bytes = Format::MessagePack.serialize(&vec![1, 2, 3]).unwrap();
+        let value: Vec<u32> = Format::MessagePack.deserialize(&bytes).unwrap();
+
+        assert_eq!(Format::from_content_type("application/msgpack"), Some(Format::MessagePack));
+        assert_eq!(value, vec![1, 2, 3]);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        let bytes = Format::Cbor.serialize(&vec![1, 2, 3]).unwrap();
+        let value: Vec<u32> = Format::Cbor.deserialize(&bytes).unwrap();
+
+        assert_eq!(Format::from_content_type("application/cbor"), Some(Format::Cbor));
+        assert_eq!(value, vec![1, 2, 3]);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_round_trip() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Item {
+            name: String,
+            size: u32,
+        }
+
+        let item = Item {
+            name: "box".to_string(),
+            size: 3,
+        };
+        let bytes = Format::Xml.serialize(&item).unwrap();
+        let value: Item = Format::Xml.deserialize(&bytes).unwrap();
+
+        assert_eq!(Format::from_content_type("application/atom+xml"), Some(Format::Xml));
+        assert_eq!(value, item);
+    }
+}
@@ -1,11 +1,21 @@
+use auth;
+use cache;
+use codec;
 use error;
+use link;
 use method;
+use middleware;
 use native_client;
 use path::Path;
+use proxy;
 use request_information;
+use secret;
+use signing;
 use std::cell;
+use std::env;
 use std::fmt;
 use std::rc;
+use tls;
 
 #[derive(Debug)]
 pub struct Domain {
@@ -37,6 +47,17 @@ impl Domain {
         Ok(())
     }
 
+    /// Adds a query parameter whose value is kept secret, such as an
+    /// `api_key`. It is sent as normal, but masked when the url is displayed,
+    /// logged, or included in an error.
+    pub fn secret_query(
+        &mut self,
+        key: &str,
+        value: impl Into<secret::Secret>,
+    ) {
+        self.info.borrow_mut().add_secret_query_param(key, value.into());
+    }
+
     pub fn header(
         &mut self,
         key: &'static str,
@@ -45,6 +66,128 @@ impl Domain {
         self.info.borrow_mut().add_header(key, value);
     }
 
+    /// Authenticates every request with the bearer token given.
+    pub fn bearer_auth(
+        &mut self,
+        token: impl Into<secret::Secret>,
+    ) {
+        let authorization = secret::bearer_authorization(&token.into());
+        self.info.borrow_mut().set_authorization(authorization);
+    }
+
+    /// Authenticates every request using HTTP Basic auth.
+    pub fn basic_auth(
+        &mut self,
+        user: &str,
+        password: impl Into<secret::Secret>,
+    ) {
+        let authorization = secret::basic_authorization(user, &password.into());
+        self.info.borrow_mut().set_authorization(authorization);
+    }
+
+    /// Sets where the credentials for each request come from.
+    ///
+    /// The provider is asked for the `Authorization` header before each
+    /// request. This is used instead of `bearer_auth` or `basic_auth` on the
+    /// `Domain`, but auth set on a `Path` takes priority.
+    pub fn auth_provider(
+        &mut self,
+        provider: impl auth::AuthProvider + 'static,
+    ) {
+        self.client.borrow_mut().set_auth_provider(Box::new(provider));
+    }
+
+    /// When turned on, requests which come back `401 Unauthorized` ask the
+    /// auth provider to refresh its credentials, and are then tried once
+    /// more. This is off by default.
+    ///
+    /// Requests made using `Path::execute_as_stream` are never retried, as
+    /// refreshing credentials blocks, and the stream is sent as a future.
+    /// Use `Path::execute_as_reader` to stream a body with retries.
+    pub fn retry_unauthorized(
+        &mut self,
+        retry: bool,
+    ) {
+        self.client.borrow_mut().set_retry_unauthorized(retry);
+    }
+
+    /// Adds middleware, which is wrapped around every request, apart from
+    /// those made with `Path::execute_as_stream`.
+    /// Middleware runs in the order it is added.
+    pub fn middleware(
+        &mut self,
+        middleware: impl middleware::Middleware + 'static,
+    ) {
+        self.client.borrow_mut().add_middleware(Box::new(middleware));
+    }
+
+    /// Caches `GET` responses in the store given, such as a
+    /// `cache::MemoryCache` or `cache::DiskCache`.
+    ///
+    /// The cache is added as middleware, so it sits inside any middleware
+    /// added before it.
+    pub fn cache(
+        &mut self,
+        store: impl cache::CacheStore + 'static,
+    ) {
+        self.middleware(cache::HttpCache::new(store));
+    }
+
+    /// Signs every request just before it is sent, such as with an
+    /// `HmacSigner`. The signer sees the final url, headers and body.
+    pub fn signer(
+        &mut self,
+        signer: impl signing::RequestSigner + 'static,
+    ) {
+        self.client.borrow_mut().set_signer(Box::new(signer));
+    }
+
+    /// Sets up the TLS connections made, such as to trust an internal CA,
+    /// or present a client certificate. Errors if a certificate or key in
+    /// the config is not valid.
+    pub fn tls(
+        &mut self,
+        config: tls::TlsConfig,
+    ) -> Result<(), error::Error> {
+        self.client.borrow_mut().set_tls(config)
+    }
+
+    /// Sends requests through the proxy, for the urls it applies to.
+    /// Proxies added first take priority.
+    pub fn proxy(
+        &mut self,
+        proxy: proxy::Proxy,
+    ) -> Result<(), error::Error> {
+        self.client.borrow_mut().add_proxies(vec![proxy])
+    }
+
+    /// Sends requests through the proxies set in the `HTTP_PROXY` and
+    /// `HTTPS_PROXY` environment variables, skipping the hosts in
+    /// `NO_PROXY`. Errors if a proxy url is not valid.
+    pub fn proxy_from_env(&mut self) -> Result<(), error::Error> {
+        let proxies = proxy::from_env(|name| env::var(name).ok())?;
+
+        self.client.borrow_mut().add_proxies(proxies)
+    }
+
+    /// Sets the format used for request and response bodies.
+    /// This is JSON by default.
+    pub fn format(
+        &mut self,
+        format: codec::Format,
+    ) {
+        self.info.borrow_mut().set_codec(codec::BodyCodec::Format(format));
+    }
+
+    /// Sets a codec of your own, used for request and response bodies in
+    /// place of a `Format`.
+    pub fn codec(
+        &mut self,
+        codec: impl codec::Codec + 'static,
+    ) {
+        self.info.borrow_mut().set_codec(codec::BodyCodec::Custom(rc::Rc::new(codec)));
+    }
+
     pub fn get(&self) -> Path {
         self.method(method::Method::Get)
     }
@@ -81,6 +224,35 @@ impl Domain {
         self.method(method::Method::Trace)
     }
 
+    /// A `GET` request for a url the server sent back, such as a
+    /// `next_url` or `self` href. See `Path::from_url`.
+    pub fn get_url(
+        &self,
+        url: &str,
+    ) -> Result<Path, error::Error> {
+        Path::from_url(self, method::Method::Get, url)
+    }
+
+    /// A `GET` request for a link the server sent back, such as from
+    /// `Response::links`. See `Path::from_url`.
+    pub fn follow(
+        &self,
+        link: &link::Link,
+    ) -> Result<Path, error::Error> {
+        self.get_url(&link.url)
+    }
+
+    /// Allows urls given to `get_url`, `follow` and `Path::from_url` to
+    /// point to another origin, such as `https://cdn.example.com`.
+    ///
+    /// The headers, query and auth of the domain are sent there too.
+    pub fn allow_origin(
+        &mut self,
+        origin: &str,
+    ) {
+        self.info.borrow_mut().allow_origin(origin);
+    }
+
     pub fn method(
         &self,
         method: method::Method,
@@ -92,6 +264,7 @@ impl Domain {
 #[cfg(test)]
 mod test {
     use super::*;
+    use test_server;
 
     #[test]
     fn domain_no_end_slash() {
@@ -108,7 +281,7 @@ mod test {
     #[test]
     fn domain_with_base_query() {
         let mut domain = Domain::new("https://api.example.com/");
-        domain.query(&"type", &"donkeys");
+        domain.query("type", &"donkeys").unwrap();
 
         let path = domain.get().push(&"list");
         assert_eq!(path.to_string(), "https://api.example.com/list?type=donkeys");
@@ -117,9 +290,112 @@ mod test {
     #[test]
     fn domain_with_base_query_and_path() {
         let mut domain = Domain::new("https://api.example.com/");
-        domain.query(&"type", &"donkeys");
+        domain.query("type", &"donkeys").unwrap();
 
-        let path = domain.get().push(&"list").query(&"length", &"long");
+        let path = domain.get().push(&"list").query("length", &"long");
         assert_eq!(path.to_string(), "https://api.example.com/list?type=donkeys&length=long");
     }
+
+    #[test]
+    fn secret_query_is_masked() {
+        let mut domain = Domain::new("https://api.example.com/");
+        domain.secret_query("app_token", "abc123");
+
+        let path = domain.get().push(&"list").query("page", &2);
+        assert_eq!(path.to_string(), "https://api.example.com/list?app_token=REDACTED&page=2");
+        assert!(!format!("{:?}", domain).contains("abc123"));
+    }
+
+    #[test]
+    fn debug_hides_auth() {
+        let mut domain = Domain::new("https://api.example.com/");
+        domain.bearer_auth("my-token");
+
+        let debug = format!("{:?}", domain);
+        assert!(!debug.contains("my-token"));
+        assert!(debug.contains("REDACTED"));
+    }
+
+    #[test]
+    fn follow_keeps_domain_headers_and_auth() {
+        let server = test_server::TestServer::new(|request| {
+            let link = "</items?page=2>; rel=\"next\"";
+            let body: &[u8] = match request.path.as_str() {
+                "/items?key=abc" => b"[1]",
+                _ => b"[2]",
+            };
+
+            test_server::response(200, &[("Link", link)], body)
+        });
+        let mut domain = Domain::new(&server.url());
+        domain.header("X-Client", &"burgundy");
+        domain.bearer_auth("token");
+        domain.secret_query("key", "abc");
+
+        let (_, meta) =
+            domain.get().push(&"items").execute_as_json_with_meta::<(), Vec<u32>>(None).unwrap();
+        let next = &meta.links()[0];
+        let items: Vec<u32> = domain.follow(next).unwrap().execute_as_json::<(), _>(None).unwrap();
+
+        let request = &server.requests()[1];
+        assert_eq!(items, vec![2]);
+        assert_eq!(request.path, "/items?key=abc&page=2");
+        assert_eq!(request.header("X-Client"), Some("burgundy"));
+        assert_eq!(request.header("Authorization"), Some("Bearer token"));
+    }
+
+    #[test]
+    fn follow_refuses_other_domains() {
+        let domain = Domain::new("https://api.example.com");
+        let link = link::Link {
+            url: "https://api.example.com.evil.com/steal".to_string(),
+            params: Vec::new(),
+        };
+
+        match domain.follow(&link) {
+            Err(error::Error::UrlNotOnDomain {
+                ..
+            }) => {},
+            other => panic!("expected url not on domain, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn get_url_accepts_absolute_and_root_relative_urls() {
+        let mut domain = Domain::new("https://api.example.com/v1/");
+        domain.query("key", &"abc").unwrap();
+
+        let absolute =
+            domain.get_url("https://API.example.com/v1/items?key=abc&page=2#top").unwrap();
+        let relative = domain.get_url("/v1/items?page=2").unwrap().query("sort", &"name");
+        let outside_base = domain.get_url("/v2/items").unwrap();
+
+        assert_eq!(absolute.to_string(), "https://api.example.com/v1/items?key=abc&page=2");
+        assert_eq!(
+            relative.to_string(),
+            "https://api.example.com/v1/items?key=abc&page=2&sort=name"
+        );
+        assert_eq!(outside_base.to_string(), "https://api.example.com/v2/items?key=abc");
+    }
+
+    #[test]
+    fn get_url_accepts_relative_urls_with_a_url_in_the_query() {
+        let domain = Domain::new("https://api.example.com");
+        let path = domain.get_url("/items?next=https://other.com/x").unwrap();
+
+        assert_eq!(path.to_string(), "https://api.example.com/items?next=https://other.com/x");
+    }
+
+    #[test]
+    fn get_url_only_goes_to_allowed_origins() {
+        let mut domain = Domain::new("https://api.example.com");
+        let url = "https://cdn.example.com/files/1?sig=xyz";
+        assert!(domain.get_url(url).is_err());
+        assert!(domain.get_url("http://api.example.com/items").is_err());
+
+        domain.allow_origin("https://cdn.example.com/");
+        let path = Path::from_url(&domain, method::Method::Head, url).unwrap().push(&"meta");
+
+        assert_eq!(path.to_string(), "https://cdn.example.com/files/1/meta?sig=xyz");
+    }
 }
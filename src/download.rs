@@ -0,0 +1,251 @@
+use crate::error;
+use crate::reader_response;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path;
+
+const DOWNLOAD_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How far along a download is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// The number of bytes written to disk so far.
+    pub received: u64,
+
+    /// The total size of the download, if the server gave a
+    /// `Content-Length`.
+    pub total: Option<u64>,
+}
+
+/// What is needed to pick up a download where it left off.
+///
+/// This is the size of the partially downloaded file, and the `ETag` or
+/// `Last-Modified` value it was downloaded with. The validator is sent as
+/// `If-Range`, so the server will only send the rest if the file has not
+/// changed since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ResumeState {
+    pub(crate) offset: u64,
+    pub(crate) validator: String,
+}
+
+impl ResumeState {
+    /// Looks for a partial download of `destination`, left behind by an
+    /// earlier attempt.
+    pub(crate) fn load(destination: &path::Path) -> Option<Self> {
+        let offset = fs::metadata(temp_path_for(destination)).ok()?.len();
+        let validator = fs::read_to_string(validator_path_for(destination)).ok()?;
+        let validator = validator.trim();
+
+        if offset == 0 || validator.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            offset,
+            validator: validator.to_string(),
+        })
+    }
+}
+
+/// Streams the body of the response into the file given.
+///
+/// The body is written to a temporary file next to the destination, which
+/// is then moved into place once the whole body has been received. This
+/// way a failed download never leaves a half written file at `destination`.
+pub(crate) fn download_to(
+    mut response: reader_response::ReaderResponse,
+    destination: &path::Path,
+    mut progress: impl FnMut(DownloadProgress),
+) -> Result<u64, error::Error> {
+    let total = content_length(&response);
+    let temp_path = temp_path_for(destination);
+
+    let result = fs::File::create(&temp_path)
+        .and_then(|file| write_to_file(&mut response, file, 0, total, &mut progress))
+        .and_then(|received| fs::rename(&temp_path, destination).map(|_| received));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    Ok(result?)
+}
+
+/// The same as `download_to`, only the temporary file is kept if the
+/// download fails. The response is expected to be for a request made with
+/// the `resume` state given, if there was one.
+///
+/// A `206 Partial Content` is appended onto the end of the temporary file.
+/// Anything else means the server is sending the whole body, and the
+/// download starts again from the beginning.
+pub(crate) fn resume_download_to(
+    mut response: reader_response::ReaderResponse,
+    destination: &path::Path,
+    resume: Option<ResumeState>,
+    mut progress: impl FnMut(DownloadProgress),
+) -> Result<u64, error::Error> {
+    let temp_path = temp_path_for(destination);
+    let validator_path = validator_path_for(destination);
+
+    let resume_offset = resume.and_then(|resume| {
+        if response.status == 206 && content_range_start(&response) == Some(resume.offset) {
+            Some(resume.offset)
+        } else {
+            None
+        }
+    });
+
+    if response.status == 206 && resume_offset.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "partial content does not start where the download left off",
+        )
+        .into());
+    }
+
+    let (file, offset, total) = match resume_offset {
+        Some(offset) => {
+            let total = content_range_total(&response)
+                .or_else(|| content_length(&response).map(|length| offset + length));
+            let file = fs::OpenOptions::new().append(true).open(&temp_path)?;
+
+            (file, offset, total)
+        },
+        None => {
+            match validator_for(&response) {
+                Some(validator) => fs::write(&validator_path, validator)?,
+                None => remove_if_exists(&validator_path)?,
+            }
+
+            (fs::File::create(&temp_path)?, 0, content_length(&response))
+        },
+    };
+
+    let received = write_to_file(&mut response, file, offset, total, &mut progress)?;
+    fs::rename(&temp_path, destination)?;
+    remove_if_exists(&validator_path)?;
+
+    Ok(received)
+}
+
+/// Handles a `416 Range Not Satisfiable`, from asking for the rest of a
+/// download with the `resume` state given.
+///
+/// When the `Content-Range` says the file is as long as the partial
+/// download, it is already complete, and is moved into place. Its size is
+/// returned. Otherwise the partial download is removed, so it can be
+/// started again from the beginning.
+pub(crate) fn finish_unsatisfiable_range(
+    response: &reader_response::ReaderResponse,
+    destination: &path::Path,
+    resume: &ResumeState,
+) -> io::Result<Option<u64>> {
+    let temp_path = temp_path_for(destination);
+    let validator_path = validator_path_for(destination);
+
+    if unsatisfied_range_total(response) == Some(resume.offset) {
+        fs::rename(&temp_path, destination)?;
+        remove_if_exists(&validator_path)?;
+
+        return Ok(Some(resume.offset));
+    }
+
+    remove_if_exists(&temp_path)?;
+    remove_if_exists(&validator_path)?;
+
+    Ok(None)
+}
+
+/// The file the download is written to, before being moved into place.
+pub(crate) fn temp_path_for(destination: &path::Path) -> path::PathBuf {
+    path_with_suffix(destination, ".part")
+}
+
+/// Holds the `ETag` or `Last-Modified` of a partial download.
+fn validator_path_for(destination: &path::Path) -> path::PathBuf {
+    path_with_suffix(destination, ".part.validator")
+}
+
+fn path_with_suffix(
+    destination: &path::Path,
+    suffix: &str,
+) -> path::PathBuf {
+    let mut file_name = destination.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+
+    destination.with_file_name(file_name)
+}
+
+/// `If-Range` only allows strong `ETag`s. When there isn't one,
+/// `Last-Modified` is used instead.
+fn validator_for(response: &reader_response::ReaderResponse) -> Option<String> {
+    let etag = response.headers.get("ETag").filter(|etag| !etag.starts_with("W/"));
+
+    etag.or_else(|| response.headers.get("Last-Modified")).map(|validator| validator.to_string())
+}
+
+fn content_length(response: &reader_response::ReaderResponse) -> Option<u64> {
+    response.headers.get("Content-Length").and_then(|length| length.trim().parse().ok())
+}
+
+/// Parses the start from a `Content-Range: bytes start-end/total` header.
+fn content_range_start(response: &reader_response::ReaderResponse) -> Option<u64> {
+    let range = response.headers.get("Content-Range")?.trim().strip_prefix("bytes ")?;
+    let start = range.split('-').next()?;
+
+    start.trim().parse().ok()
+}
+
+/// Parses the total from a `Content-Range: bytes start-end/total` header.
+fn content_range_total(response: &reader_response::ReaderResponse) -> Option<u64> {
+    let total = response.headers.get("Content-Range")?.rsplit('/').next()?;
+
+    total.trim().parse().ok()
+}
+
+/// Parses the total from a `Content-Range: bytes */total` header.
+fn unsatisfied_range_total(response: &reader_response::ReaderResponse) -> Option<u64> {
+    let total = response.headers.get("Content-Range")?.trim().strip_prefix("bytes */")?;
+
+    total.trim().parse().ok()
+}
+
+fn remove_if_exists(path: &path::Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn write_to_file(
+    response: &mut reader_response::ReaderResponse,
+    mut file: fs::File,
+    offset: u64,
+    total: Option<u64>,
+    progress: &mut impl FnMut(DownloadProgress),
+) -> io::Result<u64> {
+    let mut buffer = vec![0; DOWNLOAD_BUFFER_SIZE];
+    let mut received = offset;
+
+    loop {
+        let len = response.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..len])?;
+        received += len as u64;
+
+        progress(DownloadProgress {
+            received,
+            total,
+        });
+    }
+
+    file.sync_all()?;
+
+    Ok(received)
+}
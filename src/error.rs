@@ -1,98 +1,747 @@
+use codec;
 use http;
 use hyper;
+use method;
+use request_context;
+use request_context::RequestContext;
 use response;
-use serde_json;
 use serde_urlencoded;
 use std::error;
 use std::fmt;
+use std::io;
 use std::result;
 
 pub type Result<T> = result::Result<T, self::Error>;
 
 /// Represents the errors possible to fall out from Burgundy.
-#[derive(Fail, Debug)]
+///
+/// Errors returned from executing a `Path` hold the request which failed.
+/// This is available from `Error::request`, and is included when the
+/// error is displayed.
+///
+/// `Error` implements `std::error::Error`. Through that it is also a
+/// `failure::Fail`, for code using the `failure` crate. The error which
+/// caused it, if any, is returned from `source` rather than repeated in
+/// the message.
+#[derive(Debug)]
 pub enum Error {
-    #[fail(display = "Error whilst formatting")]
     FormatError {
         /// The underlying error.
-        #[cause]
         error: fmt::Error,
+
+        request: Option<RequestContext>,
     },
 
-    #[fail(display = "Failed to deserialize response")]
     DeserializationError {
-        /// The underlying error.
-        #[cause]
-        error: serde_json::Error,
+        /// The underlying error. This holds the path to the field which
+        /// failed, and the line and column, where they are known.
+        error: codec::CodecError,
 
         /// The text that was send to Serde. Useful for debugging.
+        ///
+        /// When deserializing at a JSON pointer, this is only the fragment
+        /// found at the pointer.
         text: String,
+
+        /// A short part of `text`, around where deserialization failed.
+        excerpt: String,
+
+        /// The JSON pointer being deserialized, if there was one.
+        pointer: Option<String>,
+
+        request: Option<RequestContext>,
+    },
+
+    PointerNotFound {
+        pointer: String,
+
+        request: Option<RequestContext>,
     },
 
-    #[fail(display = "Error serializing the blob into a query {}", error)]
     SerializeQueryError {
         /// The underlying error.
-        #[cause]
         error: serde_urlencoded::ser::Error,
+
+        request: Option<RequestContext>,
     },
 
-    #[fail(display = "Failed to serialize the body for sending {}", error)]
     SerializeBodyError {
         /// The underlying error.
-        #[cause]
-        error: serde_json::Error,
+        error: codec::CodecError,
+
+        request: Option<RequestContext>,
     },
 
-    #[fail(display = "Http error {}", error)]
     HttpError {
         /// The underlying error.
-        #[cause]
         error: http::Error,
+
+        request: Option<RequestContext>,
+    },
+
+    IoError {
+        /// The underlying error.
+        error: io::Error,
+
+        request: Option<RequestContext>,
     },
 
-    #[fail(display = "Network error {}", error)]
     NetworkError {
         /// The underlying error.
-        #[cause]
         error: hyper::Error,
+
+        request: Option<RequestContext>,
     },
 
     /// For HTTP requests which do not return 200.
-    #[fail(display = "Http request was not ok, status {}", status)]
     RequestNotOk {
         status: u32,
         body: String,
+
+        request: Option<RequestContext>,
+    },
+
+    /// For an absolute url, such as the next page of results, which does
+    /// not belong to the `Domain` it would be requested through.
+    UrlNotOnDomain {
+        url: String,
+
+        request: Option<RequestContext>,
     },
+
+    /// For a URI template, such as one given to `Path::expand`, which could
+    /// not be parsed, or a variable which could not be expanded.
+    InvalidUriTemplate {
+        template: String,
+        reason: String,
+
+        request: Option<RequestContext>,
+    },
+
+    /// For a variable used in a URI template, which was not given.
+    MissingTemplateVariable {
+        template: String,
+        variable: String,
+
+        request: Option<RequestContext>,
+    },
+
+    /// For a `412 Precondition Failed`. This is when a conditional request,
+    /// such as one using `Path::if_match`, does not match what is on the
+    /// server.
+    PreconditionFailed {
+        body: String,
+
+        request: Option<RequestContext>,
+    },
+
+    /// For a `TlsConfig` which could not be used, such as a certificate
+    /// which is not valid PEM.
+    TlsError {
+        reason: String,
+
+        request: Option<RequestContext>,
+    },
+
+    /// For a `Proxy` url which could not be used.
+    InvalidProxy {
+        reason: String,
+
+        request: Option<RequestContext>,
+    },
+}
+
+/// The broad category of an `Error`.
+///
+/// This allows callers to decide how to handle an error, without matching
+/// on every variant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request could not be built.
+    Request,
+
+    /// The request body or query could not be serialized.
+    Serialization,
+
+    /// The response could not be deserialized.
+    Deserialization,
+
+    /// Reading or writing a local file failed.
+    Io,
+
+    /// The request timed out.
+    Timeout,
+
+    /// A connection to the server could not be made.
+    Connect,
+
+    /// Something went wrong sending the request, or receiving the response.
+    Transport,
+
+    /// The server responded with a 4xx status.
+    ClientError,
+
+    /// The server responded with a 5xx status.
+    ServerError,
+
+    /// The server responded with a status which was not ok, and is not a
+    /// client or server error.
+    UnexpectedStatus,
 }
 
+/// Statuses where trying the same request again may work.
+const RETRYABLE_STATUSES: &[u32] = &[408, 425, 429, 500, 502, 503, 504];
+
 impl Error {
+    /// The broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::FormatError {
+                ..
+            }
+            | Error::HttpError {
+                ..
+            }
+            | Error::UrlNotOnDomain {
+                ..
+            }
+            | Error::InvalidUriTemplate {
+                ..
+            }
+            | Error::MissingTemplateVariable {
+                ..
+            }
+            | Error::TlsError {
+                ..
+            }
+            | Error::InvalidProxy {
+                ..
+            } => ErrorKind::Request,
+            Error::SerializeQueryError {
+                ..
+            }
+            | Error::SerializeBodyError {
+                ..
+            } => ErrorKind::Serialization,
+            Error::DeserializationError {
+                ..
+            }
+            | Error::PointerNotFound {
+                ..
+            } => ErrorKind::Deserialization,
+            Error::IoError {
+                error,
+                ..
+            } => {
+                if error.kind() == io::ErrorKind::TimedOut {
+                    ErrorKind::Timeout
+                } else {
+                    ErrorKind::Io
+                }
+            },
+            Error::NetworkError {
+                error,
+                ..
+            } => {
+                if is_timed_out(error) {
+                    ErrorKind::Timeout
+                } else if error.is_connect() {
+                    ErrorKind::Connect
+                } else {
+                    ErrorKind::Transport
+                }
+            },
+            Error::RequestNotOk {
+                status,
+                ..
+            } => match status {
+                400..=499 => ErrorKind::ClientError,
+                500..=599 => ErrorKind::ServerError,
+                _ => ErrorKind::UnexpectedStatus,
+            },
+            Error::PreconditionFailed {
+                ..
+            } => ErrorKind::ClientError,
+        }
+    }
+
+    /// The status of the response, if the error is from a response which
+    /// was not ok.
+    pub fn status(&self) -> Option<u32> {
+        match self {
+            Error::RequestNotOk {
+                status,
+                ..
+            } => Some(*status),
+            Error::PreconditionFailed {
+                ..
+            } => Some(412),
+            _ => None,
+        }
+    }
+
+    /// True if the request or connection timed out.
+    pub fn is_timeout(&self) -> bool {
+        self.kind() == ErrorKind::Timeout
+    }
+
+    /// True if a connection to the server could not be made.
+    pub fn is_connect(&self) -> bool {
+        self.kind() == ErrorKind::Connect
+    }
+
+    /// True if the server responded with a 4xx status.
+    pub fn is_client_error(&self) -> bool {
+        self.kind() == ErrorKind::ClientError
+    }
+
+    /// True if the server responded with a 5xx status.
+    pub fn is_server_error(&self) -> bool {
+        self.kind() == ErrorKind::ServerError
+    }
+
+    /// True if making the same request again might succeed.
+    ///
+    /// This covers timeouts, connection and transport failures, and
+    /// statuses such as `429 Too Many Requests` or `503 Service
+    /// Unavailable`.
+    pub fn is_retryable(&self) -> bool {
+        match self.kind() {
+            ErrorKind::Timeout | ErrorKind::Connect | ErrorKind::Transport => true,
+            ErrorKind::ClientError | ErrorKind::ServerError => {
+                self.status().is_some_and(|status| RETRYABLE_STATUSES.contains(&status))
+            },
+            _ => false,
+        }
+    }
+
+    /// The request this error came from, if it came from a request.
+    pub fn request(&self) -> Option<&RequestContext> {
+        match self {
+            Error::FormatError {
+                request,
+                ..
+            }
+            | Error::DeserializationError {
+                request,
+                ..
+            }
+            | Error::PointerNotFound {
+                request,
+                ..
+            }
+            | Error::SerializeQueryError {
+                request,
+                ..
+            }
+            | Error::SerializeBodyError {
+                request,
+                ..
+            }
+            | Error::HttpError {
+                request,
+                ..
+            }
+            | Error::IoError {
+                request,
+                ..
+            }
+            | Error::NetworkError {
+                request,
+                ..
+            }
+            | Error::RequestNotOk {
+                request,
+                ..
+            }
+            | Error::PreconditionFailed {
+                request,
+                ..
+            }
+            | Error::UrlNotOnDomain {
+                request,
+                ..
+            }
+            | Error::InvalidUriTemplate {
+                request,
+                ..
+            }
+            | Error::MissingTemplateVariable {
+                request,
+                ..
+            }
+            | Error::TlsError {
+                request,
+                ..
+            }
+            | Error::InvalidProxy {
+                request,
+                ..
+            } => request.as_ref(),
+        }
+    }
+
+    /// The method of the request which failed.
+    pub fn method(&self) -> Option<method::Method> {
+        self.request().map(|request| request.method)
+    }
+
+    /// The url of the request which failed. Sensitive query parameters are
+    /// redacted.
+    pub fn url(&self) -> Option<&str> {
+        self.request().map(|request| request.url.as_str())
+    }
+
+    /// Which attempt at the request failed, starting from 1.
+    pub fn attempt(&self) -> Option<u32> {
+        self.request().map(|request| request.attempt)
+    }
+
+    /// A copy of this error, for errors from building a url. These are
+    /// errors from formatting a path or query value, or from serializing
+    /// query parameters. Anything else cannot be copied.
+    pub(crate) fn try_clone(&self) -> Option<Self> {
+        match self {
+            Error::FormatError {
+                error,
+                request,
+            } => Some(Error::FormatError {
+                error: *error,
+                request: request.clone(),
+            }),
+            Error::SerializeQueryError {
+                error,
+                request,
+            } => Some(Error::SerializeQueryError {
+                error: error.clone(),
+                request: request.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Sets the request this error came from.
+    pub(crate) fn with_request(
+        mut self,
+        context: RequestContext,
+    ) -> Self {
+        match self {
+            Error::FormatError {
+                ref mut request,
+                ..
+            }
+            | Error::DeserializationError {
+                ref mut request,
+                ..
+            }
+            | Error::PointerNotFound {
+                ref mut request,
+                ..
+            }
+            | Error::SerializeQueryError {
+                ref mut request,
+                ..
+            }
+            | Error::SerializeBodyError {
+                ref mut request,
+                ..
+            }
+            | Error::HttpError {
+                ref mut request,
+                ..
+            }
+            | Error::IoError {
+                ref mut request,
+                ..
+            }
+            | Error::NetworkError {
+                ref mut request,
+                ..
+            }
+            | Error::RequestNotOk {
+                ref mut request,
+                ..
+            }
+            | Error::PreconditionFailed {
+                ref mut request,
+                ..
+            }
+            | Error::UrlNotOnDomain {
+                ref mut request,
+                ..
+            }
+            | Error::InvalidUriTemplate {
+                ref mut request,
+                ..
+            }
+            | Error::MissingTemplateVariable {
+                ref mut request,
+                ..
+            }
+            | Error::TlsError {
+                ref mut request,
+                ..
+            }
+            | Error::InvalidProxy {
+                ref mut request,
+                ..
+            } => *request = Some(context),
+        }
+
+        self
+    }
+
     /// Creates a new deserialization error.
     pub(crate) fn new_deserialization_error(
-        error: serde_json::Error,
+        error: codec::CodecError,
         text: String,
     ) -> Self {
+        let excerpt = excerpt_around(&text, error.line(), error.column());
+
         Error::DeserializationError {
             error,
             text,
+            excerpt,
+            pointer: None,
+            request: None,
+        }
+    }
+
+    /// Creates a new deserialization error, for the fragment found at a
+    /// JSON pointer.
+    pub(crate) fn new_pointer_deserialization_error(
+        error: codec::CodecError,
+        fragment: String,
+        pointer: &str,
+    ) -> Self {
+        let excerpt = excerpt_around(&fragment, error.line(), error.column());
+
+        Error::DeserializationError {
+            error,
+            text: fragment,
+            excerpt,
+            pointer: Some(pointer.to_string()),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_pointer_not_found(pointer: &str) -> Self {
+        Error::PointerNotFound {
+            pointer: pointer.to_string(),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_url_not_on_domain(url: &str) -> Self {
+        Error::UrlNotOnDomain {
+            url: request_context::redact_url(url),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_invalid_uri_template(
+        template: &str,
+        reason: impl Into<String>,
+    ) -> Self {
+        Error::InvalidUriTemplate {
+            template: template.to_string(),
+            reason: reason.into(),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_missing_template_variable(
+        template: &str,
+        variable: &str,
+    ) -> Self {
+        Error::MissingTemplateVariable {
+            template: template.to_string(),
+            variable: variable.to_string(),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_tls_error(reason: impl Into<String>) -> Self {
+        Error::TlsError {
+            reason: reason.into(),
+            request: None,
+        }
+    }
+
+    pub(crate) fn new_invalid_proxy(reason: impl Into<String>) -> Self {
+        Error::InvalidProxy {
+            reason: reason.into(),
+            request: None,
         }
     }
 
     pub(crate) fn new_request_not_ok(response: response::Response) -> Self {
-        Error::RequestNotOk {
-            status: response.status,
-            body: response.body,
+        let body = response.text().into_owned();
+
+        match response.status {
+            412 => Error::PreconditionFailed {
+                body,
+                request: None,
+            },
+            status => Error::RequestNotOk {
+                status,
+                body,
+                request: None,
+            },
         }
     }
 
     pub(crate) fn new_serialize_query_error(error: serde_urlencoded::ser::Error) -> Self {
         Error::SerializeQueryError {
             error,
+            request: None,
         }
     }
 
-    pub(crate) fn new_serialize_body_error(error: serde_json::Error) -> Self {
+    pub(crate) fn new_serialize_body_error(error: codec::CodecError) -> Self {
         Error::SerializeBodyError {
             error,
+            request: None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Error::FormatError {
+                ..
+            } => write!(f, "Error whilst formatting")?,
+            Error::DeserializationError {
+                error,
+                excerpt,
+                ..
+            } => {
+                write!(f, "Failed to deserialize response")?;
+                if let Some(path) = error.path() {
+                    write!(f, " at {}", path)?;
+                }
+                if let (Some(line), Some(column)) = (error.line(), error.column()) {
+                    write!(f, ", line {} column {}", line, column)?;
+                }
+                write!(f, ", near `{}`", excerpt)?
+            },
+            Error::PointerNotFound {
+                pointer,
+                ..
+            } => write!(f, "Nothing found in response at JSON pointer '{}'", pointer)?,
+            Error::SerializeQueryError {
+                ..
+            } => write!(f, "Error serializing the blob into a query")?,
+            Error::SerializeBodyError {
+                ..
+            } => write!(f, "Failed to serialize the body for sending")?,
+            Error::HttpError {
+                ..
+            } => write!(f, "Http error")?,
+            Error::IoError {
+                ..
+            } => write!(f, "IO error")?,
+            Error::NetworkError {
+                ..
+            } => write!(f, "Network error")?,
+            Error::RequestNotOk {
+                status,
+                ..
+            } => write!(f, "Http request was not ok, status {}", status)?,
+            Error::PreconditionFailed {
+                ..
+            } => write!(f, "Precondition failed, the resource has changed")?,
+            Error::UrlNotOnDomain {
+                url,
+                ..
+            } => write!(f, "Url {} is not on the domain", url)?,
+            Error::InvalidUriTemplate {
+                template,
+                reason,
+                ..
+            } => write!(f, "Invalid URI template '{}', {}", template, reason)?,
+            Error::MissingTemplateVariable {
+                template,
+                variable,
+                ..
+            } => write!(f, "Variable '{}' missing for URI template '{}'", variable, template)?,
+            Error::TlsError {
+                reason,
+                ..
+            } => write!(f, "TLS config error, {}", reason)?,
+            Error::InvalidProxy {
+                reason,
+                ..
+            } => write!(f, "Invalid proxy, {}", reason)?,
+        }
+
+        if let Some(request) = self.request() {
+            write!(f, ", for {}", request)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::FormatError {
+                error,
+                ..
+            } => Some(error),
+            Error::DeserializationError {
+                error,
+                ..
+            } => Some(error),
+            Error::SerializeQueryError {
+                error,
+                ..
+            } => Some(error),
+            Error::SerializeBodyError {
+                error,
+                ..
+            } => Some(error),
+            Error::HttpError {
+                error,
+                ..
+            } => Some(error),
+            Error::IoError {
+                error,
+                ..
+            } => Some(error),
+            Error::NetworkError {
+                error,
+                ..
+            } => Some(error),
+            Error::PointerNotFound {
+                ..
+            }
+            | Error::RequestNotOk {
+                ..
+            }
+            | Error::PreconditionFailed {
+                ..
+            }
+            | Error::UrlNotOnDomain {
+                ..
+            }
+            | Error::InvalidUriTemplate {
+                ..
+            }
+            | Error::MissingTemplateVariable {
+                ..
+            }
+            | Error::TlsError {
+                ..
+            }
+            | Error::InvalidProxy {
+                ..
+            } => None,
         }
     }
 }
@@ -101,6 +750,7 @@ impl From<fmt::Error> for Error {
     fn from(error: fmt::Error) -> Self {
         Error::FormatError {
             error,
+            request: None,
         }
     }
 }
@@ -109,6 +759,16 @@ impl From<http::Error> for Error {
     fn from(error: http::Error) -> Self {
         Error::HttpError {
             error,
+            request: None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::IoError {
+            error,
+            request: None,
         }
     }
 }
@@ -117,25 +777,171 @@ impl From<hyper::Error> for Error {
     fn from(error: hyper::Error) -> Self {
         Error::NetworkError {
             error,
+            request: None,
         }
     }
 }
 
-/// Sometimes we have to add an error, even though we know it will never
-/// happen.
-///
-/// This is for those times.
-#[derive(Debug)]
-pub(crate) struct UnreachableError;
+/// Looks through the causes of a network error, for an IO timeout.
+fn is_timed_out(error: &hyper::Error) -> bool {
+    let mut cause = error::Error::source(error);
 
-impl error::Error for UnreachableError {
+    while let Some(err) = cause {
+        if let Some(io_error) = err.downcast_ref::<io::Error>() {
+            if io_error.kind() == io::ErrorKind::TimedOut {
+                return true;
+            }
+        }
+
+        cause = err.source();
+    }
+
+    false
 }
 
-impl fmt::Display for UnreachableError {
-    fn fmt(
-        &self,
-        f: &mut fmt::Formatter,
-    ) -> fmt::Result {
-        write!(f, "This error should never be reached. If you can see it, then please report it as a bug.")
+/// The number of characters either side of an error, to include in an
+/// excerpt.
+const EXCERPT_RADIUS: usize = 40;
+
+/// Cuts out the text around the line and column given.
+/// When there is no position, this is the start of the text.
+fn excerpt_around(
+    text: &str,
+    line: Option<usize>,
+    column: Option<usize>,
+) -> String {
+    let position = match (line, column) {
+        (Some(line), Some(column)) => offset_of(text, line, column),
+        _ => 0,
+    };
+
+    let mut start = position.saturating_sub(EXCERPT_RADIUS);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = (position + EXCERPT_RADIUS).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let prefix = if start > 0 {
+        "..."
+    } else {
+        ""
+    };
+    let suffix = if end < text.len() {
+        "..."
+    } else {
+        ""
+    };
+
+    format!("{}{}{}", prefix, &text[start..end], suffix)
+}
+
+/// Turns a line and column, which start from 1, into a byte offset.
+fn offset_of(
+    text: &str,
+    line: usize,
+    column: usize,
+) -> usize {
+    let line_start: usize =
+        text.split('\n').take(line.saturating_sub(1)).map(|l| l.len() + 1).sum();
+
+    (line_start + column.saturating_sub(1)).min(text.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use failure;
+
+    fn not_ok(status: u32) -> Error {
+        Error::RequestNotOk {
+            status,
+            body: String::new(),
+            request: None,
+        }
+    }
+
+    #[test]
+    fn status_errors_are_classified() {
+        assert_eq!(not_ok(404).kind(), ErrorKind::ClientError);
+        assert_eq!(not_ok(404).status(), Some(404));
+        assert!(not_ok(404).is_client_error());
+        assert!(!not_ok(404).is_retryable());
+
+        assert_eq!(not_ok(503).kind(), ErrorKind::ServerError);
+        assert!(not_ok(503).is_server_error());
+        assert!(not_ok(503).is_retryable());
+        assert!(not_ok(429).is_retryable());
+        assert!(!not_ok(501).is_retryable());
+
+        assert_eq!(not_ok(302).kind(), ErrorKind::UnexpectedStatus);
+    }
+
+    #[test]
+    fn precondition_failed_has_its_own_variant() {
+        let error = Error::new_request_not_ok(response::Response {
+            body: b"stale".to_vec(),
+            status: 412,
+            headers: Default::default(),
+        });
+
+        match error {
+            Error::PreconditionFailed {
+                ref body,
+                ..
+            } => assert_eq!(body, "stale"),
+            ref other => panic!("expected precondition failed, got {:?}", other),
+        }
+        assert_eq!(error.status(), Some(412));
+        assert!(error.is_client_error());
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn io_timeouts_are_classified() {
+        let error = Error::from(io::Error::new(io::ErrorKind::TimedOut, "too slow"));
+
+        assert!(error.is_timeout());
+        assert!(error.is_retryable());
+        assert_eq!(error.status(), None);
+    }
+
+    #[test]
+    fn error_is_std_error_and_fail() {
+        fn as_std_error(error: &Error) -> &dyn error::Error {
+            error
+        }
+
+        fn as_fail(error: &Error) -> &dyn failure::Fail {
+            error
+        }
+
+        let error = Error::from(io::Error::other("disk full"));
+
+        assert_eq!(as_std_error(&error).source().unwrap().to_string(), "disk full");
+        assert_eq!(as_fail(&error).to_string(), "IO error");
+    }
+
+    #[test]
+    fn excerpt_of_short_text_is_whole_text() {
+        assert_eq!(excerpt_around("[1, 2, 3]", Some(1), Some(5)), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn excerpt_is_cut_around_position() {
+        let text = format!("{}\n{}{}", "a".repeat(100), "b".repeat(60), "c".repeat(60));
+        let excerpt = excerpt_around(&text, Some(2), Some(61));
+
+        assert_eq!(excerpt, format!("...{}{}...", "b".repeat(40), "c".repeat(40)));
+    }
+
+    #[test]
+    fn excerpt_without_position_is_start_of_text() {
+        let text = "x".repeat(100);
+
+        assert_eq!(excerpt_around(&text, None, None), format!("{}...", "x".repeat(40)));
     }
 }
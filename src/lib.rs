@@ -2,19 +2,48 @@
 #![warn(unreachable_pub)]
 #![deny(trivial_casts, trivial_numeric_casts, unused_extern_crates, unused_import_braces)]
 
-#[macro_use]
-extern crate failure;
+extern crate base64;
+#[cfg(feature = "derive")]
+extern crate burgundy_derive;
+pub extern crate erased_serde;
 extern crate futures;
+extern crate hex;
+extern crate hmac;
 extern crate http;
+extern crate httpdate;
 extern crate hyper;
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
 extern crate hyper_tls;
 extern crate log;
 extern crate serde;
 extern crate serde_json;
+extern crate serde_path_to_error;
 extern crate serde_urlencoded;
+extern crate sha2;
 extern crate tokio;
+extern crate url;
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+extern crate native_tls;
+
+#[cfg(any(test, feature = "rustls"))]
+extern crate rustls;
+#[cfg(feature = "rustls")]
+extern crate webpki;
+#[cfg(feature = "rustls")]
+extern crate webpki_roots;
+
+#[cfg(feature = "msgpack")]
+extern crate rmp_serde;
+
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
+
+#[cfg(feature = "xml")]
+extern crate quick_xml;
 
 mod native_client;
+mod percent_encoding;
 mod request_information;
 
 mod method;
@@ -23,16 +52,109 @@ pub use method::Method;
 mod path;
 pub use path::Path;
 
+mod uri_template;
+
+mod link;
+pub use link::Link;
+
+mod paginate;
+pub use paginate::PageStrategy;
+pub use paginate::Paginator;
+
 mod domain;
 pub use domain::Domain;
 
+mod proxy;
+pub use proxy::Proxy;
+
+mod tls;
+pub use tls::TlsConfig;
+pub use tls::TlsVersion;
+
+#[cfg(feature = "derive")]
+pub use burgundy_derive::api;
+
+mod codec;
+pub use codec::Codec;
+pub use codec::CodecError;
+pub use codec::CodecVisit;
+pub use codec::FormCodec;
+pub use codec::Format;
+pub use codec::JsonCodec;
+
+#[cfg(feature = "msgpack")]
+pub use codec::MessagePackCodec;
+
+#[cfg(feature = "cbor")]
+pub use codec::CborCodec;
+
+#[cfg(feature = "xml")]
+pub use codec::XmlCodec;
+
 mod error;
 pub use error::Error;
+pub use error::ErrorKind;
 pub use error::Result;
 
+mod request_context;
+pub use request_context::RequestContext;
+
+mod auth;
+pub use auth::AuthProvider;
+
+pub mod cache;
+pub mod oauth2;
+
+mod secret;
+pub use secret::Secret;
+
+mod request;
+pub use request::Request;
+
+mod middleware;
+pub use middleware::Middleware;
+pub use middleware::Next;
+
+mod signing;
+pub use signing::HmacSigner;
+pub use signing::RequestSigner;
+pub use signing::SignatureEncoding;
+
+#[cfg(feature = "aws-sigv4")]
+mod aws_sigv4;
+#[cfg(feature = "aws-sigv4")]
+pub use aws_sigv4::AwsSigV4Signer;
+
 mod response;
 pub use response::Response;
 
+mod response_meta;
+pub use response_meta::ResponseMeta;
+
+mod response_headers;
+pub use response_headers::ResponseHeaders;
+
+mod stream_response;
+pub use stream_response::BodyStream;
+pub use stream_response::StreamResponse;
+
+mod reader_response;
+pub use reader_response::ReaderResponse;
+
+mod download;
+pub use download::DownloadProgress;
+
+#[cfg(test)]
+extern crate failure;
+
+#[cfg(test)]
+extern crate rcgen;
+
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
+
+#[cfg(test)]
+mod test_dir;
+#[cfg(test)]
+mod test_server;
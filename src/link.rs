@@ -0,0 +1,189 @@
+/// One link from a `Link` header, as described in RFC 8288.
+///
+/// A link can be requested using `Domain::follow`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The target of the link, as it was written in the header. This may
+    /// be relative to the url of the request.
+    pub url: String,
+
+    /// The parameters after the url, such as `rel` and `title`. Names are
+    /// lowercase, and quotes are removed from the values.
+    pub params: Vec<(String, String)>,
+}
+
+impl Link {
+    /// The first value of the parameter given.
+    pub fn param(
+        &self,
+        name: &str,
+    ) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// True if the link has the relation given, such as `next`.
+    /// A link may have many relations, separated by spaces.
+    pub fn has_rel(
+        &self,
+        rel: &str,
+    ) -> bool {
+        self.param("rel")
+            .map(|rels| rels.split_whitespace().any(|value| value.eq_ignore_ascii_case(rel)))
+            .unwrap_or(false)
+    }
+}
+
+/// Parses all of the links in a `Link` header.
+///
+/// Links which cannot be parsed are skipped.
+pub(crate) fn parse_links(header: &str) -> Vec<Link> {
+    split_outside_quotes(header, ',').into_iter().filter_map(parse_link).collect()
+}
+
+fn parse_link(text: &str) -> Option<Link> {
+    let text = text.trim();
+    let url_end = text.find('>')?;
+    let url = text.strip_prefix('<')?[..url_end - 1].trim().to_string();
+
+    let params = split_outside_quotes(&text[url_end + 1..], ';')
+        .into_iter()
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .map(|value| value.replace("\\\"", "\""))
+                .unwrap_or_else(|| value.to_string());
+
+            (name.trim().to_ascii_lowercase(), value)
+        })
+        .collect();
+
+    Some(Link {
+        url,
+        params,
+    })
+}
+
+/// Splits on the separator, ignoring any inside quotes or `<...>`.
+fn split_outside_quotes(
+    text: &str,
+    separator: char,
+) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut in_url = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' if !in_url => in_quotes = !in_quotes,
+            '<' if !in_quotes => in_url = true,
+            '>' if !in_quotes => in_url = false,
+            _ if c == separator && !in_quotes && !in_url => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+    parts.push(&text[start..]);
+
+    parts
+}
+
+/// Turns a link into an absolute url, using the url of the request it came
+/// from. Links are either absolute, or relative to the root of the host.
+pub(crate) fn resolve(
+    base: &str,
+    url: &str,
+) -> String {
+    if has_scheme(url) {
+        return url.to_string();
+    }
+
+    let origin_end = base
+        .find("://")
+        .map(|scheme_end| {
+            let host_start = scheme_end + 3;
+            base[host_start..].find(['/', '?']).map(|i| host_start + i).unwrap_or(base.len())
+        })
+        .unwrap_or(0);
+    let origin = &base[..origin_end];
+
+    if url.starts_with('/') {
+        format!("{}{}", origin, url)
+    } else {
+        let path_end = base.find('?').unwrap_or(base.len());
+        let directory_end = base[..path_end].rfind('/').filter(|&i| i >= origin_end);
+
+        match directory_end {
+            Some(i) => format!("{}/{}", &base[..i], url),
+            None => format!("{}/{}", origin, url),
+        }
+    }
+}
+
+/// True if the url starts with a scheme, such as `https:`, and so is
+/// absolute.
+fn has_scheme(url: &str) -> bool {
+    let scheme = match url.find(':') {
+        Some(end) => &url[..end],
+        None => return false,
+    };
+    let mut chars = scheme.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_github_style_links() {
+        let header = r#"<https://api.github.com/user/repos?page=3&per_page=100>; rel="next", <https://api.github.com/user/repos?page=50&per_page=100>; rel="last""#;
+        let links = parse_links(header);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].url, "https://api.github.com/user/repos?page=3&per_page=100");
+        assert!(links[0].has_rel("next"));
+        assert!(links[1].has_rel("last"));
+    }
+
+    #[test]
+    fn parses_params_with_commas_and_many_rels() {
+        let header =
+            r#"</chapter/2>; rel="next alternate"; title="Two, and \"more\"", </a,b>; rel=prev"#;
+        let links = parse_links(header);
+
+        assert_eq!(links.len(), 2);
+        assert!(links[0].has_rel("alternate"));
+        assert_eq!(links[0].param("title"), Some("Two, and \"more\""));
+        assert_eq!(links[1].url, "/a,b");
+        assert_eq!(links[1].param("REL"), Some("prev"));
+    }
+
+    #[test]
+    fn resolves_relative_links() {
+        let base = "https://api.example.com/v1/items?page=1";
+
+        assert_eq!(resolve(base, "https://other.com/x"), "https://other.com/x");
+        assert_eq!(resolve(base, "/v1/items?page=2"), "https://api.example.com/v1/items?page=2");
+        assert_eq!(resolve(base, "items?page=2"), "https://api.example.com/v1/items?page=2");
+        assert_eq!(
+            resolve(base, "/v1/items?next=https://other.com/x"),
+            "https://api.example.com/v1/items?next=https://other.com/x"
+        );
+    }
+}
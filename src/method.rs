@@ -1,3 +1,5 @@
+use std::fmt;
+
 /// Which type of request it being made.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(missing_docs)]
@@ -12,3 +14,24 @@ pub enum Method {
     Patch,
     Trace,
 }
+
+impl fmt::Display for Method {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let name = match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+            Method::Connect => "CONNECT",
+            Method::Patch => "PATCH",
+            Method::Trace => "TRACE",
+        };
+
+        write!(f, "{}", name)
+    }
+}
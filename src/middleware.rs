@@ -0,0 +1,58 @@
+use crate::error;
+use crate::request;
+use crate::response;
+
+/// Wraps behaviour around the requests made by a `Domain`.
+///
+/// Middleware is added using `Domain::middleware`, and each one is called
+/// in the order it was added. It is given the request, and `next` to pass
+/// the request on to the rest of the chain. It can change the request
+/// before passing it on, look at or change the response which comes back,
+/// or return a response of its own without calling `next` at all.
+///
+/// Middleware also sees streamed requests, such as `Path::execute_as_reader`
+/// or `Path::download_to`. For those `Request::is_streamed` is true, and an
+/// ok response comes back from `next` with an empty body, as the body is
+/// streamed afterwards. A response with a body returned instead is
+/// streamed in place of it.
+///
+/// `Path::execute_as_stream` skips middleware. It returns a future, and
+/// middleware runs synchronously, so it would block before the future is
+/// polled.
+pub trait Middleware {
+    fn handle(
+        &self,
+        request: request::Request,
+        next: Next,
+    ) -> Result<response::Response, error::Error>;
+}
+
+/// The rest of the middleware chain, and then the request being sent.
+pub struct Next<'a> {
+    middleware: &'a [Box<dyn Middleware>],
+    send: &'a mut dyn FnMut(request::Request) -> Result<response::Response, error::Error>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middleware: &'a [Box<dyn Middleware>],
+        send: &'a mut dyn FnMut(request::Request) -> Result<response::Response, error::Error>,
+    ) -> Self {
+        Self {
+            middleware,
+            send,
+        }
+    }
+
+    /// Passes the request on to the next middleware, or sends it if this is
+    /// the end of the chain.
+    pub fn run(
+        self,
+        request: request::Request,
+    ) -> Result<response::Response, error::Error> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware.handle(request, Next::new(rest, self.send)),
+            None => (self.send)(request),
+        }
+    }
+}
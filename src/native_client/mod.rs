@@ -1,123 +1,515 @@
-mod string_stream;
-
+use crate::auth;
 use crate::error;
 use crate::method;
+use crate::middleware;
+use crate::proxy;
+use crate::request;
 use crate::request_information;
 use crate::response;
+use crate::response_headers;
+use crate::signing;
+use crate::stream_response;
+use crate::tls;
+use futures::future;
 use futures::stream::Stream;
 use futures::Future;
 use hyper;
-use hyper_tls;
+use log::debug;
 use log::info;
+use std::fmt;
 use tokio;
 
+type HyperClient = hyper::client::Client<tls::HttpsConnector>;
+
 /// This is a wrapper around Hyper. It has two aims.
 ///
 ///  * Bunch up common code in one place.
 ///  * Keep bridge code to Hyper (or whatever) isolated in one place.
-#[derive(Debug)]
 pub(crate) struct NativeClient {
-    client: hyper::client::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    client: HyperClient,
     tokio_runtime: tokio::runtime::Runtime,
+    auth_provider: Option<Box<dyn auth::AuthProvider>>,
+    retry_unauthorized: bool,
+    signer: Option<Box<dyn signing::RequestSigner>>,
+    middleware: Vec<Box<dyn middleware::Middleware>>,
+    tls: tls::TlsConfig,
+    proxies: Vec<proxy::Proxy>,
 }
 
 impl NativeClient {
     pub(crate) fn new() -> Self {
         info!("new native client");
         let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
-        let tokio_executor = tokio_runtime.executor();
-        let https = hyper_tls::HttpsConnector::new(4).unwrap();
-        let client = hyper::client::Client::builder()
-            .executor(tokio_executor)
-            .build::<_, hyper::Body>(https);
+        let https = tls::https_connector(&tls::TlsConfig::new(), &[])
+            .expect("the default TLS config is always valid");
+        let client = hyper_client(&tokio_runtime, https);
 
         info!("done making new native client");
 
         Self {
             client,
             tokio_runtime,
+            auth_provider: None,
+            retry_unauthorized: false,
+            signer: None,
+            middleware: Vec::new(),
+            tls: tls::TlsConfig::new(),
+            proxies: Vec::new(),
+        }
+    }
+
+    /// Replaces the connections made with ones using the TLS config.
+    pub(crate) fn set_tls(
+        &mut self,
+        config: tls::TlsConfig,
+    ) -> Result<(), error::Error> {
+        let https = tls::https_connector(&config, &self.proxies)?;
+        self.client = hyper_client(&self.tokio_runtime, https);
+        self.tls = config;
+
+        Ok(())
+    }
+
+    /// Sends requests through the proxy, where it applies. Proxies added
+    /// earlier take priority.
+    pub(crate) fn add_proxies(
+        &mut self,
+        proxies: Vec<proxy::Proxy>,
+    ) -> Result<(), error::Error> {
+        let mut all_proxies = self.proxies.clone();
+        all_proxies.extend(proxies);
+
+        let https = tls::https_connector(&self.tls, &all_proxies)?;
+        self.client = hyper_client(&self.tokio_runtime, https);
+        self.proxies = all_proxies;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_auth_provider(
+        &mut self,
+        auth_provider: Box<dyn auth::AuthProvider>,
+    ) {
+        self.auth_provider = Some(auth_provider);
+    }
+
+    pub(crate) fn set_retry_unauthorized(
+        &mut self,
+        retry_unauthorized: bool,
+    ) {
+        self.retry_unauthorized = retry_unauthorized;
+    }
+
+    pub(crate) fn set_signer(
+        &mut self,
+        signer: Box<dyn signing::RequestSigner>,
+    ) {
+        self.signer = Some(signer);
+    }
+
+    pub(crate) fn add_middleware(
+        &mut self,
+        middleware: Box<dyn middleware::Middleware>,
+    ) {
+        self.middleware.push(middleware);
+    }
+
+    pub(crate) fn retries_unauthorized(&self) -> bool {
+        self.retry_unauthorized && self.auth_provider.is_some()
+    }
+
+    /// Asks the auth provider to refresh its credentials, after a request
+    /// came back unauthorized. Returns true if the request should be tried
+    /// again.
+    pub(crate) fn refresh_auth(&mut self) -> Result<bool, error::Error> {
+        if !self.retry_unauthorized {
+            return Ok(false);
+        }
+
+        match self.auth_provider {
+            Some(ref mut auth_provider) => {
+                info!("refreshing auth");
+                auth_provider.refresh()
+            },
+            None => Ok(false),
         }
     }
 
+    /// Makes the request, and reads the whole response body.
+    ///
+    /// The request is passed through the middleware before it is sent.
     pub(crate) fn request_blocking(
         &mut self,
         method: method::Method,
         domain_info: &request_information::RequestInformation,
         path_info: &request_information::RequestInformation,
-        content: Option<String>,
+        content: Option<Vec<u8>>,
     ) -> Result<response::Response, error::Error> {
         info!("making blocking request");
-        let future = self.request(method, domain_info, path_info, content)?;
+        let request = self.build_request(method, domain_info, path_info, content)?;
 
-        info!("call blocking request");
-        let response = self.tokio_runtime.block_on(future);
+        let client = &self.client;
+        let signer = self.signer.as_deref();
+        let proxies = &self.proxies;
+        let tokio_runtime = &mut self.tokio_runtime;
+        let mut send = |request| {
+            info!("call blocking request");
+            let future = send(client, signer, proxies, request)?.and_then(move |res| {
+                let status = res.status().as_u16().into();
+                let headers = headers_from_hyper(res.headers());
+                info!("transform request to response object, with status {}", status);
+
+                res.into_body().concat2().map_err(error::Error::from).map(move |body_chunk| {
+                    response::Response {
+                        body: body_chunk.to_vec(),
+                        status,
+                        headers,
+                    }
+                })
+            });
+
+            tokio_runtime.block_on(future)
+        };
+
+        let response = middleware::Next::new(&self.middleware, &mut send).run(request);
 
         info!("done making blocking request");
         response
     }
 
-    pub(crate) fn request(
+    /// Makes a request where the body is left unread, for it to be streamed.
+    ///
+    /// The request is passed through the middleware before it is sent.
+    /// Middleware sees the status and headers of a streamed response, with
+    /// an empty body.
+    pub(crate) fn request_stream_blocking(
+        &mut self,
+        method: method::Method,
+        domain_info: &request_information::RequestInformation,
+        path_info: &request_information::RequestInformation,
+        content: Option<Vec<u8>>,
+    ) -> Result<stream_response::StreamResponse, error::Error> {
+        info!("making blocking stream request");
+        let response = self.request_stream_through_middleware(
+            method,
+            domain_info,
+            path_info,
+            content,
+            is_stream_ok,
+        );
+
+        info!("done making blocking stream request");
+        response
+    }
+
+    /// The same as `request_stream_blocking`, only for resuming a download.
+    ///
+    /// `416 Range Not Satisfiable` counts as ok too, as its `Content-Range`
+    /// says how long the whole body is.
+    pub(crate) fn request_resume_blocking(
         &mut self,
         method: method::Method,
         domain_info: &request_information::RequestInformation,
         path_info: &request_information::RequestInformation,
-        body_str: Option<String>,
-    ) -> Result<impl Future<Item = response::Response, Error = error::Error>, error::Error> {
+        content: Option<Vec<u8>>,
+    ) -> Result<stream_response::StreamResponse, error::Error> {
+        info!("making blocking resume request");
+        let response = self.request_stream_through_middleware(
+            method,
+            domain_info,
+            path_info,
+            content,
+            is_resume_ok,
+        );
+
+        info!("done making blocking resume request");
+        response
+    }
+
+    /// Makes a request where the body is left unread, for it to be streamed.
+    ///
+    /// Responses which are not ok are read in full, and returned as an error.
+    /// `206 Partial Content` counts as ok, as it only comes back when a
+    /// range of the body has been asked for.
+    ///
+    /// Middleware is skipped. It runs synchronously, and so would block
+    /// before the future is polled.
+    pub(crate) fn request_stream(
+        &mut self,
+        method: method::Method,
+        domain_info: &request_information::RequestInformation,
+        path_info: &request_information::RequestInformation,
+        body: Option<Vec<u8>>,
+    ) -> Result<
+        impl Future<Item = stream_response::StreamResponse, Error = error::Error>,
+        error::Error,
+    > {
+        let request = self.build_request(method, domain_info, path_info, body)?;
+        let future = send_stream(
+            &self.client,
+            self.signer.as_deref(),
+            &self.proxies,
+            request,
+            is_stream_ok,
+        )?
+        .and_then(|response| match response {
+            StreamedResponse::Streamed(response) => Ok(response),
+            StreamedResponse::Read(response) => Err(error::Error::new_request_not_ok(response)),
+        });
+
+        info!("done making stream request");
+        Ok(future)
+    }
+
+    /// Makes a request through the middleware, where the body is left
+    /// unread when `is_ok` says the status is ok.
+    fn request_stream_through_middleware(
+        &mut self,
+        method: method::Method,
+        domain_info: &request_information::RequestInformation,
+        path_info: &request_information::RequestInformation,
+        body: Option<Vec<u8>>,
+        is_ok: fn(u32) -> bool,
+    ) -> Result<stream_response::StreamResponse, error::Error> {
+        let mut request = self.build_request(method, domain_info, path_info, body)?;
+        request.set_streamed();
+
+        let client = &self.client;
+        let signer = self.signer.as_deref();
+        let proxies = &self.proxies;
+        let tokio_runtime = &mut self.tokio_runtime;
+        let mut stream = None;
+        let mut send = |request| {
+            info!("call stream request through middleware");
+            let response =
+                tokio_runtime.block_on(send_stream(client, signer, proxies, request, is_ok)?)?;
+
+            match response {
+                StreamedResponse::Streamed(response) => {
+                    let head = response::Response {
+                        body: Vec::new(),
+                        status: response.status,
+                        headers: response.headers.clone(),
+                    };
+                    stream = Some(response);
+
+                    Ok(head)
+                },
+                StreamedResponse::Read(response) => Ok(response),
+            }
+        };
+
+        let response = middleware::Next::new(&self.middleware, &mut send).run(request);
+
+        response.and_then(|response| to_stream_response(response, stream, is_ok))
+    }
+
+    /// Builds the request from the domain and path, with the
+    /// `Authorization` header added.
+    fn build_request(
+        &mut self,
+        method: method::Method,
+        domain_info: &request_information::RequestInformation,
+        path_info: &request_information::RequestInformation,
+        body: Option<Vec<u8>>,
+    ) -> Result<request::Request, error::Error> {
         info!("make request");
-        let hyper_method = method_to_hyper(method);
         let url = request_information::to_full_url(domain_info, path_info)?;
-        let mut request_builder = hyper::Request::builder();
+        let display_url = request_information::to_display_url(domain_info, path_info)?;
+        info!("making request to {}", display_url);
 
-        info!("making request to {}", url);
-        request_builder.method(hyper_method).uri(&url);
+        let mut request = request::Request::new(method, url, display_url, body);
 
-        info!("set headers");
+        debug!("set headers");
         domain_info.for_each_header(|(key, value)| {
-            info!("set domain header '{}' to '{}'", key, value);
-            request_builder.header(key, value);
+            debug!("set domain header '{}'", key);
+            request.headers.push((key.to_string(), value.to_string()));
         });
         path_info.for_each_header(|(key, value)| {
-            info!("set path header '{}' to '{}'", key, value);
-            request_builder.header(key, value);
+            debug!("set path header '{}'", key);
+            request.headers.push((key.to_string(), value.to_string()));
         });
 
-        info!("turn request into body");
-        let body = content_to_body(body_str);
-        let request = request_builder.body(body)?;
+        let provided = match (path_info.authorization(), self.auth_provider.as_mut()) {
+            (None, Some(auth_provider)) => auth_provider.authorization()?,
+            _ => None,
+        };
+        let authorization =
+            path_info.authorization().or(provided.as_ref()).or(domain_info.authorization());
+        if let Some(authorization) = authorization {
+            debug!("set authorization header");
+            request.set_header("Authorization", &authorization.expose());
+        }
 
-        info!("make request future");
-        let future = self
-            .client
-            .request(request)
-            .and_then(|res| {
-                let status = res.status().as_u16().into();
-                info!("transform request to response object, with status {}", status);
+        Ok(request)
+    }
+}
 
-                res.into_body().concat2().map(move |body_chunk| {
-                    let body = String::from_utf8(body_chunk.to_vec()).unwrap();
+fn hyper_client(
+    tokio_runtime: &tokio::runtime::Runtime,
+    https: tls::HttpsConnector,
+) -> HyperClient {
+    hyper::client::Client::builder()
+        .executor(tokio_runtime.executor())
+        .build::<_, hyper::Body>(https)
+}
 
-                    response::Response {
-                        body,
+/// A response to a request made for streaming. Only ok responses are
+/// streamed, and the rest are read in full.
+enum StreamedResponse {
+    Streamed(stream_response::StreamResponse),
+    Read(response::Response),
+}
+
+/// Sends the request, leaving the body unread if the response is ok.
+fn send_stream(
+    client: &HyperClient,
+    signer: Option<&dyn signing::RequestSigner>,
+    proxies: &[proxy::Proxy],
+    request: request::Request,
+    is_ok: fn(u32) -> bool,
+) -> Result<impl Future<Item = StreamedResponse, Error = error::Error>, error::Error> {
+    let future = send(client, signer, proxies, request)?.and_then(move |res| {
+        let status: u32 = res.status().as_u16().into();
+        let headers = headers_from_hyper(res.headers());
+        info!("transform request to stream response, with status {}", status);
+
+        if is_ok(status) {
+            let response = stream_response::StreamResponse {
+                status,
+                headers,
+                body: stream_response::BodyStream::new(res.into_body()),
+            };
+
+            future::Either::A(future::ok(StreamedResponse::Streamed(response)))
+        } else {
+            let response =
+                res.into_body().concat2().map_err(error::Error::from).map(move |body_chunk| {
+                    StreamedResponse::Read(response::Response {
+                        body: body_chunk.to_vec(),
                         status,
-                    }
-                })
-            })
-            .map_err(error::Error::from);
+                        headers,
+                    })
+                });
 
-        info!("done making request");
-        Ok(future)
+            future::Either::B(response)
+        }
+    });
+
+    Ok(future)
+}
+
+/// Turns the response middleware returned back into a stream.
+///
+/// When it is the response sent, the body still being streamed is used.
+/// Otherwise the middleware made its own response, such as from a cache,
+/// and that body is streamed instead.
+fn to_stream_response(
+    response: response::Response,
+    stream: Option<stream_response::StreamResponse>,
+    is_ok: fn(u32) -> bool,
+) -> Result<stream_response::StreamResponse, error::Error> {
+    if !is_ok(response.status) {
+        return Err(error::Error::new_request_not_ok(response));
     }
+
+    let body = match stream {
+        Some(stream) if response.body.is_empty() => stream.body,
+        _ => stream_response::BodyStream::new(hyper::Body::from(response.body)),
+    };
+
+    Ok(stream_response::StreamResponse {
+        status: response.status,
+        headers: response.headers,
+        body,
+    })
 }
 
-pub(crate) fn content_to_body(maybe_content: Option<String>) -> hyper::Body {
+/// Signs the request, and sends it.
+///
+/// Proxy credentials are added last, so they are never signed, or seen by
+/// middleware.
+fn send(
+    client: &HyperClient,
+    signer: Option<&dyn signing::RequestSigner>,
+    proxies: &[proxy::Proxy],
+    mut request: request::Request,
+) -> Result<impl Future<Item = hyper::Response<hyper::Body>, Error = error::Error>, error::Error> {
+    if let Some(signer) = signer {
+        info!("sign request");
+        signer.sign(&mut request)?;
+    }
+
+    if let Some(authorization) = proxy::authorization_for(proxies, &request.url) {
+        debug!("set proxy authorization header");
+        request.set_header("Proxy-Authorization", &authorization.expose());
+    }
+
+    info!("turn request into body");
+    let request = request_to_hyper(request)?;
+
+    info!("make request future");
+    Ok(client.request(request).map_err(error::Error::from))
+}
+
+impl fmt::Debug for NativeClient {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("NativeClient")
+            .field("client", &self.client)
+            .field("tokio_runtime", &self.tokio_runtime)
+            .field("auth_provider", &self.auth_provider.as_ref().map(|_| ".."))
+            .field("retry_unauthorized", &self.retry_unauthorized)
+            .field("signer", &self.signer.as_ref().map(|_| ".."))
+            .field("middleware", &self.middleware.len())
+            .field("tls", &self.tls)
+            .field("proxies", &self.proxies)
+            .finish()
+    }
+}
+
+pub(crate) fn content_to_body(maybe_content: Option<Vec<u8>>) -> hyper::Body {
     if let Some(content) = maybe_content {
-        let stream = string_stream::StringStream::new(content);
-        hyper::Body::wrap_stream(stream)
+        hyper::Body::from(content)
     } else {
         hyper::Body::empty()
     }
 }
 
+fn request_to_hyper(
+    request: request::Request
+) -> Result<hyper::Request<hyper::Body>, error::Error> {
+    let mut request_builder = hyper::Request::builder();
+    request_builder.method(method_to_hyper(request.method)).uri(&request.url);
+
+    for (key, value) in &request.headers {
+        request_builder.header(key.as_str(), value.as_str());
+    }
+
+    Ok(request_builder.body(content_to_body(request.body))?)
+}
+
+fn is_stream_ok(status: u32) -> bool {
+    status == 200 || status == 206
+}
+
+fn is_resume_ok(status: u32) -> bool {
+    is_stream_ok(status) || status == 416
+}
+
+fn headers_from_hyper(headers: &hyper::HeaderMap) -> response_headers::ResponseHeaders {
+    let headers = headers
+        .iter()
+        .map(|(key, value)| {
+            (key.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+        })
+        .collect();
+
+    response_headers::ResponseHeaders::new(headers)
+}
+
 fn method_to_hyper(method: method::Method) -> hyper::Method {
     match method {
         method::Method::Get => hyper::Method::GET,
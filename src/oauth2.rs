@@ -0,0 +1,380 @@
+//! OAuth 2.0 access tokens, for use as an `AuthProvider`.
+//!
+//! Tokens are fetched from the token endpoint using either the client
+//! credentials or refresh token grant. They are cached until shortly
+//! before they expire.
+
+use crate::auth;
+use crate::codec;
+use crate::domain;
+use crate::error;
+use crate::secret;
+use serde_json;
+use std::time;
+
+/// Tokens are fetched again this long before they are due to expire, so
+/// they don't expire whilst a request is being made.
+const EXPIRY_LEEWAY: time::Duration = time::Duration::from_secs(30);
+
+/// Fetches and caches access tokens from an OAuth 2.0 token endpoint.
+///
+/// The client id and secret are sent in the form body of the token
+/// request.
+#[derive(Debug)]
+pub struct OAuth2 {
+    token_domain: domain::Domain,
+    client_id: String,
+    client_secret: secret::Secret,
+    grant: Grant,
+    scope: Option<String>,
+    token: Option<AccessToken>,
+}
+
+#[derive(Debug)]
+enum Grant {
+    ClientCredentials,
+    RefreshToken(secret::Secret),
+}
+
+#[derive(Debug)]
+struct AccessToken {
+    value: secret::Secret,
+    expires_at: Option<time::Instant>,
+}
+
+impl AccessToken {
+    fn is_fresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => time::Instant::now() + EXPIRY_LEEWAY < expires_at,
+            None => true,
+        }
+    }
+}
+
+impl OAuth2 {
+    /// Fetches tokens using the client credentials grant.
+    pub fn client_credentials(
+        token_url: &str,
+        client_id: &str,
+        client_secret: impl Into<secret::Secret>,
+    ) -> Self {
+        Self::new(token_url, client_id, client_secret.into(), Grant::ClientCredentials)
+    }
+
+    /// Fetches tokens using the refresh token grant.
+    ///
+    /// If the server hands back a new refresh token, it is used from then on.
+    pub fn refresh_token(
+        token_url: &str,
+        client_id: &str,
+        client_secret: impl Into<secret::Secret>,
+        refresh_token: impl Into<secret::Secret>,
+    ) -> Self {
+        let grant = Grant::RefreshToken(refresh_token.into());
+
+        Self::new(token_url, client_id, client_secret.into(), grant)
+    }
+
+    fn new(
+        token_url: &str,
+        client_id: &str,
+        client_secret: secret::Secret,
+        grant: Grant,
+    ) -> Self {
+        Self {
+            token_domain: domain::Domain::new(token_url),
+            client_id: client_id.to_string(),
+            client_secret,
+            grant,
+            scope: None,
+            token: None,
+        }
+    }
+
+    /// Sends token requests through the domain given, instead of one made
+    /// from the token url. This allows the token endpoint to have its own
+    /// headers, TLS settings, or proxy.
+    ///
+    /// The url of the domain is used as the token endpoint.
+    pub fn token_domain(
+        mut self,
+        domain: domain::Domain,
+    ) -> Self {
+        self.token_domain = domain;
+
+        self
+    }
+
+    /// The scope to ask for, as a space separated list.
+    pub fn scope(
+        mut self,
+        scope: &str,
+    ) -> Self {
+        self.scope = Some(scope.to_string());
+
+        self
+    }
+
+    /// The current access token. A new one is fetched if there isn't one
+    /// yet, or it is about to expire.
+    pub fn access_token(&mut self) -> Result<secret::Secret, error::Error> {
+        if let Some(ref token) = self.token {
+            if token.is_fresh() {
+                return Ok(token.value.clone());
+            }
+        }
+
+        self.fetch_token()
+    }
+
+    /// Throws away the cached token, and fetches a new one.
+    pub fn fetch_token(&mut self) -> Result<secret::Secret, error::Error> {
+        self.token = None;
+
+        let mut form = vec![
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.expose()),
+        ];
+        match self.grant {
+            Grant::ClientCredentials => {
+                form.push(("grant_type", "client_credentials"));
+            },
+            Grant::RefreshToken(ref refresh_token) => {
+                form.push(("grant_type", "refresh_token"));
+                form.push(("refresh_token", refresh_token.expose()));
+            },
+        }
+        if let Some(ref scope) = self.scope {
+            form.push(("scope", scope));
+        }
+
+        let requested_at = time::Instant::now();
+        let path = self.token_domain.post().format(codec::Format::Form);
+        let request = path.request_context();
+        let response = path.execute_as_value(Some(&form))?;
+
+        let value = match response.get("access_token").and_then(|token| token.as_str()) {
+            Some(value) => secret::Secret::new(value),
+            None => {
+                let error = codec::CodecError::new("the token response has no `access_token`");
+
+                return Err(error::Error::new_deserialization_error(
+                    error,
+                    describe_token_response(&response),
+                )
+                .with_request(request));
+            },
+        };
+        let expires_at = response
+            .get("expires_in")
+            .and_then(serde_json::Value::as_u64)
+            .map(|seconds| requested_at + time::Duration::from_secs(seconds));
+
+        if let Some(refresh_token) = response.get("refresh_token").and_then(|token| token.as_str())
+        {
+            if let Grant::RefreshToken(ref mut current) = self.grant {
+                *current = secret::Secret::new(refresh_token);
+            }
+        }
+
+        self.token = Some(AccessToken {
+            value: value.clone(),
+            expires_at,
+        });
+
+        Ok(value)
+    }
+}
+
+/// Describes a token response which has no access token, for its error.
+///
+/// Only the `error` and `error_description` fields are included, or else
+/// the names of the fields, as the response can hold secrets such as a
+/// `refresh_token`.
+fn describe_token_response(response: &serde_json::Value) -> String {
+    let fields = match response.as_object() {
+        Some(fields) => fields,
+        None => return "the response is not an object".to_string(),
+    };
+
+    let errors = ["error", "error_description"]
+        .iter()
+        .filter_map(|key| Some(format!("{}: {}", key, fields.get(*key)?.as_str()?)))
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return errors.join(", ");
+    }
+
+    let names = fields.keys().map(String::as_str).collect::<Vec<_>>();
+    format!("fields: {}", names.join(", "))
+}
+
+impl auth::AuthProvider for OAuth2 {
+    fn authorization(&mut self) -> Result<Option<secret::Secret>, error::Error> {
+        let token = self.access_token()?;
+
+        Ok(Some(secret::bearer_authorization(&token)))
+    }
+
+    fn refresh(&mut self) -> Result<bool, error::Error> {
+        self.fetch_token()?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::auth::AuthProvider;
+    use crate::codec::Codec;
+    use crate::test_server;
+
+    const JSON: &[(&str, &str)] = &[("Content-Type", "application/json")];
+
+    fn form_value(
+        request: &test_server::ReceivedRequest,
+        key: &str,
+    ) -> Option<String> {
+        let form: Vec<(String, String)> = codec::FormCodec.deserialize(&request.body).unwrap();
+
+        form.into_iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    #[test]
+    fn client_credentials_token_is_cached() {
+        let token_server = test_server::TestServer::new(|_| {
+            test_server::response(200, JSON, br#"{"access_token":"first","expires_in":3600}"#)
+        });
+        let api_server = test_server::TestServer::with_response(200, "ok");
+
+        let oauth = OAuth2::client_credentials(&token_server.url(), "my-id", "my-secret")
+            .scope("read write");
+        let mut api = domain::Domain::new(&api_server.url());
+        api.auth_provider(oauth);
+
+        api.get().execute_as_string::<()>(None).unwrap();
+        api.get().execute_as_string::<()>(None).unwrap();
+
+        let token_requests = token_server.requests();
+        assert_eq!(token_requests.len(), 1);
+        assert_eq!(
+            token_requests[0].header("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(form_value(&token_requests[0], "grant_type").unwrap(), "client_credentials");
+        assert_eq!(form_value(&token_requests[0], "client_id").unwrap(), "my-id");
+        assert_eq!(form_value(&token_requests[0], "client_secret").unwrap(), "my-secret");
+        assert_eq!(form_value(&token_requests[0], "scope").unwrap(), "read write");
+
+        for request in api_server.requests() {
+            assert_eq!(request.header("Authorization"), Some("Bearer first"));
+        }
+    }
+
+    #[test]
+    fn expired_tokens_are_fetched_again() {
+        let token_server = test_server::TestServer::new(|_| {
+            test_server::response(200, JSON, br#"{"access_token":"short","expires_in":10}"#)
+        });
+
+        let mut oauth = OAuth2::client_credentials(&token_server.url(), "my-id", "my-secret");
+        oauth.access_token().unwrap();
+        oauth.access_token().unwrap();
+
+        assert_eq!(token_server.requests().len(), 2);
+    }
+
+    #[test]
+    fn refresh_token_is_replaced_on_refresh() {
+        let token_server = test_server::TestServer::new(|request| {
+            let body = match form_value(request, "refresh_token").as_deref() {
+                Some("refresh-1") => r#"{"access_token":"access-1","refresh_token":"refresh-2"}"#,
+                Some("refresh-2") => r#"{"access_token":"access-2"}"#,
+                _ => return test_server::response(400, JSON, br#"{"error":"invalid_grant"}"#),
+            };
+
+            test_server::response(200, JSON, body.as_bytes())
+        });
+
+        let mut oauth =
+            OAuth2::refresh_token(&token_server.url(), "my-id", "my-secret", "refresh-1");
+
+        assert_eq!(oauth.authorization().unwrap().unwrap().expose(), "Bearer access-1");
+        assert!(oauth.refresh().unwrap());
+        assert_eq!(oauth.authorization().unwrap().unwrap().expose(), "Bearer access-2");
+
+        let token_requests = token_server.requests();
+        assert_eq!(form_value(&token_requests[0], "grant_type").unwrap(), "refresh_token");
+        assert_eq!(token_requests.len(), 2);
+    }
+
+    #[test]
+    fn missing_access_token_is_an_error() {
+        let token_server = test_server::TestServer::new(|_| {
+            test_server::response(200, JSON, br#"{"token_type":"bearer","refresh_token":"r3"}"#)
+        });
+
+        let mut oauth = OAuth2::client_credentials(&token_server.url(), "my-id", "my-secret");
+
+        match oauth.access_token() {
+            Err(error::Error::DeserializationError {
+                error,
+                text,
+                request,
+                ..
+            }) => {
+                assert_eq!(error.to_string(), "the token response has no `access_token`");
+                assert_eq!(text, "fields: refresh_token, token_type");
+                assert_eq!(request.unwrap().url, token_server.url());
+            },
+            other => panic!("expected a deserialization error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_access_token_reports_only_the_oauth_error() {
+        let token_server = test_server::TestServer::new(|_| {
+            test_server::response(
+                200,
+                JSON,
+                br#"{"error":"invalid_grant","error_description":"expired","refresh_token":"r3"}"#,
+            )
+        });
+
+        let mut oauth = OAuth2::client_credentials(&token_server.url(), "my-id", "my-secret");
+        let error = oauth.access_token().unwrap_err();
+
+        match error {
+            error::Error::DeserializationError {
+                ref text,
+                ..
+            } => assert_eq!(text, "error: invalid_grant, error_description: expired"),
+            ref other => panic!("expected a deserialization error, got {:?}", other),
+        }
+        assert!(!error.to_string().contains("r3"));
+    }
+
+    #[test]
+    fn token_domain_is_used_for_token_requests() {
+        let token_server = test_server::TestServer::new(|_| {
+            test_server::response(200, JSON, br#"{"access_token":"first"}"#)
+        });
+        let mut token_domain = domain::Domain::new(&token_server.url());
+        token_domain.header("X-Tenant", &"acme");
+
+        let mut oauth = OAuth2::client_credentials("https://unused.example.com", "id", "secret")
+            .token_domain(token_domain);
+
+        assert_eq!(oauth.access_token().unwrap().expose(), "first");
+        assert_eq!(token_server.requests()[0].header("X-Tenant"), Some("acme"));
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let oauth = OAuth2::refresh_token("https://auth.example.com", "id", "hunter2", "refresh");
+
+        assert!(!format!("{:?}", oauth).contains("hunter2"));
+        assert!(!format!("{:?}", oauth).contains("refresh\""));
+    }
+}
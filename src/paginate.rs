@@ -0,0 +1,484 @@
+use crate::codec;
+use crate::error;
+use crate::link;
+use crate::path;
+use crate::request_context;
+use crate::response;
+use futures::future;
+use futures::stream;
+use futures::Future;
+use futures::Stream;
+use serde;
+use serde_json;
+use serde_path_to_error;
+use std::collections;
+
+/// How a list endpoint splits its results across pages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageStrategy {
+    /// Numbered pages, i.e. `?page=2&per_page=50`.
+    /// Stops at the first page with fewer than `per_page` items.
+    Page {
+        page_param: String,
+        per_page_param: String,
+        first_page: u64,
+        per_page: u64,
+    },
+
+    /// Items from an offset, i.e. `?offset=100&limit=50`.
+    /// Stops at the first page with fewer than `limit` items.
+    Offset {
+        offset_param: String,
+        limit_param: String,
+        limit: u64,
+    },
+
+    /// Each page holds a cursor for the next one, found in the body at
+    /// the JSON pointer given. It is sent back as a query parameter.
+    /// Stops when there is no cursor, or it is empty.
+    Cursor {
+        cursor_param: String,
+        cursor_pointer: String,
+    },
+
+    /// Follows the `Link` header with `rel="next"`, such as GitHub uses.
+    /// Stops when there is no next link.
+    LinkHeader,
+}
+
+impl PageStrategy {
+    /// Numbered pages using `page` and `per_page`, starting from page 1.
+    pub fn page(per_page: u64) -> Self {
+        PageStrategy::Page {
+            page_param: "page".to_string(),
+            per_page_param: "per_page".to_string(),
+            first_page: 1,
+            per_page,
+        }
+    }
+
+    /// Pages using `offset` and `limit`, starting from 0.
+    pub fn offset(limit: u64) -> Self {
+        PageStrategy::Offset {
+            offset_param: "offset".to_string(),
+            limit_param: "limit".to_string(),
+            limit,
+        }
+    }
+
+    /// Pages using a cursor from the body, such as `"/meta/next_cursor"`.
+    pub fn cursor(
+        cursor_param: &str,
+        cursor_pointer: &str,
+    ) -> Self {
+        PageStrategy::Cursor {
+            cursor_param: cursor_param.to_string(),
+            cursor_pointer: cursor_pointer.to_string(),
+        }
+    }
+
+    /// Follows the `Link` header with `rel="next"`, resolving relative
+    /// links against the page's url.
+    ///
+    /// Links stay on the origin of the domain, or an origin added with
+    /// `Domain::allow_origin`. A next link anywhere else ends paging with
+    /// `Error::UrlNotOnDomain`, so the domain's credentials are not sent
+    /// elsewhere.
+    pub fn link_header() -> Self {
+        PageStrategy::LinkHeader
+    }
+
+    fn first_page(&self) -> NextPage {
+        self.numbered_page(0, 0).unwrap_or_else(|| NextPage::Query(Vec::new()))
+    }
+
+    /// The query for page and offset strategies.
+    fn numbered_page(
+        &self,
+        pages_fetched: u64,
+        items_seen: u64,
+    ) -> Option<NextPage> {
+        match self {
+            PageStrategy::Page {
+                page_param,
+                per_page_param,
+                first_page,
+                per_page,
+            } => Some(NextPage::Query(vec![
+                (page_param.clone(), (first_page + pages_fetched).to_string()),
+                (per_page_param.clone(), per_page.to_string()),
+            ])),
+            PageStrategy::Offset {
+                offset_param,
+                limit_param,
+                limit,
+            } => Some(NextPage::Query(vec![
+                (offset_param.clone(), items_seen.to_string()),
+                (limit_param.clone(), limit.to_string()),
+            ])),
+            _ => None,
+        }
+    }
+}
+
+/// Where the next page is.
+#[derive(Debug, Clone)]
+enum NextPage {
+    /// The query parameters to add to the path.
+    Query(Vec<(String, String)>),
+
+    /// An absolute url on the domain.
+    Url(String),
+}
+
+/// Iterates over every item of a list endpoint, fetching each page as it
+/// is needed. Made using `Path::paginate`.
+///
+/// Every page is requested using the headers and query of the `Path` and
+/// `Domain` it came from. Pages are read as JSON.
+#[derive(Debug)]
+pub struct Paginator<T> {
+    path: path::Path,
+    strategy: PageStrategy,
+    items_pointer: String,
+    next: Option<NextPage>,
+    pages_fetched: u64,
+    items_seen: u64,
+    items: collections::VecDeque<T>,
+}
+
+impl<T: serde::de::DeserializeOwned> Paginator<T> {
+    pub(crate) fn new(
+        path: path::Path,
+        strategy: PageStrategy,
+    ) -> Self {
+        Self {
+            path,
+            next: Some(strategy.first_page()),
+            strategy,
+            items_pointer: String::new(),
+            pages_fetched: 0,
+            items_seen: 0,
+            items: collections::VecDeque::new(),
+        }
+    }
+
+    /// Where the items are within each page, as a JSON pointer such as
+    /// `"/data"`. By default each page is expected to be a list of items.
+    pub fn items_at(
+        mut self,
+        pointer: &str,
+    ) -> Self {
+        self.items_pointer = pointer.to_string();
+
+        self
+    }
+
+    /// The same items as a `Stream`, where each page is fetched without
+    /// blocking.
+    ///
+    /// Pages are fetched with `Path::execute_as_stream`, and so skip any
+    /// middleware on the domain.
+    pub fn into_stream(mut self) -> impl Stream<Item = T, Error = error::Error> {
+        let buffered = self.items.drain(..).collect::<Vec<_>>();
+
+        let pages = stream::unfold(self, |mut paginator| {
+            let next = paginator.next.take()?;
+            let page = future::result(paginator.page_path(&next)).and_then(|path| {
+                let request = path.request_context();
+
+                path.execute_as_stream::<()>(None).and_then(|response| {
+                    let status = response.status;
+                    let headers = response.headers;
+
+                    response.body.concat2().map(move |body| {
+                        let response = response::Response {
+                            body,
+                            status,
+                            headers,
+                        };
+
+                        (request, response)
+                    })
+                })
+            });
+
+            let items = page.and_then(move |(request, response)| {
+                let items = paginator
+                    .read_page(&request, &response)
+                    .map_err(|err| err.with_request(request))?;
+
+                Ok((items, paginator))
+            });
+
+            Some(items)
+        });
+
+        stream::iter_ok(buffered).chain(pages.map(stream::iter_ok).flatten())
+    }
+
+    fn page_path(
+        &self,
+        next: &NextPage,
+    ) -> Result<path::Path, error::Error> {
+        match next {
+            NextPage::Query(params) => Ok(self.path.clone().query_pairs(params)),
+            NextPage::Url(url) => self.path.with_url(url),
+        }
+    }
+
+    /// Fetches the next page, blocking until it has arrived.
+    fn fetch_page(
+        &mut self,
+        next: &NextPage,
+    ) -> Result<Vec<T>, error::Error> {
+        let path = self.page_path(next)?;
+        let (request, response) = path.execute_ok(&codec::JsonCodec, None::<&()>)?;
+
+        self.read_page(&request, &response).map_err(|err| err.with_request(request))
+    }
+
+    /// Pulls the items out of a page, and works out where the next one is.
+    fn read_page(
+        &mut self,
+        request: &request_context::RequestContext,
+        response: &response::Response,
+    ) -> Result<Vec<T>, error::Error> {
+        let mut page: serde_json::Value =
+            serde_json::from_slice(&response.body).map_err(|err| {
+                error::Error::new_deserialization_error(err.into(), response.text().into_owned())
+            })?;
+
+        let cursor = match self.strategy {
+            PageStrategy::Cursor {
+                ref cursor_pointer,
+                ..
+            } => page.pointer(cursor_pointer).and_then(cursor_to_string),
+            _ => None,
+        };
+
+        let fragment = page
+            .pointer_mut(&self.items_pointer)
+            .map(serde_json::Value::take)
+            .ok_or_else(|| error::Error::new_pointer_not_found(&self.items_pointer))?;
+        let items: Vec<T> = serde_path_to_error::deserialize(&fragment).map_err(|err| {
+            error::Error::new_pointer_deserialization_error(
+                err.into(),
+                fragment.to_string(),
+                &self.items_pointer,
+            )
+        })?;
+
+        self.pages_fetched += 1;
+        self.items_seen += items.len() as u64;
+
+        let is_full_page = match self.strategy {
+            PageStrategy::Page {
+                per_page: size,
+                ..
+            }
+            | PageStrategy::Offset {
+                limit: size,
+                ..
+            } => items.len() as u64 >= size,
+            _ => true,
+        };
+
+        self.next = if items.is_empty() || !is_full_page {
+            None
+        } else {
+            match self.strategy {
+                PageStrategy::Cursor {
+                    ref cursor_param,
+                    ..
+                } => cursor.map(|cursor| NextPage::Query(vec![(cursor_param.clone(), cursor)])),
+                PageStrategy::LinkHeader => next_link(request, response).map(NextPage::Url),
+                _ => self.strategy.numbered_page(self.pages_fetched, self.items_seen),
+            }
+        };
+
+        Ok(items)
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Iterator for Paginator<T> {
+    type Item = Result<T, error::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.pop_front() {
+                return Some(Ok(item));
+            }
+
+            let next = self.next.take()?;
+            match self.fetch_page(&next) {
+                Ok(items) => self.items.extend(items),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Cursors may be strings or numbers. Empty strings and `null` mean there
+/// are no more pages.
+fn cursor_to_string(cursor: &serde_json::Value) -> Option<String> {
+    match cursor {
+        serde_json::Value::String(cursor) if !cursor.is_empty() => Some(cursor.clone()),
+        serde_json::Value::Number(cursor) => Some(cursor.to_string()),
+        _ => None,
+    }
+}
+
+/// The absolute url of the `Link` with `rel="next"`, if there is one.
+fn next_link(
+    request: &request_context::RequestContext,
+    response: &response::Response,
+) -> Option<String> {
+    let next = response
+        .headers
+        .get_all("Link")
+        .flat_map(link::parse_links)
+        .find(|link| link.has_rel("next"))?;
+
+    Some(link::resolve(&request.url, &next.url))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain;
+    use crate::test_server;
+
+    const JSON: &[(&str, &str)] = &[("Content-Type", "application/json")];
+
+    fn query_value(
+        request: &test_server::ReceivedRequest,
+        key: &str,
+    ) -> Option<String> {
+        let query = request.path.split_once('?')?.1;
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query).ok()?;
+
+        params.into_iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    /// Serves the numbers 1 to 5, using the offset given.
+    fn numbers_from(offset: u64) -> Vec<u8> {
+        let numbers = (offset + 1..=5).take(2).collect::<Vec<_>>();
+
+        test_server::response(200, JSON, serde_json::to_string(&numbers).unwrap().as_bytes())
+    }
+
+    #[test]
+    fn page_strategy() {
+        let server = test_server::TestServer::new(|request| {
+            let page: u64 = query_value(request, "page").unwrap().parse().unwrap();
+            numbers_from((page - 1) * 2)
+        });
+        let mut domain = domain::Domain::new(&server.url());
+        domain.query("sort", &"asc").unwrap();
+
+        let numbers = domain
+            .get()
+            .push(&"numbers")
+            .paginate::<u64>(PageStrategy::page(2))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let paths = server.requests().into_iter().map(|request| request.path).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            paths,
+            vec![
+                "/numbers?sort=asc&page=1&per_page=2",
+                "/numbers?sort=asc&page=2&per_page=2",
+                "/numbers?sort=asc&page=3&per_page=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn offset_strategy() {
+        let server = test_server::TestServer::new(|request| {
+            numbers_from(query_value(request, "offset").unwrap().parse().unwrap())
+        });
+        let domain = domain::Domain::new(&server.url());
+
+        let numbers = domain
+            .get()
+            .paginate::<u64>(PageStrategy::offset(2))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        assert_eq!(query_value(&server.requests()[2], "offset").unwrap(), "4");
+    }
+
+    #[test]
+    fn cursor_strategy_as_stream() {
+        let server = test_server::TestServer::new(|request| {
+            let body: &[u8] = match query_value(request, "after").as_deref() {
+                None => br#"{"data":["a","b"],"next":"c+d=="}"#,
+                Some("c+d==") => br#"{"data":["c"],"next":null}"#,
+                Some(_) => br#"{"data":[]}"#,
+            };
+
+            test_server::response(200, JSON, body)
+        });
+        let domain = domain::Domain::new(&server.url());
+
+        let letters = domain
+            .get()
+            .paginate::<String>(PageStrategy::cursor("after", "/next"))
+            .items_at("/data")
+            .into_stream()
+            .collect()
+            .wait()
+            .unwrap();
+
+        assert_eq!(letters, vec!["a", "b", "c"]);
+        assert_eq!(server.requests()[1].path, "/?after=c%2Bd%3D%3D");
+    }
+
+    #[test]
+    fn link_header_strategy() {
+        let server = test_server::TestServer::new(|request| {
+            let (body, link): (&[u8], _) = match request.path.as_str() {
+                "/repos?per_page=2" => (b"[1,2]", r#"</repos?per_page=2&page=2>; rel="next""#),
+                _ => (b"[3]", r#"</repos?per_page=2&page=1>; rel="first""#),
+            };
+
+            test_server::response(200, &[("Link", link)], body)
+        });
+        let domain = domain::Domain::new(&server.url());
+
+        let numbers = domain
+            .get()
+            .push(&"repos")
+            .query("per_page", &2)
+            .paginate::<u32>(PageStrategy::link_header())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(server.requests()[1].path, "/repos?per_page=2&page=2");
+    }
+
+    #[test]
+    fn link_to_another_domain_is_an_error() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("Link", "<https://evil.example.com/>; rel=next")], b"[1]")
+        });
+        let domain = domain::Domain::new(&server.url());
+
+        let results = domain.get().paginate::<u32>(PageStrategy::link_header()).collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 2);
+        match results[1] {
+            Err(error::Error::UrlNotOnDomain {
+                ref url,
+                ..
+            }) => assert_eq!(url, "https://evil.example.com/"),
+            ref other => panic!("expected url not on domain, got {:?}", other),
+        }
+    }
+}
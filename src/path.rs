@@ -1,13 +1,30 @@
+use crate::codec;
+use crate::domain;
+use crate::download;
 use crate::error;
+use crate::link;
 use crate::method;
 use crate::native_client;
+use crate::paginate;
+use crate::reader_response;
+use crate::request_context;
 use crate::request_information;
 use crate::response;
+use crate::response_meta;
+use crate::secret;
+use crate::stream_response;
+use crate::uri_template;
+use futures::future;
+use futures::Future;
+use httpdate;
 use serde;
 use serde_json;
+use serde_path_to_error;
 use std::cell;
 use std::fmt;
+use std::path;
 use std::rc;
+use std::time;
 
 /// To make a `Path`, you need to use the `Domain` first.
 /// From that you can generate `Path` objects.
@@ -17,6 +34,19 @@ pub struct Path {
     client: rc::Rc<cell::RefCell<native_client::NativeClient>>,
     domain_info: rc::Rc<cell::RefCell<request_information::RequestInformation>>,
     info: request_information::RequestInformation,
+    /// The first error from building the url, returned when this is
+    /// executed.
+    error: Option<UrlError>,
+}
+
+/// An error from building the url of a `Path`.
+#[derive(Debug)]
+struct UrlError(error::Error);
+
+impl Clone for UrlError {
+    fn clone(&self) -> Self {
+        UrlError(self.0.try_clone().expect("errors from building a url can be copied"))
+    }
 }
 
 impl Path {
@@ -32,33 +62,96 @@ impl Path {
             client,
             domain_info,
             info,
+            error: None,
         }
     }
 
+    /// A path for a url the server sent back, such as a `next_url` or
+    /// `self` href. The headers, query and auth of the domain are kept,
+    /// and the query of the url is added to the path.
+    ///
+    /// The url is either absolute, or relative to the domain, such as
+    /// `/items?page=2`. It must be on the origin of the domain, or on one
+    /// allowed with `Domain::allow_origin`, so credentials are never sent
+    /// elsewhere. Otherwise `Error::UrlNotOnDomain` is returned.
+    pub fn from_url(
+        domain: &domain::Domain,
+        method: method::Method,
+        url: &str,
+    ) -> Result<Self, error::Error> {
+        let path = domain.method(method);
+        let url = link::resolve(path.domain_info.borrow().url(), url);
+
+        path.with_url(&url)
+    }
+
+    /// Adds a segment onto the end of the path. It is percent encoded, so
+    /// characters such as `/` and `?` are sent as part of the segment.
     pub fn push(
         mut self,
         next: &impl fmt::Display,
     ) -> Self {
-        self.info.push_path_part(next);
+        let result = self.info.push_path_part(next);
+        self.keep_error(result);
 
         self
     }
 
+    /// Adds onto the end of the last segment of the path, such as a
+    /// `.json` extension. This is percent encoded the same as `push`.
     pub fn push_partial(
         mut self,
         next: &impl fmt::Display,
     ) -> Self {
-        self.info.push_path_part_partial(next);
+        let result = self.info.push_path_part_partial(next);
+        self.keep_error(result);
 
         self
     }
 
+    /// Adds to the path by expanding a URI template, as described in
+    /// RFC 6570, such as `/repos/{owner}/{repo}/issues{?state,labels}`.
+    /// The variables are taken from a struct or map.
+    ///
+    /// Query expressions, such as `{?state}`, are added as query
+    /// parameters. Fragments are not sent. Variables which are `None` are
+    /// left out, but variables which are missing are an error.
+    pub fn expand<V: serde::ser::Serialize + ?Sized>(
+        mut self,
+        template: &str,
+        variables: &V,
+    ) -> Result<Self, error::Error> {
+        let expansion = uri_template::UriTemplate::parse(template)?.expand(variables)?;
+        let url = expansion.path.split('#').next().unwrap_or("");
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+        self.info.push_path_raw(path);
+        self.info.add_query_raw(query);
+        self.info.add_query_blob(&expansion.query)?;
+
+        Ok(self)
+    }
+
     pub fn query(
         mut self,
         key: &str,
         value: &impl fmt::Display,
     ) -> Self {
-        self.info.add_query_param(key, value);
+        let result = self.info.add_query_param(key, value);
+        self.keep_error(result);
+
+        self
+    }
+
+    /// Adds a query parameter whose value is kept secret, such as an
+    /// `api_key`. It is sent as normal, but masked when the url is displayed,
+    /// logged, or included in an error.
+    pub fn secret_query(
+        mut self,
+        key: &str,
+        value: impl Into<secret::Secret>,
+    ) -> Self {
+        self.info.add_secret_query_param(key, value.into());
 
         self
     }
@@ -71,12 +164,199 @@ impl Path {
         self.info.add_header(key, value);
     }
 
+    /// Authenticates this request with the bearer token given.
+    /// This is used instead of any auth set on the `Domain`.
+    pub fn bearer_auth(
+        mut self,
+        token: impl Into<secret::Secret>,
+    ) -> Self {
+        self.info.set_authorization(secret::bearer_authorization(&token.into()));
+
+        self
+    }
+
+    /// Authenticates this request using HTTP Basic auth.
+    /// This is used instead of any auth set on the `Domain`.
+    pub fn basic_auth(
+        mut self,
+        user: &str,
+        password: impl Into<secret::Secret>,
+    ) -> Self {
+        self.info.set_authorization(secret::basic_authorization(user, &password.into()));
+
+        self
+    }
+
+    /// Only makes the request if the resource still has the `ETag` given,
+    /// using `If-Match`. Otherwise `Error::PreconditionFailed` is returned.
+    ///
+    /// The `ETag` should be as the server sent it, quotes included.
+    pub fn if_match(
+        mut self,
+        etag: &str,
+    ) -> Self {
+        self.info.add_header("If-Match", &etag);
+
+        self
+    }
+
+    /// Only makes the request if the resource no longer has the `ETag`
+    /// given, using `If-None-Match`.
+    pub fn if_none_match(
+        mut self,
+        etag: &str,
+    ) -> Self {
+        self.info.add_header("If-None-Match", &etag);
+
+        self
+    }
+
+    /// Only makes the request if the resource has changed since the time
+    /// given, using `If-Modified-Since`.
+    pub fn if_modified_since(
+        mut self,
+        time: time::SystemTime,
+    ) -> Self {
+        self.info.add_header("If-Modified-Since", &httpdate::fmt_http_date(time));
+
+        self
+    }
+
+    /// Only makes the request if the resource has not changed since the time
+    /// given, using `If-Unmodified-Since`. Otherwise
+    /// `Error::PreconditionFailed` is returned.
+    pub fn if_unmodified_since(
+        mut self,
+        time: time::SystemTime,
+    ) -> Self {
+        self.info.add_header("If-Unmodified-Since", &httpdate::fmt_http_date(time));
+
+        self
+    }
+
+    /// Sets the format used for the request and response bodies, for just
+    /// this path. Otherwise the format of the `Domain` is used.
+    pub fn format(
+        mut self,
+        format: codec::Format,
+    ) -> Self {
+        self.info.set_codec(codec::BodyCodec::Format(format));
+
+        self
+    }
+
+    /// Sets a codec of your own for the request and response bodies, for
+    /// just this path.
+    pub fn codec(
+        mut self,
+        codec: impl codec::Codec + 'static,
+    ) -> Self {
+        self.info.set_codec(codec::BodyCodec::Custom(rc::Rc::new(codec)));
+
+        self
+    }
+
+    /// Iterates over every item of a list endpoint, fetching each page as it
+    /// is needed. `strategy` says how the endpoint splits up its pages.
+    pub fn paginate<T: serde::de::DeserializeOwned>(
+        self,
+        strategy: paginate::PageStrategy,
+    ) -> paginate::Paginator<T> {
+        paginate::Paginator::new(self, strategy)
+    }
+
     /// Executes the path, and deserializes what comes back.
+    ///
+    /// This always uses JSON, regardless of the format set.
     pub fn execute_as_json<B: serde::ser::Serialize + ?Sized, R: serde::de::DeserializeOwned>(
         self,
         body: Option<&B>,
     ) -> Result<R, error::Error> {
-        deserialize::<R>(self.execute_as_string(body)?)
+        self.execute_with_codec(&codec::JsonCodec, body)
+    }
+
+    /// The same as `execute_as_json`, only the status and headers of the
+    /// response are returned too. i.e. to get the `ETag`.
+    pub fn execute_as_json_with_meta<
+        B: serde::ser::Serialize + ?Sized,
+        R: serde::de::DeserializeOwned,
+    >(
+        self,
+        body: Option<&B>,
+    ) -> Result<(R, response_meta::ResponseMeta), error::Error> {
+        let codec = codec::JsonCodec;
+        let (request, response) = self.execute_ok(&codec, body)?;
+        let meta = response_meta::ResponseMeta {
+            status: response.status,
+            headers: response.headers,
+        };
+
+        match deserialize(&codec, response.body) {
+            Ok(value) => Ok((value, meta)),
+            Err(err) => Err(err.with_request(request)),
+        }
+    }
+
+    /// Executes the path, and deserializes what comes back.
+    ///
+    /// The body is sent using the format of the `Path` or `Domain`. The
+    /// response is read using the format matching its `Content-Type`, and
+    /// falls back to the same format as the body.
+    pub fn execute_as<B: serde::ser::Serialize + ?Sized, R: serde::de::DeserializeOwned>(
+        self,
+        body: Option<&B>,
+    ) -> Result<R, error::Error> {
+        self.execute_as_with_request(body).map(|(_, value)| value)
+    }
+
+    /// Executes the path, and returns what comes back as a JSON value.
+    ///
+    /// Like `execute_as`, the response is read using the format matching
+    /// its `Content-Type`.
+    pub fn execute_as_value<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+    ) -> Result<serde_json::Value, error::Error> {
+        self.execute_as(body)
+    }
+
+    /// Executes the path, and deserializes only the part of the response
+    /// found at the JSON pointer given. i.e. `"/data/items"`.
+    pub fn execute_at<B: serde::ser::Serialize + ?Sized, R: serde::de::DeserializeOwned>(
+        self,
+        pointer: &str,
+        body: Option<&B>,
+    ) -> Result<R, error::Error> {
+        let (request, mut value) = self.execute_as_with_request::<B, serde_json::Value>(body)?;
+        let fragment =
+            value.pointer_mut(pointer).map(serde_json::Value::take).ok_or_else(|| {
+                error::Error::new_pointer_not_found(pointer).with_request(request.clone())
+            })?;
+
+        serde_path_to_error::deserialize(&fragment).map_err(|err| {
+            error::Error::new_pointer_deserialization_error(
+                err.into(),
+                fragment.to_string(),
+                pointer,
+            )
+            .with_request(request)
+        })
+    }
+
+    /// Executes the path, using the codec given for both the request and
+    /// response bodies.
+    pub fn execute_with_codec<
+        C: codec::Codec,
+        B: serde::ser::Serialize + ?Sized,
+        R: serde::de::DeserializeOwned,
+    >(
+        self,
+        codec: &C,
+        body: Option<&B>,
+    ) -> Result<R, error::Error> {
+        let (request, response) = self.execute_ok(codec, body)?;
+
+        deserialize(codec, response.body).map_err(|err| err.with_request(request))
     }
 
     /// Sends the request, returns the response as just a String.
@@ -84,42 +364,375 @@ impl Path {
         self,
         body: Option<&B>,
     ) -> Result<String, error::Error> {
-        string_or_error(self.execute(body))
+        let codec = self.codec_or_default();
+        let (_, response) = self.execute_ok(&codec, body)?;
+
+        Ok(response.text().into_owned())
+    }
+
+    /// Sends the request, and returns the body as a stream of chunks.
+    ///
+    /// The body is not read into memory. It is handed back as it arrives,
+    /// along with the status and headers.
+    ///
+    /// This is never retried after a `401 Unauthorized`, even when
+    /// `Domain::retry_unauthorized` is turned on, and it skips middleware,
+    /// including a cache added with `Domain::cache`. Both would block
+    /// before the future is polled. `execute_as_reader` does neither.
+    pub fn execute_as_stream<B: serde::ser::Serialize + ?Sized>(
+        mut self,
+        body: Option<&B>,
+    ) -> impl Future<Item = stream_response::StreamResponse, Error = error::Error> {
+        let codec = self.codec_or_default();
+        let response = self.prepare(&codec, body).and_then(|(request, body_bytes)| {
+            self.client
+                .borrow_mut()
+                .request_stream(self.method, &self.domain_info.borrow(), &self.info, body_bytes)
+                .map(|response| response.map_err(move |err| err.with_request(request)))
+                .map_err(|err| err.with_request(self.request_context()))
+        });
+
+        future::result(response).flatten()
+    }
+
+    /// Sends the request, and returns a blocking reader over the body.
+    ///
+    /// The body is pulled from the network as it is read.
+    pub fn execute_as_reader<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+    ) -> Result<reader_response::ReaderResponse, error::Error> {
+        self.execute_reader(body, native_client::NativeClient::request_stream_blocking)
+            .map(|(_, reader)| reader)
+    }
+
+    /// Sends the request, and streams the body into the file given.
+    ///
+    /// The file is only created once the whole body has been downloaded.
+    /// Returns the number of bytes written.
+    pub fn download_to<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+        destination: impl AsRef<path::Path>,
+    ) -> Result<u64, error::Error> {
+        self.download_to_with_progress(body, destination, |_| {})
+    }
+
+    /// The same as `download_to`, only `progress` is called each time more
+    /// of the body is written to disk.
+    pub fn download_to_with_progress<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+        destination: impl AsRef<path::Path>,
+        progress: impl FnMut(download::DownloadProgress),
+    ) -> Result<u64, error::Error> {
+        let (request, response) =
+            self.execute_reader(body, native_client::NativeClient::request_stream_blocking)?;
+
+        download::download_to(response, destination.as_ref(), progress)
+            .map_err(|err| err.with_request(request))
     }
 
-    fn execute<B: serde::ser::Serialize + ?Sized>(
+    /// Downloads into the file given, picking up from where an earlier
+    /// failed attempt left off.
+    ///
+    /// Unlike `download_to`, a failed download leaves the partial file
+    /// behind. When called again, only the rest of the body is asked for
+    /// using a `Range` request. `If-Range` is sent with the `ETag` or
+    /// `Last-Modified` of the partial file, and if the file on the server
+    /// has changed then the download starts again from scratch. The same
+    /// happens if the server says the range is not satisfiable, unless the
+    /// partial file turns out to be the whole file.
+    ///
+    /// Returns the size of the downloaded file.
+    pub fn resume_download_to<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+        destination: impl AsRef<path::Path>,
+    ) -> Result<u64, error::Error> {
+        self.resume_download_to_with_progress(body, destination, |_| {})
+    }
+
+    /// The same as `resume_download_to`, only `progress` is called each
+    /// time more of the body is written to disk.
+    pub fn resume_download_to_with_progress<B: serde::ser::Serialize + ?Sized>(
+        self,
+        body: Option<&B>,
+        destination: impl AsRef<path::Path>,
+        progress: impl FnMut(download::DownloadProgress),
+    ) -> Result<u64, error::Error> {
+        let destination = destination.as_ref();
+
+        if let Some(resume) = download::ResumeState::load(destination) {
+            let mut ranged = self.clone();
+            ranged.header("Range", &format!("bytes={}-", resume.offset));
+            ranged.header("If-Range", &resume.validator);
+
+            let (request, response) = ranged
+                .execute_reader(body, native_client::NativeClient::request_resume_blocking)?;
+
+            if response.status != 416 {
+                return download::resume_download_to(response, destination, Some(resume), progress)
+                    .map_err(|err| err.with_request(request));
+            }
+
+            // Either the partial file is already the whole file, or it no
+            // longer matches it and has been removed.
+            let finished = download::finish_unsatisfiable_range(&response, destination, &resume)
+                .map_err(|err| error::Error::from(err).with_request(request))?;
+            if let Some(size) = finished {
+                return Ok(size);
+            }
+        }
+
+        let (request, response) =
+            self.execute_reader(body, native_client::NativeClient::request_stream_blocking)?;
+
+        download::resume_download_to(response, destination, None, progress)
+            .map_err(|err| err.with_request(request))
+    }
+
+    /// Sends the request, with the body using the format of the `Path` or
+    /// `Domain`. The response is read using the format matching its
+    /// `Content-Type`, and falls back to the same format as the body.
+    fn execute_as_with_request<
+        B: serde::ser::Serialize + ?Sized,
+        R: serde::de::DeserializeOwned,
+    >(
+        self,
+        body: Option<&B>,
+    ) -> Result<(request_context::RequestContext, R), error::Error> {
+        let codec = self.codec_or_default();
+        let (request, response) = self.execute_ok(&codec, body)?;
+        let response_codec = codec.for_response(response.headers.get("Content-Type"));
+
+        match deserialize(&response_codec, response.body) {
+            Ok(value) => Ok((request, value)),
+            Err(err) => Err(err.with_request(request)),
+        }
+    }
+
+    /// Adds query parameters, encoding their values.
+    pub(crate) fn query_pairs(
         mut self,
+        params: &[(String, String)],
+    ) -> Self {
+        let result = self.info.add_query_blob(params);
+        self.keep_error(result);
+
+        self
+    }
+
+    /// A copy of this path, pointed at the absolute url given instead.
+    /// The method, headers, auth and format of this path are kept.
+    pub(crate) fn with_url(
+        &self,
+        url: &str,
+    ) -> Result<Path, error::Error> {
+        let info = self
+            .info
+            .with_absolute_url(&self.domain_info.borrow(), url)
+            .ok_or_else(|| error::Error::new_url_not_on_domain(url))?;
+
+        Ok(Path {
+            method: self.method,
+            client: rc::Rc::clone(&self.client),
+            domain_info: rc::Rc::clone(&self.domain_info),
+            info,
+            error: self.error.clone(),
+        })
+    }
+
+    /// Sends the request, and returns the response if it was ok.
+    /// Returned with it is a description of the request that was made.
+    pub(crate) fn execute_ok<C: codec::Codec, B: serde::ser::Serialize + ?Sized>(
+        self,
+        codec: &C,
         maybe_body: Option<&B>,
-    ) -> Result<response::Response, error::Error> {
-        let mut body_str = None;
-
-        match maybe_body {
-            Some(body) => {
-                match self.method {
-                    method::Method::Get => {
-                        self.info.add_query_blob(body)?;
-                    },
-                    _ => {
-                        body_str = Some(
-                            serde_json::to_string(body)
-                                .map_err(error::Error::new_serialize_body_error)?,
-                        );
-                    },
-                }
-            },
-            _ => {},
+    ) -> Result<(request_context::RequestContext, response::Response), error::Error> {
+        let (request, response) = self.execute(codec, maybe_body)?;
+
+        if response.status == 200 {
+            Ok((request, response))
+        } else {
+            Err(error::Error::new_request_not_ok(response).with_request(request))
         }
+    }
 
-        self.client.borrow_mut().request_blocking(
+    fn execute<C: codec::Codec, B: serde::ser::Serialize + ?Sized>(
+        mut self,
+        codec: &C,
+        maybe_body: Option<&B>,
+    ) -> Result<(request_context::RequestContext, response::Response), error::Error> {
+        let (_, body_bytes) = self.prepare(codec, maybe_body)?;
+
+        self.send_blocking(
+            body_bytes,
+            |result| match result {
+                Ok(response) => response.status == 401,
+                Err(_) => false,
+            },
+            native_client::NativeClient::request_blocking,
+        )
+    }
+
+    fn execute_reader<B: serde::ser::Serialize + ?Sized>(
+        mut self,
+        body: Option<&B>,
+        send: impl Fn(
+            &mut native_client::NativeClient,
+            method::Method,
+            &request_information::RequestInformation,
+            &request_information::RequestInformation,
+            Option<Vec<u8>>,
+        ) -> Result<stream_response::StreamResponse, error::Error>,
+    ) -> Result<(request_context::RequestContext, reader_response::ReaderResponse), error::Error>
+    {
+        let codec = self.codec_or_default();
+        let (_, body_bytes) = self.prepare(&codec, body)?;
+
+        let (request, response) = self.send_blocking(
+            body_bytes,
+            |result| match result {
+                Err(err) => err.status() == Some(401),
+                Ok(_) => false,
+            },
+            send,
+        )?;
+
+        Ok((request, reader_response::ReaderResponse::new(self.client, response)))
+    }
+
+    /// Sends the request using `send`.
+    ///
+    /// If it comes back unauthorized, and the auth provider is able to
+    /// refresh its credentials, then it is sent once more.
+    fn send_blocking<T>(
+        &self,
+        body_bytes: Option<Vec<u8>>,
+        is_unauthorized: impl Fn(&Result<T, error::Error>) -> bool,
+        send: impl Fn(
+            &mut native_client::NativeClient,
+            method::Method,
+            &request_information::RequestInformation,
+            &request_information::RequestInformation,
+            Option<Vec<u8>>,
+        ) -> Result<T, error::Error>,
+    ) -> Result<(request_context::RequestContext, T), error::Error> {
+        let retry_body = if self.client.borrow().retries_unauthorized() {
+            body_bytes.clone()
+        } else {
+            None
+        };
+
+        let mut request = self.request_context();
+        let mut result = send(
+            &mut self.client.borrow_mut(),
             self.method,
             &self.domain_info.borrow(),
             &self.info,
-            body_str,
-        )
+            body_bytes,
+        );
+
+        if is_unauthorized(&result) {
+            let refresh = self.client.borrow_mut().refresh_auth();
+            if refresh.map_err(|err| err.with_request(request.clone()))? {
+                request.attempt += 1;
+                result = send(
+                    &mut self.client.borrow_mut(),
+                    self.method,
+                    &self.domain_info.borrow(),
+                    &self.info,
+                    retry_body,
+                );
+            }
+        }
+
+        match result {
+            Ok(value) => Ok((request, value)),
+            Err(err) => Err(err.with_request(request)),
+        }
+    }
+
+    /// Holds on to the first error from building the url, so it can be
+    /// returned when the path is executed.
+    fn keep_error(
+        &mut self,
+        result: Result<(), error::Error>,
+    ) {
+        if let (Err(err), None) = (result, &self.error) {
+            self.error = Some(UrlError(err));
+        }
+    }
+
+    /// Serializes the body, and describes the request about to be made.
+    ///
+    /// Any error from building the url is returned here, before anything
+    /// is sent.
+    fn prepare<C: codec::Codec, B: serde::ser::Serialize + ?Sized>(
+        &mut self,
+        codec: &C,
+        maybe_body: Option<&B>,
+    ) -> Result<(request_context::RequestContext, Option<Vec<u8>>), error::Error> {
+        if let Some(UrlError(err)) = self.error.take() {
+            return Err(err.with_request(self.request_context()));
+        }
+
+        let body_bytes = self
+            .prepare_body(codec, maybe_body)
+            .map_err(|err| err.with_request(self.request_context()))?;
+
+        Ok((self.request_context(), body_bytes))
+    }
+
+    pub(crate) fn request_context(&self) -> request_context::RequestContext {
+        request_context::RequestContext::new(self.method, &self.to_string(), 1)
+    }
+
+    /// The codec set on the path, then the domain, and then JSON.
+    fn codec_or_default(&self) -> codec::BodyCodec {
+        self.info
+            .codec()
+            .cloned()
+            .or_else(|| self.domain_info.borrow().codec().cloned())
+            .unwrap_or_default()
+    }
+
+    /// Bodies for `Get` requests are sent as query parameters.
+    /// Everything else is serialized using the codec given.
+    ///
+    /// The `Content-Type` is set to match the codec, unless one has already
+    /// been set.
+    fn prepare_body<C: codec::Codec, B: serde::ser::Serialize + ?Sized>(
+        &mut self,
+        codec: &C,
+        maybe_body: Option<&B>,
+    ) -> Result<Option<Vec<u8>>, error::Error> {
+        if let Some(body) = maybe_body {
+            match self.method {
+                method::Method::Get => {
+                    self.info.add_query_blob(body)?;
+                },
+                _ => {
+                    let body_bytes =
+                        codec.serialize(body).map_err(error::Error::new_serialize_body_error)?;
+
+                    let has_content_type = self.info.has_header("Content-Type")
+                        || self.domain_info.borrow().has_header("Content-Type");
+                    if !has_content_type {
+                        self.info.add_header("Content-Type", &codec.content_type());
+                    }
+
+                    return Ok(Some(body_bytes));
+                },
+            }
+        }
+
+        Ok(None)
     }
 }
 
-impl<'a> fmt::Display for Path {
+impl fmt::Display for Path {
     fn fmt(
         &self,
         f: &mut fmt::Formatter,
@@ -128,30 +741,48 @@ impl<'a> fmt::Display for Path {
     }
 }
 
-fn string_or_error(
-    response: Result<response::Response, error::Error>,
-) -> Result<String, error::Error> {
-    response.and_then(|r| {
-        if r.status == 200 {
-            Ok(r.body)
-        } else {
-            Err(error::Error::new_request_not_ok(r))
-        }
+fn deserialize<T: serde::de::DeserializeOwned>(
+    codec: &impl codec::Codec,
+    body: Vec<u8>,
+) -> Result<T, error::Error> {
+    codec.deserialize::<T>(&body).map_err(|err| {
+        error::Error::new_deserialization_error(err, String::from_utf8_lossy(&body).into_owned())
     })
 }
 
-fn deserialize<T: serde::de::DeserializeOwned>(body: String) -> Result<T, error::Error> {
-    Ok(serde_json::from_str::<T>(&body)
-        .map_err(|err| error::Error::new_deserialization_error(err, body))?)
-}
-
 #[cfg(test)]
 mod test {
     use super::super::Domain;
+    use crate::auth::AuthProvider;
+    use crate::codec::test::ReversedJsonCodec;
+    use crate::download::DownloadProgress;
+    use crate::error;
+    use crate::method::Method;
+    use crate::middleware::Middleware;
+    use crate::middleware::Next;
+    use crate::request::Request;
+    use crate::response::Response;
+    use crate::response_headers::ResponseHeaders;
+    use crate::secret::Secret;
+    use crate::signing::HmacSigner;
+    use crate::test_dir;
+    use crate::test_server;
+    use futures::future;
+    use futures::Future;
+    use futures::Stream;
+    use serde_json;
+    use std::cell;
+    use std::fmt;
+    use std::fs;
+    use std::io::Read;
+    use std::rc;
+    use std::sync;
+    use std::time;
+    use tokio;
 
     #[test]
     fn push_works() {
-        let domain = Domain::new(&"https://api.example.com");
+        let domain = Domain::new("https://api.example.com");
         let path = domain.get().push(&"org").push(&"Microsoft").push(&"projects");
 
         assert_eq!(path.to_string(), "https://api.example.com/org/Microsoft/projects");
@@ -159,17 +790,904 @@ mod test {
 
     #[test]
     fn domain_should_strip_slash() {
-        let domain = Domain::new(&"https://api.example.com");
+        let domain = Domain::new("https://api.example.com");
         let path = domain.get().push(&"list").push(&123);
 
         assert_eq!(path.to_string(), "https://api.example.com/list/123");
     }
 
+    #[test]
+    fn errors_building_the_url_are_returned_when_executed() {
+        struct Broken;
+
+        impl fmt::Display for Broken {
+            fn fmt(
+                &self,
+                _: &mut fmt::Formatter,
+            ) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let server = test_server::TestServer::with_response(200, "ok");
+        let domain = Domain::new(&server.url());
+
+        let pushed = domain.get().push(&Broken).push(&"list").execute_as_string::<()>(None);
+        let queried =
+            domain.get().push(&"list").query("size", &Broken).execute_as_reader::<()>(None);
+
+        match pushed {
+            Err(error::Error::FormatError {
+                request,
+                ..
+            }) => assert_eq!(request.unwrap().attempt, 1),
+            other => panic!("expected a format error, got {:?}", other),
+        }
+        assert!(matches!(queried, Err(error::Error::FormatError { .. })));
+        assert!(server.requests().is_empty());
+    }
+
     #[test]
     fn query_parameters() {
         let domain = Domain::new("https://api.example.com");
-        let path = domain.get().push(&"list").query(&"size", &50).query(&"index", &2);
+        let path = domain.get().push(&"list").query("size", &50).query("index", &2);
 
         assert_eq!(path.to_string(), "https://api.example.com/list?size=50&index=2");
     }
+
+    #[test]
+    fn expand_uri_template() {
+        #[derive(Serialize)]
+        struct Issues<'a> {
+            owner: &'a str,
+            repo: &'a str,
+            state: Option<&'a str>,
+            labels: Vec<&'a str>,
+        }
+
+        let domain = Domain::new("https://api.example.com");
+        let issues = Issues {
+            owner: "rust lang",
+            repo: "rust",
+            state: None,
+            labels: vec!["bug", "good first issue"],
+        };
+        let path = domain
+            .get()
+            .expand("/repos/{owner}/{repo}/issues{?state,labels*}", &issues)
+            .unwrap()
+            .query("page", &2);
+
+        assert_eq!(
+            path.to_string(),
+            "https://api.example.com/repos/rust%20lang/rust/issues?labels=bug&labels=good+first+issue&page=2"
+        );
+    }
+
+    #[test]
+    fn expand_encodes_segments_the_same_as_push() {
+        let domain = Domain::new("https://api.example.com");
+
+        for segment in &["rust lang", "a/b", "100%", "what?#", "ünïcode", "x.y-z_~", "a+b&c=d"] {
+            let pushed = domain.get().push(&"repos").push(segment);
+            let expanded = domain
+                .get()
+                .expand("/repos/{segment}", &serde_json::json!({ "segment": segment }))
+                .unwrap();
+
+            assert_eq!(pushed.to_string(), expanded.to_string());
+        }
+
+        assert_eq!(domain.get().push(&"a/b c").to_string(), "https://api.example.com/a%2Fb%20c");
+    }
+
+    #[test]
+    fn expand_uri_template_missing_variable() {
+        let domain = Domain::new("https://api.example.com");
+        let result =
+            domain.get().expand("/repos/{owner}/{repo}", &serde_json::json!({ "owner": "me" }));
+
+        match result {
+            Err(error::Error::MissingTemplateVariable {
+                variable,
+                ..
+            }) => assert_eq!(variable, "repo"),
+            other => panic!("expected missing variable, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn execute_as_reader_reads_body() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("X-Total", "3")], b"abc")
+        });
+        let domain = Domain::new(&server.url());
+
+        let mut reader = domain.get().push(&"export").execute_as_reader::<()>(None).unwrap();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+
+        assert_eq!(reader.status, 200);
+        assert_eq!(reader.headers.get("x-total"), Some("3"));
+        assert_eq!(body, "abc");
+        assert_eq!(server.requests()[0].path, "/export");
+    }
+
+    #[test]
+    fn execute_as_stream_returns_chunks() {
+        let server = test_server::TestServer::with_response(200, "streamed body");
+        let domain = Domain::new(&server.url());
+
+        let response = domain.get().execute_as_stream::<()>(None).wait().unwrap();
+        let body = response.body.concat2().wait().unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(body, b"streamed body".to_vec());
+    }
+
+    #[test]
+    fn execute_as_reader_errors_when_not_ok() {
+        let server = test_server::TestServer::with_response(404, "not found");
+        let domain = Domain::new(&server.url());
+
+        match domain.get().execute_as_reader::<()>(None) {
+            Err(error::Error::RequestNotOk {
+                status,
+                body,
+                request,
+            }) => {
+                assert_eq!(status, 404);
+                assert_eq!(body, "not found");
+                assert_eq!(request.unwrap().method, Method::Get);
+            },
+            other => panic!("expected request not ok, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn execute_as_string_sends_body_and_headers() {
+        let server = test_server::TestServer::with_response(200, "done");
+        let domain = Domain::new(&server.url());
+
+        let mut path = domain.post().push(&"items");
+        path.header("X-Request", &"first");
+        let text = path.execute_as_string(Some(&vec![1, 2, 3])).unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(text, "done");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("x-request"), Some("first"));
+        assert_eq!(request.body, b"[1,2,3]".to_vec());
+    }
+
+    #[test]
+    fn bearer_auth_is_sent() {
+        let server = test_server::TestServer::with_response(200, "done");
+        let mut domain = Domain::new(&server.url());
+        domain.bearer_auth("abc123");
+
+        domain.get().execute_as_string::<()>(None).unwrap();
+
+        assert_eq!(server.requests()[0].header("Authorization"), Some("Bearer abc123"));
+    }
+
+    #[test]
+    fn path_auth_replaces_domain_auth() {
+        let server = test_server::TestServer::with_response(200, "done");
+        let mut domain = Domain::new(&server.url());
+        domain.bearer_auth("abc123");
+
+        let path = domain.get().basic_auth("Aladdin", "open sesame");
+        assert!(!format!("{:?}", path).contains("open sesame"));
+        path.execute_as_string::<()>(None).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("Authorization"), Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+    }
+
+    #[test]
+    fn secret_query_is_sent_but_not_shown() {
+        let server = test_server::TestServer::with_response(500, "broken");
+        let domain = Domain::new(&server.url());
+
+        let path = domain.get().push(&"items").secret_query("sid", "s3cr3t");
+        let err = path.execute_as_string::<()>(None).unwrap_err();
+
+        assert_eq!(server.requests()[0].path, "/items?sid=s3cr3t");
+        assert!(err.url().unwrap().ends_with("/items?sid=REDACTED"));
+        assert!(!err.to_string().contains("s3cr3t"));
+    }
+
+    struct Tag(&'static str, rc::Rc<cell::RefCell<Vec<String>>>);
+
+    impl Middleware for Tag {
+        fn handle(
+            &self,
+            mut request: Request,
+            next: Next,
+        ) -> Result<Response, error::Error> {
+            let previous = request.header("X-Tags").unwrap_or("").to_string();
+            request.set_header("X-Tags", &format!("{}{}", previous, self.0));
+
+            let response = next.run(request)?;
+            self.1.borrow_mut().push(format!("{} saw {}", self.0, response.status));
+
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn middleware_runs_in_order_around_the_request() {
+        let server = test_server::TestServer::with_response(200, "done");
+        let seen = rc::Rc::new(cell::RefCell::new(Vec::new()));
+        let mut domain = Domain::new(&server.url());
+        domain.middleware(Tag("a", rc::Rc::clone(&seen)));
+        domain.middleware(Tag("b", rc::Rc::clone(&seen)));
+
+        domain.get().execute_as_string::<()>(None).unwrap();
+
+        assert_eq!(server.requests()[0].header("X-Tags"), Some("ab"));
+        assert_eq!(*seen.borrow(), vec!["b saw 200", "a saw 200"]);
+    }
+
+    struct Canned;
+
+    impl Middleware for Canned {
+        fn handle(
+            &self,
+            _: Request,
+            _: Next,
+        ) -> Result<Response, error::Error> {
+            Ok(Response {
+                body: b"[1,2,3]".to_vec(),
+                status: 200,
+                headers: ResponseHeaders::new(Vec::new()),
+            })
+        }
+    }
+
+    #[test]
+    fn middleware_can_short_circuit() {
+        let server = test_server::TestServer::with_response(500, "broken");
+        let mut domain = Domain::new(&server.url());
+        domain.middleware(Canned);
+
+        let items: Vec<u32> = domain.get().execute_as_json::<(), _>(None).unwrap();
+
+        assert_eq!(items, vec![1, 2, 3]);
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn middleware_runs_around_streamed_requests() {
+        let server = test_server::TestServer::with_response(200, "streamed body");
+        let seen = rc::Rc::new(cell::RefCell::new(Vec::new()));
+        let mut domain = Domain::new(&server.url());
+        domain.middleware(Tag("a", rc::Rc::clone(&seen)));
+
+        let mut body = String::new();
+        domain.get().execute_as_reader::<()>(None).unwrap().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "streamed body");
+
+        let directory = test_dir::TestDir::new("middleware_runs_around_streamed_requests");
+        let destination = directory.join("download.txt");
+        domain.get().download_to::<()>(None, &destination).unwrap();
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "streamed body");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.header("X-Tags") == Some("a")));
+        assert_eq!(*seen.borrow(), vec!["a saw 200", "a saw 200"]);
+    }
+
+    #[test]
+    fn middleware_can_short_circuit_streamed_requests() {
+        let server = test_server::TestServer::with_response(500, "broken");
+        let mut domain = Domain::new(&server.url());
+        domain.middleware(Canned);
+
+        let mut body = String::new();
+        domain.get().execute_as_reader::<()>(None).unwrap().read_to_string(&mut body).unwrap();
+
+        assert_eq!(body, "[1,2,3]");
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn execute_as_stream_skips_middleware_and_runs_inside_an_executor() {
+        let server = test_server::TestServer::with_response(200, "streamed body");
+        let seen = rc::Rc::new(cell::RefCell::new(Vec::new()));
+        let mut domain = Domain::new(&server.url());
+        domain.middleware(Tag("a", rc::Rc::clone(&seen)));
+
+        let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+        let body = runtime
+            .block_on(future::lazy(|| {
+                domain
+                    .get()
+                    .execute_as_stream::<()>(None)
+                    .and_then(|response| response.body.concat2())
+            }))
+            .unwrap();
+
+        assert_eq!(body, b"streamed body".to_vec());
+        assert_eq!(server.requests()[0].header("X-Tags"), None);
+        assert!(seen.borrow().is_empty());
+    }
+
+    #[test]
+    fn conditional_update_with_etag() {
+        let version = sync::Arc::new(sync::atomic::AtomicU32::new(1));
+        let server = test_server::TestServer::new(move |request| {
+            let current = format!("\"v{}\"", version.load(sync::atomic::Ordering::SeqCst));
+
+            match request.header("If-Match") {
+                Some(etag) if etag != current => test_server::response(412, &[], b"stale"),
+                Some(_) => {
+                    let next = version.fetch_add(1, sync::atomic::Ordering::SeqCst) + 1;
+                    let etag = format!("\"v{}\"", next);
+                    test_server::response(200, &[("ETag", &etag)], b"{}")
+                },
+                None => test_server::response(200, &[("ETag", &current)], b"{\"n\":1}"),
+            }
+        });
+        let domain = Domain::new(&server.url());
+
+        let (item, meta) =
+            domain.get().execute_as_json_with_meta::<(), serde_json::Value>(None).unwrap();
+        assert_eq!(item["n"], 1);
+        assert_eq!(meta.etag(), Some("\"v1\""));
+
+        let (_, meta) = domain
+            .put()
+            .if_match(meta.etag().unwrap())
+            .execute_as_json_with_meta::<_, serde_json::Value>(Some(&"update"))
+            .unwrap();
+        assert_eq!(meta.etag(), Some("\"v2\""));
+
+        let err = domain.put().if_match("\"v1\"").execute_as_json::<_, ()>(Some(&"again"));
+        match err {
+            Err(error::Error::PreconditionFailed {
+                body,
+                request,
+            }) => {
+                assert_eq!(body, "stale");
+                assert_eq!(request.unwrap().method, Method::Put);
+            },
+            other => panic!("expected precondition failed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn conditional_dates_are_http_dates() {
+        let server = test_server::TestServer::with_response(200, "ok");
+        let domain = Domain::new(&server.url());
+        let time = time::UNIX_EPOCH + time::Duration::from_secs(784_111_777);
+
+        domain
+            .get()
+            .if_none_match("\"v1\"")
+            .if_modified_since(time)
+            .if_unmodified_since(time)
+            .execute_as_string::<()>(None)
+            .unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.header("If-None-Match"), Some("\"v1\""));
+        assert_eq!(request.header("If-Modified-Since"), Some("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(request.header("If-Unmodified-Since"), Some("Sun, 06 Nov 1994 08:49:37 GMT"));
+    }
+
+    #[test]
+    fn signer_sees_the_final_request() {
+        let server = test_server::TestServer::with_response(200, "done");
+        let mut domain = Domain::new(&server.url());
+        domain.signer(HmacSigner::new("shh").timestamp(|| "1700000000".to_string()));
+
+        domain.get().push(&"balance").execute_as_string::<()>(None).unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.header("X-Timestamp"), Some("1700000000"));
+        assert_eq!(
+            request.header("X-Signature"),
+            Some("144aefb6b2dee8ef4d003236ef2d0f3308baba1e1cc85a32c7b53836c61c1a3f")
+        );
+    }
+
+    #[derive(Clone)]
+    struct RefreshingProvider {
+        token: rc::Rc<cell::Cell<u32>>,
+    }
+
+    impl AuthProvider for RefreshingProvider {
+        fn authorization(&mut self) -> Result<Option<Secret>, error::Error> {
+            Ok(Some(Secret::new(format!("Bearer token-{}", self.token.get()))))
+        }
+
+        fn refresh(&mut self) -> Result<bool, error::Error> {
+            self.token.set(self.token.get() + 1);
+
+            Ok(true)
+        }
+    }
+
+    fn unauthorized_unless_refreshed(request: &test_server::ReceivedRequest) -> Vec<u8> {
+        if request.header("Authorization") == Some("Bearer token-2") {
+            test_server::response(200, &[], b"[1]")
+        } else {
+            test_server::response(401, &[], b"expired")
+        }
+    }
+
+    #[test]
+    fn auth_provider_retries_once_after_refresh() {
+        let server = test_server::TestServer::new(unauthorized_unless_refreshed);
+        let provider = RefreshingProvider {
+            token: rc::Rc::new(cell::Cell::new(1)),
+        };
+        let mut domain = Domain::new(&server.url());
+        domain.auth_provider(provider.clone());
+        domain.retry_unauthorized(true);
+
+        let items: Vec<u32> = domain.post().execute_as_json(Some(&"body")).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(items, vec![1]);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer token-1"));
+        assert_eq!(requests[1].body, b"\"body\"".to_vec());
+        assert_eq!(provider.token.get(), 2);
+    }
+
+    #[test]
+    fn auth_provider_does_not_retry_by_default() {
+        let server = test_server::TestServer::new(unauthorized_unless_refreshed);
+        let mut domain = Domain::new(&server.url());
+        domain.auth_provider(RefreshingProvider {
+            token: rc::Rc::new(cell::Cell::new(1)),
+        });
+
+        let err = domain.get().execute_as_reader::<()>(None).unwrap_err();
+
+        assert_eq!(err.status(), Some(401));
+        assert_eq!(err.attempt(), Some(1));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn execute_as_stream_is_not_retried_when_unauthorized() {
+        let server = test_server::TestServer::new(unauthorized_unless_refreshed);
+        let provider = RefreshingProvider {
+            token: rc::Rc::new(cell::Cell::new(1)),
+        };
+        let mut domain = Domain::new(&server.url());
+        domain.auth_provider(provider.clone());
+        domain.retry_unauthorized(true);
+
+        let err = domain.get().execute_as_stream::<()>(None).wait().unwrap_err();
+
+        assert_eq!(err.status(), Some(401));
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(provider.token.get(), 1);
+
+        let mut body = String::new();
+        domain.get().execute_as_reader::<()>(None).unwrap().read_to_string(&mut body).unwrap();
+
+        assert_eq!(body, "[1]");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn auth_provider_reports_second_attempt() {
+        let server = test_server::TestServer::with_response(401, "still expired");
+        let mut domain = Domain::new(&server.url());
+        domain.auth_provider(RefreshingProvider {
+            token: rc::Rc::new(cell::Cell::new(5)),
+        });
+        domain.retry_unauthorized(true);
+
+        let err = domain.get().execute_as_string::<()>(None).unwrap_err();
+
+        assert_eq!(err.status(), Some(401));
+        assert_eq!(err.attempt(), Some(2));
+    }
+
+    #[test]
+    fn download_to_writes_file_and_reports_progress() {
+        let server = test_server::TestServer::with_response(200, "file contents");
+        let domain = Domain::new(&server.url());
+        let directory = test_dir::TestDir::new("download_to_writes_file_and_reports_progress");
+        let destination = directory.join("download.txt");
+
+        let mut reports = Vec::new();
+        let written = domain
+            .get()
+            .push(&"report.csv")
+            .download_to_with_progress::<()>(None, &destination, |progress| reports.push(progress))
+            .unwrap();
+
+        let contents = fs::read_to_string(&destination).unwrap();
+
+        assert_eq!(written, 13);
+        assert_eq!(contents, "file contents");
+        assert_eq!(
+            reports.last(),
+            Some(&DownloadProgress {
+                received: 13,
+                total: Some(13),
+            })
+        );
+    }
+
+    #[test]
+    fn download_to_leaves_no_file_on_error() {
+        let server = test_server::TestServer::with_response(500, "broken");
+        let domain = Domain::new(&server.url());
+        let directory = test_dir::TestDir::new("download_to_leaves_no_file_on_error");
+        let destination = directory.join("download.txt");
+
+        let result = domain.get().download_to::<()>(None, &destination);
+
+        assert!(result.is_err());
+        assert!(!destination.exists());
+    }
+
+    #[test]
+    fn resume_download_to_asks_for_the_rest() {
+        let server = test_server::TestServer::new(|request| {
+            if request.header("Range") == Some("bytes=5-")
+                && request.header("If-Range") == Some("\"v1\"")
+            {
+                test_server::response(
+                    206,
+                    &[("ETag", "\"v1\""), ("Content-Range", "bytes 5-12/13")],
+                    b"contents",
+                )
+            } else {
+                test_server::response(200, &[("ETag", "\"v1\"")], b"file contents")
+            }
+        });
+        let domain = Domain::new(&server.url());
+        let directory = test_dir::TestDir::new("resume_download_to_asks_for_the_rest");
+        let destination = directory.join("download.txt");
+        fs::write(directory.join("download.txt.part"), "file ").unwrap();
+        fs::write(directory.join("download.txt.part.validator"), "\"v1\"").unwrap();
+
+        let mut reports = Vec::new();
+        let written = domain
+            .get()
+            .resume_download_to_with_progress::<()>(None, &destination, |progress| {
+                reports.push(progress)
+            })
+            .unwrap();
+
+        let contents = fs::read_to_string(&destination).unwrap();
+
+        assert_eq!(written, 13);
+        assert_eq!(contents, "file contents");
+        assert_eq!(
+            reports.last(),
+            Some(&DownloadProgress {
+                received: 13,
+                total: Some(13),
+            })
+        );
+        assert!(!directory.join("download.txt.part.validator").exists());
+    }
+
+    #[test]
+    fn resume_download_to_restarts_when_file_changed() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(200, &[("ETag", "\"v2\"")], b"new contents")
+        });
+        let domain = Domain::new(&server.url());
+        let directory = test_dir::TestDir::new("resume_download_to_restarts_when_file_changed");
+        let destination = directory.join("download.txt");
+        fs::write(directory.join("download.txt.part"), "old ").unwrap();
+        fs::write(directory.join("download.txt.part.validator"), "\"v1\"").unwrap();
+
+        let written = domain.get().resume_download_to::<()>(None, &destination).unwrap();
+
+        let contents = fs::read_to_string(&destination).unwrap();
+
+        assert_eq!(written, 12);
+        assert_eq!(contents, "new contents");
+        assert_eq!(server.requests()[0].header("Range"), Some("bytes=4-"));
+    }
+
+    #[test]
+    fn resume_download_to_finishes_when_already_complete() {
+        let server = test_server::TestServer::new(|_| {
+            test_server::response(416, &[("Content-Range", "bytes */13")], b"")
+        });
+        let domain = Domain::new(&server.url());
+        let directory = test_dir::TestDir::new("resume_download_to_finishes_when_already_complete");
+        let destination = directory.join("download.txt");
+        fs::write(directory.join("download.txt.part"), "file contents").unwrap();
+        fs::write(directory.join("download.txt.part.validator"), "\"v1\"").unwrap();
+
+        let written = domain.get().resume_download_to::<()>(None, &destination).unwrap();
+
+        let contents = fs::read_to_string(&destination).unwrap();
+
+        assert_eq!(written, 13);
+        assert_eq!(contents, "file contents");
+        assert_eq!(server.requests().len(), 1);
+        assert!(!directory.join("download.txt.part").exists());
+        assert!(!directory.join("download.txt.part.validator").exists());
+    }
+
+    #[test]
+    fn resume_download_to_restarts_when_range_is_not_satisfiable() {
+        let server = test_server::TestServer::new(|request| {
+            if request.header("Range").is_some() {
+                test_server::response(416, &[("Content-Range", "bytes */12")], b"")
+            } else {
+                test_server::response(200, &[("ETag", "\"v2\"")], b"new contents")
+            }
+        });
+        let domain = Domain::new(&server.url());
+        let directory =
+            test_dir::TestDir::new("resume_download_to_restarts_when_range_is_not_satisfiable");
+        let destination = directory.join("download.txt");
+        fs::write(directory.join("download.txt.part"), "old contents, and longer").unwrap();
+        fs::write(directory.join("download.txt.part.validator"), "\"v1\"").unwrap();
+
+        let written = domain.get().resume_download_to::<()>(None, &destination).unwrap();
+
+        let contents = fs::read_to_string(&destination).unwrap();
+
+        assert_eq!(written, 12);
+        assert_eq!(contents, "new contents");
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(server.requests()[1].header("Range"), None);
+        assert!(!directory.join("download.txt.part.validator").exists());
+    }
+
+    #[test]
+    fn execute_as_json_sends_content_type() {
+        let server = test_server::TestServer::with_response(200, "[4,5]");
+        let domain = Domain::new(&server.url());
+
+        let value: Vec<u32> = domain.post().execute_as_json(Some(&vec![1, 2])).unwrap();
+
+        assert_eq!(value, vec![4, 5]);
+        assert_eq!(server.requests()[0].header("Content-Type"), Some("application/json"));
+    }
+
+    #[test]
+    fn execute_as_keeps_content_type_already_set() {
+        let server = test_server::TestServer::with_response(200, "[]");
+        let mut domain = Domain::new(&server.url());
+        domain.header("Content-Type", &"application/vnd.api+json");
+
+        let _: Vec<u32> = domain.put().execute_as(Some(&vec![1])).unwrap();
+
+        assert_eq!(server.requests()[0].header("Content-Type"), Some("application/vnd.api+json"));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn execute_as_reads_response_format_from_content_type() {
+        use crate::codec::Codec;
+        use crate::codec::Format;
+
+        let server = test_server::TestServer::new(|request| {
+            let numbers: Vec<u32> = Format::MessagePack.deserialize(&request.body).unwrap();
+            let doubled = numbers.iter().map(|n| n * 2).collect::<Vec<_>>();
+
+            test_server::response(
+                200,
+                &[("Content-Type", "application/json")],
+                &Format::Json.serialize(&doubled).unwrap(),
+            )
+        });
+        let mut domain = Domain::new(&server.url());
+        domain.format(Format::MessagePack);
+
+        let value: Vec<u32> = domain.post().execute_as(Some(&vec![1, 2])).unwrap();
+
+        assert_eq!(value, vec![2, 4]);
+        assert_eq!(server.requests()[0].header("Content-Type"), Some("application/msgpack"));
+    }
+
+    #[test]
+    fn custom_codec_is_used_for_the_domain() {
+        let server = test_server::TestServer::new(|request| {
+            test_server::response(
+                200,
+                &[("Content-Type", "application/x-reversed-json")],
+                &request.body,
+            )
+        });
+        let mut domain = Domain::new(&server.url());
+        domain.codec(ReversedJsonCodec);
+
+        let value: Vec<u32> = domain.post().execute_as(Some(&vec![1, 2])).unwrap();
+
+        assert_eq!(value, vec![1, 2]);
+        assert_eq!(server.requests()[0].body, b"]2,1[".to_vec());
+        assert_eq!(
+            server.requests()[0].header("Content-Type"),
+            Some("application/x-reversed-json")
+        );
+    }
+
+    #[test]
+    fn custom_codec_on_a_path_reads_known_formats_by_content_type() {
+        let server = test_server::TestServer::new(|request| {
+            let mut body = request.body.clone();
+            body.reverse();
+
+            test_server::response(200, &[("Content-Type", "application/json")], &body)
+        });
+        let domain = Domain::new(&server.url());
+
+        let value: Vec<u32> =
+            domain.post().codec(ReversedJsonCodec).execute_as(Some(&vec![3, 4])).unwrap();
+
+        assert_eq!(value, vec![3, 4]);
+        assert_eq!(server.requests()[0].body, b"]4,3[".to_vec());
+    }
+
+    #[test]
+    fn execute_at_reads_only_the_pointer() {
+        let server = test_server::TestServer::with_response(
+            200,
+            r#"{"data":{"items":[{"id":1},{"id":2}]},"other":"ignored"}"#,
+        );
+        let domain = Domain::new(&server.url());
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Item {
+            id: u32,
+        }
+
+        let items: Vec<Item> = domain.get().execute_at::<(), _>("/data/items", None).unwrap();
+
+        assert_eq!(
+            items,
+            vec![
+                Item {
+                    id: 1
+                },
+                Item {
+                    id: 2
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn execute_at_reports_pointer_and_fragment() {
+        let server = test_server::TestServer::with_response(
+            200,
+            r#"{"data":{"count":"many"},"big":"blob"}"#,
+        );
+        let domain = Domain::new(&server.url());
+
+        match domain.get().execute_at::<(), u32>("/data/count", None) {
+            Err(error::Error::DeserializationError {
+                text,
+                pointer,
+                ..
+            }) => {
+                assert_eq!(text, r#""many""#);
+                assert_eq!(pointer, Some("/data/count".to_string()));
+            },
+            other => panic!("expected deserialization error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserialization_error_describes_failure() {
+        let server = test_server::TestServer::with_response(
+            200,
+            r#"{"items":[{"owner":{"id":1}},{"owner":{"id":"two"}}],"padding":"lots and lots of padding text"}"#,
+        );
+        let domain = Domain::new(&server.url());
+
+        #[derive(Deserialize, Debug)]
+        struct Owner {
+            #[allow(dead_code)]
+            id: u32,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Item {
+            #[allow(dead_code)]
+            owner: Owner,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Items {
+            #[allow(dead_code)]
+            items: Vec<Item>,
+        }
+
+        let error = domain.get().push(&"items").execute_as_json::<(), Items>(None).unwrap_err();
+        match error {
+            error::Error::DeserializationError {
+                ref error,
+                ref excerpt,
+                ref request,
+                ..
+            } => {
+                assert_eq!(error.path(), Some("items[1].owner.id"));
+                assert_eq!(error.line(), Some(1));
+                assert_eq!(error.column(), Some(49));
+                assert_eq!(
+                    excerpt,
+                    r#"...:[{"owner":{"id":1}},{"owner":{"id":"two"}}],"padding":"lots and lots of padding..."#
+                );
+                assert_eq!(request.as_ref().unwrap().url, format!("{}/items", server.url()));
+            },
+            ref other => panic!("expected deserialization error, got {:?}", other),
+        }
+
+        let message = error.to_string();
+        assert!(message.starts_with(
+            "Failed to deserialize response at items[1].owner.id, line 1 column 49, near `"
+        ));
+        assert!(!message.contains("invalid type"));
+        let source = std::error::Error::source(&error).unwrap().to_string();
+        assert!(source.starts_with("at items[1].owner.id: invalid type"));
+        assert!(message.ends_with(&format!("for GET {}/items (attempt 1)", server.url())));
+    }
+
+    #[test]
+    fn execute_at_missing_pointer() {
+        let server = test_server::TestServer::with_response(200, r#"{"data":{}}"#);
+        let domain = Domain::new(&server.url());
+
+        match domain.get().execute_at::<(), u32>("/data/count", None) {
+            Err(error::Error::PointerNotFound {
+                pointer,
+                ..
+            }) => assert_eq!(pointer, "/data/count"),
+            other => panic!("expected pointer not found, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn execute_as_value() {
+        let server = test_server::TestServer::with_response(200, r#"{"name":"burgundy"}"#);
+        let domain = Domain::new(&server.url());
+
+        let value = domain.get().execute_as_value::<()>(None).unwrap();
+
+        assert_eq!(value["name"], "burgundy");
+    }
+
+    #[test]
+    fn errors_describe_the_request() {
+        let server = test_server::TestServer::with_response(500, "broken");
+        let domain = Domain::new(&server.url());
+
+        let error = domain
+            .delete()
+            .push(&"items")
+            .query("access_token", &"secret123")
+            .execute_as_string::<()>(None)
+            .unwrap_err();
+
+        let url = format!("{}/items?access_token=REDACTED", server.url());
+        assert_eq!(error.method(), Some(Method::Delete));
+        assert_eq!(error.url(), Some(url.as_str()));
+        assert_eq!(error.attempt(), Some(1));
+        assert_eq!(
+            error.to_string(),
+            format!("Http request was not ok, status 500, for DELETE {} (attempt 1)", url)
+        );
+    }
+
+    #[test]
+    fn network_errors_describe_the_request() {
+        let domain = Domain::new("http://127.0.0.1:1");
+
+        let error = domain.get().push(&"items").execute_as_string::<()>(None).unwrap_err();
+
+        assert!(error.to_string().starts_with("Network error"));
+        assert_eq!(error.url(), Some("http://127.0.0.1:1/items"));
+    }
 }
@@ -0,0 +1,73 @@
+//! Percent encoding, as used in urls.
+
+use std::fmt::Write;
+
+/// Characters which are allowed through as they are by the `+` and `#`
+/// operators of URI templates, on top of the unreserved characters.
+const RESERVED: &str = ":/?#[]@!$&'()*+,;=";
+
+/// Percent encodes a segment of a path, such as one given to `Path::push`
+/// or expanded from a URI template. Everything but the unreserved
+/// characters is encoded, including `/`.
+pub(crate) fn encode_path_segment(text: &str) -> String {
+    encode(text, false)
+}
+
+/// Percent encodes everything but the unreserved and reserved characters,
+/// leaving existing percent encoding alone. This is for the `+` and `#`
+/// operators of URI templates.
+pub(crate) fn encode_allowing_reserved(text: &str) -> String {
+    encode(text, true)
+}
+
+/// Percent encodes everything but the unreserved characters. When
+/// `allow_reserved` is set, reserved characters and existing percent
+/// encoding are also left alone.
+fn encode(
+    text: &str,
+    allow_reserved: bool,
+) -> String {
+    let bytes = text.as_bytes();
+    let mut encoded = String::with_capacity(text.len());
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let c = byte as char;
+        let is_unreserved = c.is_ascii_alphanumeric() || "-._~".contains(c);
+        let is_reserved = c.is_ascii() && RESERVED.contains(c);
+        let is_percent_encoded = c == '%'
+            && bytes.len() > i + 2
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+
+        if is_unreserved || (allow_reserved && (is_reserved || is_percent_encoded)) {
+            encoded.push(c);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+
+    encoded
+}
+
+/// Decodes percent encoding. Invalid encoding is left as it is.
+pub(crate) fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,221 @@
+//! Opening tunnels through a proxy, using HTTP `CONNECT` or SOCKS5.
+
+use crate::secret;
+use futures::future;
+use futures::Async;
+use futures::Future;
+use futures::Poll;
+use std::io;
+use std::net;
+use tokio::io::AsyncRead;
+use tokio::net::TcpStream;
+
+/// The most that is read of the response to a `CONNECT`, before giving up.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+type Tunnel = Box<dyn Future<Item = TcpStream, Error = io::Error> + Send>;
+
+/// Asks an HTTP proxy to open a tunnel to the host and port.
+pub(super) fn http_connect(
+    socket: TcpStream,
+    host: &str,
+    port: u16,
+    authorization: Option<secret::Secret>,
+) -> Tunnel {
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization.expose()));
+    }
+    request.push_str("\r\n");
+
+    Box::new(tokio::io::write_all(socket, request.into_bytes()).and_then(|(socket, _)| {
+        ConnectResponse {
+            socket: Some(socket),
+            head: Vec::new(),
+        }
+    }))
+}
+
+/// Reads the head of the response to a `CONNECT`, and hands back the
+/// socket if the tunnel was opened.
+struct ConnectResponse {
+    socket: Option<TcpStream>,
+    head: Vec<u8>,
+}
+
+impl Future for ConnectResponse {
+    type Item = TcpStream;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<TcpStream, io::Error> {
+        while !self.head.ends_with(b"\r\n\r\n") {
+            if self.head.len() > MAX_CONNECT_RESPONSE {
+                return Err(proxy_error("the proxy's response to CONNECT is too long"));
+            }
+
+            // Read a byte at a time, so nothing past the head is taken from
+            // the tunnel.
+            let mut byte = [0; 1];
+            let socket = self.socket.as_mut().expect("polled after the tunnel was opened");
+            match socket.poll_read(&mut byte)? {
+                Async::Ready(0) => {
+                    return Err(proxy_error("the proxy closed the connection during CONNECT"))
+                },
+                Async::Ready(_) => self.head.push(byte[0]),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+
+        let head = String::from_utf8_lossy(&self.head);
+        let status = head.split_whitespace().nth(1).unwrap_or("");
+        if !status.starts_with('2') {
+            let status_line = head.lines().next().unwrap_or("");
+            return Err(proxy_error(&format!("the proxy refused to CONNECT, '{}'", status_line)));
+        }
+
+        Ok(Async::Ready(self.socket.take().expect("polled after the tunnel was opened")))
+    }
+}
+
+/// Asks a SOCKS5 proxy to open a tunnel to the host and port. A host which
+/// is not an IP address is sent as a name, for the proxy to resolve.
+pub(super) fn socks5_connect(
+    socket: TcpStream,
+    host: &str,
+    port: u16,
+    auth: Option<(String, secret::Secret)>,
+) -> Tunnel {
+    let greeting = if auth.is_some() {
+        vec![5, 2, 0, 2]
+    } else {
+        vec![5, 1, 0]
+    };
+    let request = match socks5_request(host, port) {
+        Ok(request) => request,
+        Err(err) => return Box::new(future::err(err)),
+    };
+
+    let tunnel = tokio::io::write_all(socket, greeting)
+        .and_then(|(socket, _)| tokio::io::read_exact(socket, [0; 2]))
+        .and_then(move |(socket, choice)| -> Tunnel {
+            match (choice, auth) {
+                ([5, 0], _) => Box::new(future::ok(socket)),
+                ([5, 2], Some((user, password))) => socks5_authenticate(socket, &user, &password),
+                _ => Box::new(future::err(proxy_error(
+                    "the SOCKS5 proxy accepts none of the auth methods offered",
+                ))),
+            }
+        })
+        .and_then(move |socket| tokio::io::write_all(socket, request))
+        .and_then(|(socket, _)| tokio::io::read_exact(socket, [0; 4]))
+        .and_then(|(socket, reply)| -> Tunnel {
+            if reply[0] != 5 || reply[1] != 0 {
+                return Box::new(future::err(proxy_error(&format!(
+                    "the SOCKS5 proxy refused to connect, {}",
+                    socks5_reply_reason(reply[1])
+                ))));
+            }
+
+            // The address the proxy bound is not needed, but has to be read.
+            let skip = move |socket, length: usize| {
+                tokio::io::read_exact(socket, vec![0; length]).map(|(socket, _)| socket)
+            };
+            match reply[3] {
+                1 => Box::new(skip(socket, 4 + 2)),
+                4 => Box::new(skip(socket, 16 + 2)),
+                3 => Box::new(
+                    tokio::io::read_exact(socket, [0; 1])
+                        .and_then(move |(socket, length)| skip(socket, length[0] as usize + 2)),
+                ),
+                _ => Box::new(future::err(proxy_error(
+                    "the SOCKS5 proxy replied with an unknown address",
+                ))),
+            }
+        });
+
+    Box::new(tunnel)
+}
+
+fn socks5_authenticate(
+    socket: TcpStream,
+    user: &str,
+    password: &secret::Secret,
+) -> Tunnel {
+    let password = password.expose();
+    if user.len() > 255 || password.len() > 255 {
+        return Box::new(future::err(proxy_error(
+            "SOCKS5 user names and passwords can be at most 255 bytes",
+        )));
+    }
+
+    let mut request = vec![1, user.len() as u8];
+    request.extend_from_slice(user.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+
+    Box::new(
+        tokio::io::write_all(socket, request)
+            .and_then(|(socket, _)| tokio::io::read_exact(socket, [0; 2]))
+            .and_then(|(socket, reply)| {
+                if reply[1] == 0 {
+                    Ok(socket)
+                } else {
+                    Err(proxy_error("the SOCKS5 proxy rejected the user name and password"))
+                }
+            }),
+    )
+}
+
+/// The request asking the proxy to connect to the host and port.
+fn socks5_request(
+    host: &str,
+    port: u16,
+) -> Result<Vec<u8>, io::Error> {
+    let mut request = vec![5, 1, 0];
+
+    match host.parse::<net::IpAddr>() {
+        Ok(net::IpAddr::V4(address)) => {
+            request.push(1);
+            request.extend_from_slice(&address.octets());
+        },
+        Ok(net::IpAddr::V6(address)) => {
+            request.push(4);
+            request.extend_from_slice(&address.octets());
+        },
+        Err(_) if host.len() > 255 => {
+            return Err(proxy_error("host names sent to SOCKS5 proxies can be at most 255 bytes"))
+        },
+        Err(_) => {
+            request.push(3);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        },
+    }
+
+    request.extend_from_slice(&port.to_be_bytes());
+    Ok(request)
+}
+
+fn socks5_reply_reason(reply: u8) -> &'static str {
+    match reply {
+        1 => "general failure",
+        2 => "connection not allowed",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn proxy_error(reason: &str) -> io::Error {
+    io::Error::other(reason.to_string())
+}
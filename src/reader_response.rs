@@ -0,0 +1,79 @@
+use crate::native_client;
+use crate::response_headers::ResponseHeaders;
+use crate::stream_response::BodyStream;
+use crate::stream_response::StreamResponse;
+use futures::stream;
+use futures::stream::Stream;
+use std::cell;
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::rc;
+
+/// A response where the body is read using blocking IO.
+///
+/// The body is pulled from the network as it is read, rather than being
+/// held in memory all at once.
+pub struct ReaderResponse {
+    pub status: u32,
+    pub headers: ResponseHeaders,
+
+    /// Held to keep the runtime alive, which drives the connection.
+    _client: rc::Rc<cell::RefCell<native_client::NativeClient>>,
+    body: stream::Wait<BodyStream>,
+    chunk: Vec<u8>,
+    chunk_position: usize,
+}
+
+impl ReaderResponse {
+    pub(crate) fn new(
+        client: rc::Rc<cell::RefCell<native_client::NativeClient>>,
+        response: StreamResponse,
+    ) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers,
+            _client: client,
+            body: response.body.wait(),
+            chunk: Vec::new(),
+            chunk_position: 0,
+        }
+    }
+}
+
+impl io::Read for ReaderResponse {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        while self.chunk_position >= self.chunk.len() {
+            match self.body.next() {
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.chunk_position = 0;
+                },
+                Some(Err(err)) => return Err(io::Error::other(err.to_string())),
+                None => return Ok(0),
+            }
+        }
+
+        let remaining = &self.chunk[self.chunk_position..];
+        let len = cmp::min(remaining.len(), buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.chunk_position += len;
+
+        Ok(len)
+    }
+}
+
+impl fmt::Debug for ReaderResponse {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("ReaderResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
@@ -0,0 +1,185 @@
+use crate::method;
+use http;
+use std::fmt;
+
+/// Headers whose values are hidden from `Debug` output.
+const SENSITIVE_HEADERS: &[&str] = &["Authorization", "Proxy-Authorization"];
+
+/// A request which is ready to be sent.
+///
+/// This is the full url, with all headers from the `Domain` and `Path`
+/// added, and the body already serialized.
+#[derive(Clone)]
+pub struct Request {
+    pub method: method::Method,
+
+    /// The url, including the values of any secret query parameters.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+
+    /// The url with secret query parameters masked, for `Debug` output.
+    display_url: String,
+
+    is_streamed: bool,
+}
+
+impl Request {
+    pub(crate) fn new(
+        method: method::Method,
+        url: String,
+        display_url: String,
+        body: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            method,
+            url,
+            headers: Vec::new(),
+            body,
+            display_url,
+            is_streamed: false,
+        }
+    }
+
+    /// True if the response body is streamed, such as for
+    /// `Path::execute_as_reader` or `Path::download_to`, rather than read
+    /// in full.
+    ///
+    /// Middleware sees the status and headers of a streamed response, but
+    /// its body is left empty, as it has not been read yet.
+    pub fn is_streamed(&self) -> bool {
+        self.is_streamed
+    }
+
+    pub(crate) fn set_streamed(&mut self) {
+        self.is_streamed = true;
+    }
+
+    /// The first value for the header given.
+    /// Header keys are matched case insensitively.
+    pub fn header(
+        &self,
+        key: &str,
+    ) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the header, replacing any values it already has.
+    pub fn set_header(
+        &mut self,
+        key: &str,
+        value: &impl fmt::Display,
+    ) {
+        self.headers.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+        self.headers.push((key.to_string(), value.to_string()));
+    }
+
+    /// The url with the values of secret query parameters masked.
+    pub(crate) fn display_url(&self) -> &str {
+        &self.display_url
+    }
+
+    /// The path of the url, such as `/users/123`.
+    pub fn path(&self) -> String {
+        match self.url.parse::<http::Uri>() {
+            Ok(uri) => uri.path().to_string(),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// The query string of the url, without the leading `?`.
+    /// This is empty when there is no query.
+    pub fn query(&self) -> String {
+        match self.url.parse::<http::Uri>() {
+            Ok(uri) => uri.query().unwrap_or("").to_string(),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// The body, or an empty slice if there isn't one.
+    pub fn body_bytes(&self) -> &[u8] {
+        self.body.as_deref().unwrap_or(&[])
+    }
+}
+
+/// True for headers which hold credentials.
+pub(crate) fn is_sensitive_header(key: &str) -> bool {
+    SENSITIVE_HEADERS.iter().any(|name| name.eq_ignore_ascii_case(key))
+}
+
+impl fmt::Debug for Request {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(key, value)| {
+                let value = if is_sensitive_header(key) {
+                    "REDACTED"
+                } else {
+                    value
+                };
+
+                (key.as_str(), value)
+            })
+            .collect::<Vec<_>>();
+
+        f.debug_struct("Request")
+            .field("method", &self.method)
+            .field("url", &self.display_url)
+            .field("headers", &headers)
+            .field("body", &self.body.as_ref().map(|body| body.len()))
+            .field("is_streamed", &self.is_streamed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn path_and_query() {
+        let request = Request::new(
+            method::Method::Get,
+            "https://api.example.com/orders/1?symbol=BTC&side=buy".to_string(),
+            "https://api.example.com/orders/1?symbol=BTC&side=buy".to_string(),
+            None,
+        );
+
+        assert_eq!(request.path(), "/orders/1");
+        assert_eq!(request.query(), "symbol=BTC&side=buy");
+    }
+
+    #[test]
+    fn set_header_replaces_values() {
+        let mut request = Request::new(
+            method::Method::Get,
+            "https://api.example.com".to_string(),
+            "https://api.example.com".to_string(),
+            None,
+        );
+        request.headers.push(("x-sig".to_string(), "old".to_string()));
+        request.set_header("X-Sig", &"new");
+
+        assert_eq!(request.headers, vec![("X-Sig".to_string(), "new".to_string())]);
+    }
+
+    #[test]
+    fn debug_hides_authorization() {
+        let mut request = Request::new(
+            method::Method::Get,
+            "https://api.example.com".to_string(),
+            "https://api.example.com".to_string(),
+            None,
+        );
+        request.set_header("Authorization", &"Bearer abc123");
+
+        assert!(!format!("{:?}", request).contains("abc123"));
+    }
+}
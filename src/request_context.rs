@@ -0,0 +1,101 @@
+use crate::method;
+use std::fmt;
+
+/// What a query parameter's value is replaced with, when it is sensitive.
+const REDACTED: &str = "REDACTED";
+
+/// Query parameters with these in their name are redacted.
+const SENSITIVE_QUERY_PARTS: &[&str] =
+    &["token", "secret", "password", "passwd", "api_key", "apikey", "signature", "credential"];
+
+/// Query parameters with exactly these names are redacted.
+const SENSITIVE_QUERY_NAMES: &[&str] = &["key", "sig", "auth", "code"];
+
+/// Describes the request an error came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub method: method::Method,
+
+    /// The full url of the request. The values of sensitive query
+    /// parameters, such as `api_key` or `access_token`, are redacted.
+    pub url: String,
+
+    /// Which attempt at making the request this was, starting from 1.
+    pub attempt: u32,
+}
+
+impl RequestContext {
+    pub(crate) fn new(
+        method: method::Method,
+        url: &str,
+        attempt: u32,
+    ) -> Self {
+        Self {
+            method,
+            url: redact_url(url),
+            attempt,
+        }
+    }
+}
+
+impl fmt::Display for RequestContext {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "{} {} (attempt {})", self.method, self.url, self.attempt)
+    }
+}
+
+/// Replaces the values of sensitive query parameters in the url.
+pub(crate) fn redact_url(url: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some(parts) => parts,
+        None => return url.to_string(),
+    };
+
+    let query = query
+        .split('&')
+        .map(|param| match param.split_once('=') {
+            Some((key, _)) if is_sensitive(key) => format!("{}={}", key, REDACTED),
+            _ => param.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{}?{}", base, query)
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_ascii_lowercase().replace('-', "_");
+
+    SENSITIVE_QUERY_NAMES.contains(&key.as_str())
+        || SENSITIVE_QUERY_PARTS.iter().any(|part| key.contains(part))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redact_url_without_query() {
+        assert_eq!(redact_url("https://api.example.com/list"), "https://api.example.com/list");
+    }
+
+    #[test]
+    fn redact_url_hides_sensitive_params() {
+        let url = "https://api.example.com/list?page=2&api_key=abc123&Access-Token=xyz&key=k";
+
+        assert_eq!(
+            redact_url(url),
+            "https://api.example.com/list?page=2&api_key=REDACTED&Access-Token=REDACTED&key=REDACTED"
+        );
+    }
+
+    #[test]
+    fn redact_url_keeps_similar_names() {
+        let url = "https://api.example.com/list?keyword=cats&monkey=1";
+
+        assert_eq!(redact_url(url), url);
+    }
+}
@@ -18,13 +18,25 @@ impl Headers {
         key: &str,
         value: &impl fmt::Display,
     ) {
-        if let None = self.headers {
+        if self.headers.is_none() {
             self.headers = Some(Vec::new());
         }
 
         self.headers.as_mut().unwrap().push((key.to_string(), value.to_string()));
     }
 
+    /// True if a header with this key has been added.
+    /// Header keys are matched case insensitively.
+    pub(crate) fn contains(
+        &self,
+        key: &str,
+    ) -> bool {
+        match self.headers {
+            Some(ref headers) => headers.iter().any(|(name, _)| name.eq_ignore_ascii_case(key)),
+            None => false,
+        }
+    }
+
     /// An iterator over all header key => value pairs.
     pub(crate) fn for_each(
         &self,
@@ -1,4 +1,7 @@
+use crate::codec;
 use crate::error;
+use crate::percent_encoding;
+use crate::secret;
 use serde;
 use std::fmt;
 use std::fmt::Write;
@@ -17,6 +20,13 @@ pub(crate) struct RequestInformation {
     url: String,
     query: query_builder::QueryBuilder,
     headers: headers::Headers,
+    codec: Option<codec::BodyCodec>,
+    authorization: Option<secret::Secret>,
+    /// Where the url is sent instead of the domain, for paths made from
+    /// a url on another origin, or outside of the domain's base path.
+    origin: Option<String>,
+    /// Other origins which absolute urls are allowed to point to.
+    allowed_origins: Vec<String>,
 }
 
 impl RequestInformation {
@@ -25,27 +35,115 @@ impl RequestInformation {
             url,
             query: query_builder::QueryBuilder::new(),
             headers: headers::Headers::new(),
+            codec: None,
+            authorization: None,
+            origin: None,
+            allowed_origins: Vec::new(),
         }
     }
 
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Allows absolute urls to point to the origin given, such as
+    /// `https://cdn.example.com`, as well as to the domain.
+    pub(crate) fn allow_origin(
+        &mut self,
+        origin: &str,
+    ) {
+        self.allowed_origins.push(origin.trim_end_matches('/').to_string());
+    }
+
+    /// A copy of this, pointed at the absolute url given instead.
+    ///
+    /// The url must be on the origin of the `domain`, or on an origin it
+    /// allows. Query parameters which the domain adds anyway are dropped
+    /// from the url, so they are not sent twice. Returns `None` if the url
+    /// is not on the domain.
+    pub(crate) fn with_absolute_url(
+        &self,
+        domain: &RequestInformation,
+        url: &str,
+    ) -> Option<Self> {
+        let url = url.split('#').next().unwrap_or(url);
+        let base = domain.url.trim_end_matches('/');
+        let (origin, rest) = match url.strip_prefix(base) {
+            Some(rest) if rest.is_empty() || rest.starts_with(['/', '?']) => (None, rest),
+            _ => {
+                let url_origin = origin(url)?;
+                let rest = &url[url_origin.len()..];
+                if is_same_origin(url_origin, base) {
+                    (origin(base).map(str::to_string), rest)
+                } else if domain
+                    .allowed_origins
+                    .iter()
+                    .any(|allowed| is_same_origin(url_origin, allowed))
+                {
+                    (Some(url_origin.to_string()), rest)
+                } else {
+                    return None;
+                }
+            },
+        };
+
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let domain_query = domain.query.exposed().to_string();
+        let mut domain_params = domain_query.split('&').collect::<Vec<_>>();
+
+        let mut info = self.clone();
+        info.url = path.to_string();
+        info.origin = origin;
+        info.query = query_builder::QueryBuilder::new();
+
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            match domain_params.iter().position(|domain_param| *domain_param == param) {
+                Some(i) => {
+                    domain_params.remove(i);
+                },
+                None => info.query.add_raw(param),
+            }
+        }
+
+        Some(info)
+    }
+
+    /// Adds a segment onto the end of the path, percent encoding it.
     pub(crate) fn push_path_part(
         &mut self,
         part: &impl fmt::Display,
     ) -> Result<(), error::Error> {
-        write!(self.url, "/{}", part)?;
+        let mut text = String::new();
+        write!(text, "{}", part)?;
+
+        self.url.push('/');
+        self.url.push_str(&percent_encoding::encode_path_segment(&text));
 
         Ok(())
     }
 
+    /// Adds onto the end of the last segment of the path, percent encoding
+    /// it.
     pub(crate) fn push_path_part_partial(
         &mut self,
         part: &impl fmt::Display,
     ) -> Result<(), error::Error> {
-        write!(self.url, "{}", part)?;
+        let mut text = String::new();
+        write!(text, "{}", part)?;
+
+        self.url.push_str(&percent_encoding::encode_path_segment(&text));
 
         Ok(())
     }
 
+    /// Adds onto the end of the path, which is already encoded.
+    pub(crate) fn push_path_raw(
+        &mut self,
+        path: &str,
+    ) {
+        self.url.push_str(path);
+    }
+
     pub(crate) fn add_query_param(
         &mut self,
         key: &str,
@@ -54,6 +152,22 @@ impl RequestInformation {
         self.query.add(key, value)
     }
 
+    /// Adds query parameters which are already encoded.
+    pub(crate) fn add_query_raw(
+        &mut self,
+        query: &str,
+    ) {
+        self.query.add_raw(query);
+    }
+
+    pub(crate) fn add_secret_query_param(
+        &mut self,
+        key: &str,
+        value: secret::Secret,
+    ) {
+        self.query.add_secret(key, value);
+    }
+
     pub(crate) fn add_query_blob<B: serde::ser::Serialize + ?Sized>(
         &mut self,
         blob: &B,
@@ -69,6 +183,36 @@ impl RequestInformation {
         self.headers.add(key, value);
     }
 
+    pub(crate) fn has_header(
+        &self,
+        key: &str,
+    ) -> bool {
+        self.headers.contains(key)
+    }
+
+    pub(crate) fn set_codec(
+        &mut self,
+        codec: codec::BodyCodec,
+    ) {
+        self.codec = Some(codec);
+    }
+
+    pub(crate) fn codec(&self) -> Option<&codec::BodyCodec> {
+        self.codec.as_ref()
+    }
+
+    /// Sets the `Authorization` header. This replaces any set before.
+    pub(crate) fn set_authorization(
+        &mut self,
+        authorization: secret::Secret,
+    ) {
+        self.authorization = Some(authorization);
+    }
+
+    pub(crate) fn authorization(&self) -> Option<&secret::Secret> {
+        self.authorization.as_ref()
+    }
+
     pub(crate) fn for_each_header(
         &self,
         f: impl FnMut((&str, &str)),
@@ -77,9 +221,37 @@ impl RequestInformation {
     }
 }
 
+/// The url the request is sent to.
+/// This includes the real values of secret query parameters.
 pub(crate) fn to_full_url(
     domain: &RequestInformation,
     parts: &RequestInformation,
+) -> Result<String, fmt::Error> {
+    format_url(domain, parts, true)
+}
+
+/// The url with the values of secret query parameters masked.
+/// This is what is shown in logs, and when displaying a `Path`.
+pub(crate) fn to_display_url(
+    domain: &RequestInformation,
+    parts: &RequestInformation,
+) -> Result<String, fmt::Error> {
+    format_url(domain, parts, false)
+}
+
+/// Writes the url, with the values of secret query parameters masked.
+pub(crate) fn write_full_url(
+    f: &mut fmt::Formatter,
+    domain: &RequestInformation,
+    parts: &RequestInformation,
+) -> fmt::Result {
+    write_url(f, domain, parts, false)
+}
+
+fn format_url(
+    domain: &RequestInformation,
+    parts: &RequestInformation,
+    expose_secrets: bool,
 ) -> Result<String, fmt::Error> {
     let mut text = String::new();
     write!(
@@ -87,35 +259,62 @@ pub(crate) fn to_full_url(
         "{}",
         UrlFormatter {
             domain,
-            parts
+            parts,
+            expose_secrets,
         }
     )?;
     Ok(text)
 }
 
-pub(crate) fn write_full_url(
+fn write_url(
     f: &mut fmt::Formatter,
     domain: &RequestInformation,
     parts: &RequestInformation,
+    expose_secrets: bool,
 ) -> fmt::Result {
-    write!(f, "{}{}", domain.url.trim_right_matches(&"/"), parts.url)?;
+    let origin = parts.origin.as_deref().unwrap_or(&domain.url);
+    write!(f, "{}{}", origin.trim_end_matches(&"/"), parts.url)?;
 
-    if !domain.query.is_empty() {
-        write!(f, "?{}", domain.query)?;
-
-        if !parts.query.is_empty() {
-            write!(f, "&{}", parts.query)?;
+    let queries = [&domain.query, &parts.query];
+    let mut separator = "?";
+    for query in queries.iter().filter(|query| !query.is_empty()) {
+        if expose_secrets {
+            write!(f, "{}{}", separator, query.exposed())?;
+        } else {
+            write!(f, "{}{}", separator, query)?;
         }
-    } else if !parts.query.is_empty() {
-        write!(f, "?{}", parts.query)?;
+
+        separator = "&";
     }
 
     Ok(())
 }
 
+/// The scheme and authority of an absolute url, such as
+/// `https://api.example.com:8080`.
+fn origin(url: &str) -> Option<&str> {
+    let host_start = url.find("://")? + 3;
+    let host_end = url[host_start..].find(['/', '?']).map(|i| host_start + i).unwrap_or(url.len());
+
+    Some(&url[..host_end]).filter(|_| host_end > host_start)
+}
+
+/// True if both urls are on the same origin. The scheme and host are
+/// compared case insensitively.
+fn is_same_origin(
+    url_a: &str,
+    url_b: &str,
+) -> bool {
+    match (origin(url_a), origin(url_b)) {
+        (Some(origin_a), Some(origin_b)) => origin_a.eq_ignore_ascii_case(origin_b),
+        _ => false,
+    }
+}
+
 struct UrlFormatter<'a> {
     domain: &'a RequestInformation,
     parts: &'a RequestInformation,
+    expose_secrets: bool,
 }
 
 impl<'a> fmt::Display for UrlFormatter<'a> {
@@ -123,18 +322,6 @@ impl<'a> fmt::Display for UrlFormatter<'a> {
         &self,
         f: &mut fmt::Formatter,
     ) -> fmt::Result {
-        write!(f, "{}{}", self.domain.url.trim_right_matches(&"/"), self.parts.url)?;
-
-        if !self.domain.query.is_empty() {
-            write!(f, "?{}", self.domain.query)?;
-
-            if !self.parts.query.is_empty() {
-                write!(f, "&{}", self.parts.query)?;
-            }
-        } else if !self.parts.query.is_empty() {
-            write!(f, "?{}", self.parts.query)?;
-        }
-
-        Ok(())
+        write_url(f, self.domain, self.parts, self.expose_secrets)
     }
 }
@@ -1,46 +1,52 @@
 use crate::error;
+use crate::secret;
 use serde;
 use serde_urlencoded;
 use std::fmt;
 use std::fmt::Write;
 
-const QUERY_STRING_START_SIZE: usize = 20;
+/// What the values of secret query parameters are shown as.
+const MASKED_VALUE: &str = "REDACTED";
 
 #[derive(Debug, Clone)]
 pub(crate) struct QueryBuilder {
-    contents: Option<String>,
+    parts: Vec<QueryPart>,
+}
+
+/// One or more key/value pairs of the query string.
+#[derive(Debug, Clone)]
+enum QueryPart {
+    Plain(String),
+    Secret {
+        key: String,
+        value: secret::Secret,
+    },
 }
 
 impl QueryBuilder {
     /// Trivial constructor.
     pub(crate) fn new() -> Self {
         QueryBuilder {
-            contents: None,
+            parts: Vec::new(),
         }
     }
 
     /// True if this is empty.
     pub(crate) fn is_empty(&self) -> bool {
-        if let Some(ref query) = self.contents {
-            query.len() == 0
-        } else {
-            true
-        }
+        self.parts.is_empty()
     }
+
     pub(crate) fn add_blob<B: serde::ser::Serialize + ?Sized>(
         &mut self,
         blob: &B,
     ) -> Result<(), error::Error> {
-        let query_str = self.get_query_string_buffer();
+        let blob_str =
+            serde_urlencoded::to_string(blob).map_err(error::Error::new_serialize_query_error)?;
 
-        if query_str.len() > 0 {
-            write!(query_str, "&")?;
+        if !blob_str.is_empty() {
+            self.parts.push(QueryPart::Plain(blob_str));
         }
 
-        let blob_str =
-            serde_urlencoded::to_string(&blob).map_err(error::Error::new_serialize_query_error)?;
-        write!(query_str, "{}", &blob_str)?;
-
         Ok(())
     }
 
@@ -50,37 +56,91 @@ impl QueryBuilder {
         key: &str,
         value: &impl fmt::Display,
     ) -> Result<(), error::Error> {
-        let query_str = self.get_query_string_buffer();
+        let mut part = String::new();
+        write!(part, "{}={}", &key, &value)?;
+        self.parts.push(QueryPart::Plain(part));
 
-        if query_str.len() > 0 {
-            write!(query_str, "&")?;
+        Ok(())
+    }
+
+    /// Pushes query parameters which are already encoded, such as
+    /// `page=2&sort=name`.
+    pub(crate) fn add_raw(
+        &mut self,
+        query: &str,
+    ) {
+        if !query.is_empty() {
+            self.parts.push(QueryPart::Plain(query.to_string()));
         }
+    }
 
-        write!(query_str, "{}={}", &key, &value)?;
+    /// Pushes a query parameter whose value is masked when displayed.
+    pub(crate) fn add_secret(
+        &mut self,
+        key: &str,
+        value: secret::Secret,
+    ) {
+        self.parts.push(QueryPart::Secret {
+            key: key.to_string(),
+            value,
+        });
+    }
 
-        Ok(())
+    /// Displays the query with the real values of secret parameters, for
+    /// sending over the wire.
+    pub(crate) fn exposed(&self) -> ExposedQuery<'_> {
+        ExposedQuery {
+            query: self,
+        }
     }
 
-    fn get_query_string_buffer(&mut self) -> &mut String {
-        if let None = self.contents {
-            let query = String::with_capacity(QUERY_STRING_START_SIZE);
-            self.contents = Some(query);
+    fn write_parts(
+        &self,
+        f: &mut fmt::Formatter,
+        expose_secrets: bool,
+    ) -> fmt::Result {
+        for (i, part) in self.parts.iter().enumerate() {
+            if i > 0 {
+                write!(f, "&")?;
+            }
+
+            match part {
+                QueryPart::Plain(text) => write!(f, "{}", text)?,
+                QueryPart::Secret {
+                    key,
+                    value,
+                } if expose_secrets => write!(f, "{}={}", key, value.expose())?,
+                QueryPart::Secret {
+                    key,
+                    ..
+                } => write!(f, "{}={}", key, MASKED_VALUE)?,
+            }
         }
 
-        self.contents.as_mut().unwrap()
+        Ok(())
     }
 }
 
+/// Secret values are masked.
 impl fmt::Display for QueryBuilder {
     fn fmt(
         &self,
         f: &mut fmt::Formatter,
     ) -> fmt::Result {
-        if let Some(ref contents) = self.contents {
-            write!(f, "{}", contents)
-        } else {
-            Ok(())
-        }
+        self.write_parts(f, false)
+    }
+}
+
+pub(crate) struct ExposedQuery<'a> {
+    query: &'a QueryBuilder,
+}
+
+impl<'a> fmt::Display for ExposedQuery<'a> {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        self.query.write_parts(f, true)
     }
 }
 
@@ -98,7 +158,7 @@ mod test {
     #[test]
     fn one_query_key_value() {
         let mut query = QueryBuilder::new();
-        query.add(&"key", &"value");
+        query.add("key", &"value").unwrap();
 
         assert_eq!(query.to_string(), "key=value");
     }
@@ -106,9 +166,9 @@ mod test {
     #[test]
     fn multiple_query_key_value() {
         let mut query = QueryBuilder::new();
-        query.add(&"key", &"value");
-        query.add(&"donkeyfy", &true);
-        query.add(&"num_cats", &123);
+        query.add("key", &"value").unwrap();
+        query.add("donkeyfy", &true).unwrap();
+        query.add("num_cats", &123).unwrap();
 
         assert_eq!(query.to_string(), "key=value&donkeyfy=true&num_cats=123");
     }
@@ -127,7 +187,7 @@ mod test {
             name: "abc_999_xyz",
         };
 
-        query.add_blob(&blob);
+        query.add_blob(&blob).unwrap();
 
         assert_eq!(query.to_string(), "pages=123&name=abc_999_xyz");
     }
@@ -147,10 +207,21 @@ mod test {
             name: "abc_999_xyz",
         };
 
-        query.add(&"donkeyfy", &true);
-        query.add_blob(&blob);
-        query.add(&"num_cats", &123);
+        query.add("donkeyfy", &true).unwrap();
+        query.add_blob(&blob).unwrap();
+        query.add("num_cats", &123).unwrap();
 
         assert_eq!(query.to_string(), "donkeyfy=true&pages=123&name=abc_999_xyz&num_cats=123");
     }
+
+    #[test]
+    fn secret_values_are_masked() {
+        let mut query = QueryBuilder::new();
+        query.add("page", &2).unwrap();
+        query.add_secret("api_key", secret::Secret::from("abc123"));
+
+        assert_eq!(query.to_string(), "page=2&api_key=REDACTED");
+        assert_eq!(query.exposed().to_string(), "page=2&api_key=abc123");
+        assert!(!format!("{:?}", query).contains("abc123"));
+    }
 }
@@ -1,4 +1,26 @@
+use crate::link;
+use crate::response_headers::ResponseHeaders;
+use std::borrow;
+
+#[derive(Debug, Clone)]
 pub struct Response {
-    pub body: String,
+    /// The raw bytes of the body, which may not be text, such as with
+    /// MessagePack or CBOR. Use `text` to read it as a string.
+    pub body: Vec<u8>,
     pub status: u32,
+    pub headers: ResponseHeaders,
+}
+
+impl Response {
+    /// The links from the `Link` headers of the response.
+    /// i.e. the next page of results.
+    pub fn links(&self) -> Vec<link::Link> {
+        self.headers.links()
+    }
+
+    /// The body as text. Bytes which are not valid UTF-8 are replaced
+    /// with `U+FFFD`.
+    pub fn text(&self) -> borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
 }
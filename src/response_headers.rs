@@ -0,0 +1,106 @@
+use crate::link;
+
+/// The headers that came back with a response.
+///
+/// Header names are matched case insensitively, as per the HTTP spec.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl ResponseHeaders {
+    /// Header names are kept as given, and matched case insensitively.
+    pub fn new(headers: Vec<(String, String)>) -> Self {
+        Self {
+            headers,
+        }
+    }
+
+    /// Returns the first value for the header given, if it is present.
+    pub fn get(
+        &self,
+        key: &str,
+    ) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns every value for the header given.
+    pub fn get_all<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// True if the header given is present.
+    pub fn contains(
+        &self,
+        key: &str,
+    ) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// An iterator over all header key => value pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The links from every `Link` header, in the order they were sent.
+    pub fn links(&self) -> Vec<link::Link> {
+        self.get_all("Link").flat_map(link::parse_links).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn links_from_every_header() {
+        let headers = ResponseHeaders::new(vec![
+            ("Link".to_string(), "</a>; rel=next".to_string()),
+            ("link".to_string(), "</b>; rel=\"prev\", </c>; rel=last".to_string()),
+        ]);
+
+        let links = headers.links();
+        let urls = links.iter().map(|link| link.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["/a", "/b", "/c"]);
+        assert!(links[1].has_rel("prev"));
+    }
+
+    #[test]
+    fn get_is_case_insensitive() {
+        let headers =
+            ResponseHeaders::new(vec![("content-type".to_string(), "text/plain".to_string())]);
+
+        assert_eq!(headers.get("Content-Type"), Some("text/plain"));
+        assert_eq!(headers.get("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(headers.get("content-length"), None);
+    }
+
+    #[test]
+    fn get_all_returns_repeated_headers() {
+        let headers = ResponseHeaders::new(vec![
+            ("set-cookie".to_string(), "a=1".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+            ("set-cookie".to_string(), "b=2".to_string()),
+        ]);
+
+        let cookies = headers.get_all("Set-Cookie").collect::<Vec<_>>();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::link;
+use crate::response_headers;
+use httpdate;
+use std::time;
+
+/// The status and headers of a response, returned alongside its body.
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    pub status: u32,
+    pub headers: response_headers::ResponseHeaders,
+}
+
+impl ResponseMeta {
+    /// The `ETag` of the response, for passing to `Path::if_match` or
+    /// `Path::if_none_match` on a later request.
+    pub fn etag(&self) -> Option<&str> {
+        self.headers.get("ETag")
+    }
+
+    /// The links from the `Link` headers of the response.
+    pub fn links(&self) -> Vec<link::Link> {
+        self.headers.links()
+    }
+
+    /// The `Last-Modified` of the response, if it is a valid HTTP date.
+    pub fn last_modified(&self) -> Option<time::SystemTime> {
+        httpdate::parse_http_date(self.headers.get("Last-Modified")?).ok()
+    }
+}
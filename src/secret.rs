@@ -0,0 +1,76 @@
+use base64;
+use std::fmt;
+
+/// A value which should never be printed, such as a password or token.
+///
+/// The value is hidden from `Debug` output, so it doesn't end up in logs.
+/// Use `expose` to get at the value itself.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// The secret value itself.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "Secret(REDACTED)")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl<'a> From<&'a str> for Secret {
+    fn from(value: &'a str) -> Self {
+        Secret::new(value)
+    }
+}
+
+/// Builds the value for an `Authorization` header, using a bearer token.
+pub(crate) fn bearer_authorization(token: &Secret) -> Secret {
+    Secret(format!("Bearer {}", token.expose()))
+}
+
+/// Builds the value for an `Authorization` header, using HTTP Basic auth.
+pub(crate) fn basic_authorization(
+    user: &str,
+    password: &Secret,
+) -> Secret {
+    let credentials = format!("{}:{}", user, password.expose());
+
+    Secret(format!("Basic {}", base64::encode(credentials)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debug_hides_the_value() {
+        let secret = Secret::from("hunter2");
+
+        assert_eq!(format!("{:?}", secret), "Secret(REDACTED)");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn basic_authorization_encodes_credentials() {
+        let value = basic_authorization("Aladdin", &Secret::from("open sesame"));
+
+        assert_eq!(value.expose(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error;
+use crate::request;
+use crate::secret;
+use base64;
+use hex;
+use hmac;
+use hmac::Mac;
+use sha2;
+use std::fmt;
+use std::time;
+
+/// Signs requests just before they are sent.
+///
+/// This is set on a `Domain` using `Domain::signer`. It is given the
+/// request once the url, headers and body are final, and can add headers
+/// to it.
+pub trait RequestSigner {
+    fn sign(
+        &self,
+        request: &mut request::Request,
+    ) -> Result<(), error::Error>;
+}
+
+/// How the bytes of a signature are written into a header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignatureEncoding {
+    /// Lowercase hex.
+    Hex,
+    Base64,
+}
+
+/// Signs requests using HMAC-SHA256.
+///
+/// The signature is made over the timestamp, method, path, query and body,
+/// each separated by a newline. i.e.
+///
+/// ```text
+/// 1700000000
+/// POST
+/// /orders
+/// symbol=BTCUSD
+/// {"size":1}
+/// ```
+///
+/// The timestamp is the number of seconds since the unix epoch. It is sent
+/// in the `X-Timestamp` header, and the signature in `X-Signature`.
+pub struct HmacSigner {
+    secret: secret::Secret,
+    signature_header: &'static str,
+    timestamp_header: &'static str,
+    key_header: Option<(&'static str, String)>,
+    encoding: SignatureEncoding,
+    timestamp: Box<dyn Fn() -> String>,
+}
+
+impl HmacSigner {
+    pub fn new(secret: impl Into<secret::Secret>) -> Self {
+        Self {
+            secret: secret.into(),
+            signature_header: "X-Signature",
+            timestamp_header: "X-Timestamp",
+            key_header: None,
+            encoding: SignatureEncoding::Hex,
+            timestamp: Box::new(unix_timestamp),
+        }
+    }
+
+    /// The header the signature is sent in.
+    pub fn signature_header(
+        mut self,
+        header: &'static str,
+    ) -> Self {
+        self.signature_header = header;
+
+        self
+    }
+
+    /// The header the timestamp is sent in.
+    pub fn timestamp_header(
+        mut self,
+        header: &'static str,
+    ) -> Self {
+        self.timestamp_header = header;
+
+        self
+    }
+
+    /// Sends the public key identifying the secret, in the header given.
+    pub fn key_header(
+        mut self,
+        header: &'static str,
+        key: &str,
+    ) -> Self {
+        self.key_header = Some((header, key.to_string()));
+
+        self
+    }
+
+    pub fn encoding(
+        mut self,
+        encoding: SignatureEncoding,
+    ) -> Self {
+        self.encoding = encoding;
+
+        self
+    }
+
+    /// Replaces how the timestamp is made. i.e. for APIs which expect
+    /// milliseconds, or an ISO 8601 date.
+    pub fn timestamp(
+        mut self,
+        timestamp: impl Fn() -> String + 'static,
+    ) -> Self {
+        self.timestamp = Box::new(timestamp);
+
+        self
+    }
+
+    /// The signature for the request, made at the time given.
+    fn signature(
+        &self,
+        request: &request::Request,
+        timestamp: &str,
+    ) -> String {
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(self.secret.expose().as_bytes())
+            .expect("HMAC accepts keys of any length");
+
+        let head =
+            format!("{}\n{}\n{}\n{}\n", timestamp, request.method, request.path(), request.query());
+        mac.update(head.as_bytes());
+        mac.update(request.body_bytes());
+
+        let signature = mac.finalize().into_bytes();
+        match self.encoding {
+            SignatureEncoding::Hex => hex::encode(signature),
+            SignatureEncoding::Base64 => base64::encode(signature),
+        }
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(
+        &self,
+        request: &mut request::Request,
+    ) -> Result<(), error::Error> {
+        let timestamp = (self.timestamp)();
+        let signature = self.signature(request, &timestamp);
+
+        if let Some((header, ref key)) = self.key_header {
+            request.set_header(header, key);
+        }
+        request.set_header(self.timestamp_header, &timestamp);
+        request.set_header(self.signature_header, &signature);
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HmacSigner {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("secret", &self.secret)
+            .field("signature_header", &self.signature_header)
+            .field("timestamp_header", &self.timestamp_header)
+            .field("key_header", &self.key_header)
+            .field("encoding", &self.encoding)
+            .finish()
+    }
+}
+
+fn unix_timestamp() -> String {
+    let now = time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default();
+
+    now.as_secs().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::method;
+
+    fn signer() -> HmacSigner {
+        HmacSigner::new("shh").timestamp(|| "1700000000".to_string())
+    }
+
+    #[test]
+    fn signs_method_path_query_and_body() {
+        let mut request = request::Request::new(
+            method::Method::Post,
+            "https://api.example.com/orders?symbol=BTCUSD".to_string(),
+            "https://api.example.com/orders?symbol=BTCUSD".to_string(),
+            Some(b"{\"size\":1}".to_vec()),
+        );
+        signer().key_header("X-Api-Key", "public").sign(&mut request).unwrap();
+
+        assert_eq!(request.header("X-Timestamp"), Some("1700000000"));
+        assert_eq!(request.header("X-Api-Key"), Some("public"));
+        assert_eq!(
+            request.header("X-Signature"),
+            Some("5eabde6a7b97d558f2fb3ce6c11a347484b38738ed3372b6f3d9152883ca148d")
+        );
+    }
+
+    #[test]
+    fn signs_with_base64() {
+        let mut request = request::Request::new(
+            method::Method::Post,
+            "https://api.example.com/orders?symbol=BTCUSD".to_string(),
+            "https://api.example.com/orders?symbol=BTCUSD".to_string(),
+            Some(b"{\"size\":1}".to_vec()),
+        );
+        signer()
+            .encoding(SignatureEncoding::Base64)
+            .signature_header("Sig")
+            .sign(&mut request)
+            .unwrap();
+
+        assert_eq!(request.header("Sig"), Some("XqveanuX1Vjy+zzmwRo0dISzhzjtM3K289kVKIPKFI0="));
+    }
+}
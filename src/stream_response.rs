@@ -0,0 +1,45 @@
+use crate::error;
+use crate::response_headers::ResponseHeaders;
+use futures;
+use futures::stream::Stream;
+use hyper;
+
+/// A response where the body has not yet been read.
+///
+/// The body is handed back as a stream of chunks, as they arrive over the
+/// network. This allows large responses to be processed without holding
+/// them in memory.
+///
+/// The `Domain` used to make the request must outlive the stream, as it
+/// owns the runtime which drives the connection.
+#[derive(Debug)]
+pub struct StreamResponse {
+    pub status: u32,
+    pub headers: ResponseHeaders,
+    pub body: BodyStream,
+}
+
+/// The chunks of a response body, as they arrive.
+#[derive(Debug)]
+pub struct BodyStream {
+    body: hyper::Body,
+}
+
+impl BodyStream {
+    pub(crate) fn new(body: hyper::Body) -> Self {
+        Self {
+            body,
+        }
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Vec<u8>;
+    type Error = error::Error;
+
+    fn poll(&mut self) -> Result<futures::Async<Option<Self::Item>>, Self::Error> {
+        let polled = self.body.poll()?;
+
+        Ok(polled.map(|maybe_chunk| maybe_chunk.map(|chunk| chunk.to_vec())))
+    }
+}
@@ -0,0 +1,44 @@
+//! Directories for tests which write files.
+
+use std::env;
+use std::fs;
+use std::path;
+use std::process;
+
+/// A directory of its own for a test, named after the test and the
+/// process, so tests running at the same time never share files.
+///
+/// It is removed, along with everything in it, when it is dropped.
+pub(crate) struct TestDir {
+    path: path::PathBuf,
+}
+
+impl TestDir {
+    pub(crate) fn new(test_name: &str) -> Self {
+        let path = env::temp_dir().join(format!("burgundy_{}_{}", test_name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self {
+            path,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    /// A file in the directory.
+    pub(crate) fn join(
+        &self,
+        name: &str,
+    ) -> path::PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
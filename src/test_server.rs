@@ -0,0 +1,212 @@
+//! A tiny HTTP server, for testing requests against.
+//!
+//! Every connection is served a single response, and then closed.
+
+use rustls;
+use rustls::Session;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net;
+use std::sync;
+use std::thread;
+
+/// What the server saw of a request.
+#[derive(Debug, Clone)]
+pub(crate) struct ReceivedRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    pub(crate) fn header(
+        &self,
+        key: &str,
+    ) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub(crate) struct TestServer {
+    address: net::SocketAddr,
+    requests: sync::Arc<sync::Mutex<Vec<ReceivedRequest>>>,
+    is_tls: bool,
+}
+
+impl TestServer {
+    /// Starts a server which answers each request using the handler.
+    ///
+    /// The handler returns the full raw response, status line included.
+    pub(crate) fn new(handler: impl Fn(&ReceivedRequest) -> Vec<u8> + Send + 'static) -> Self {
+        Self::start(None, handler)
+    }
+
+    /// Starts a server which answers over TLS, with the config given. It is
+    /// reached at `localhost`, so the certificate can be checked.
+    pub(crate) fn with_tls(
+        config: sync::Arc<rustls::ServerConfig>,
+        status: u32,
+        body: &'static str,
+    ) -> Self {
+        Self::start(Some(config), move |_| response(status, &[], body.as_bytes()))
+    }
+
+    fn start(
+        tls: Option<sync::Arc<rustls::ServerConfig>>,
+        handler: impl Fn(&ReceivedRequest) -> Vec<u8> + Send + 'static,
+    ) -> Self {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = sync::Arc::new(sync::Mutex::new(Vec::new()));
+        let server_requests = sync::Arc::clone(&requests);
+        let is_tls = tls.is_some();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                };
+
+                match &tls {
+                    Some(config) => {
+                        let mut session = rustls::ServerSession::new(config);
+                        serve(
+                            &mut rustls::Stream::new(&mut session, &mut stream),
+                            &handler,
+                            &server_requests,
+                        );
+
+                        session.send_close_notify();
+                        let _ = session.write_tls(&mut stream);
+                    },
+                    None => serve(&mut stream, &handler, &server_requests),
+                }
+            }
+        });
+
+        Self {
+            address,
+            requests,
+            is_tls,
+        }
+    }
+
+    /// Starts a server which always responds with the same status and body.
+    pub(crate) fn with_response(
+        status: u32,
+        body: &'static str,
+    ) -> Self {
+        Self::new(move |_| response(status, &[], body.as_bytes()))
+    }
+
+    pub(crate) fn url(&self) -> String {
+        if self.is_tls {
+            format!("https://localhost:{}", self.address.port())
+        } else {
+            format!("http://{}", self.address)
+        }
+    }
+
+    pub(crate) fn requests(&self) -> Vec<ReceivedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Builds a raw response, with the content length filled in.
+pub(crate) fn response(
+    status: u32,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Vec<u8> {
+    let mut raw = format!("HTTP/1.1 {} Status\r\n", status);
+    for (key, value) in headers {
+        raw.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    raw.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+
+    let mut raw = raw.into_bytes();
+    raw.extend_from_slice(body);
+    raw
+}
+
+/// Reads a request from the stream, and writes back the response for it.
+fn serve(
+    stream: &mut (impl Read + Write),
+    handler: &impl Fn(&ReceivedRequest) -> Vec<u8>,
+    requests: &sync::Mutex<Vec<ReceivedRequest>>,
+) {
+    if let Some(request) = read_request(stream) {
+        let response = handler(&request);
+        requests.lock().unwrap().push(request);
+        let _ = stream.write_all(&response);
+        let _ = stream.flush();
+    }
+}
+
+fn read_request(stream: &mut impl Read) -> Option<ReceivedRequest> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next()?.to_string();
+    let path = request_parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let mut header = line.splitn(2, ':');
+        let key = header.next()?.trim().to_string();
+        let value = header.next().unwrap_or("").trim().to_string();
+        headers.push((key, value));
+    }
+
+    let find_header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    let mut body = Vec::new();
+    if find_header("transfer-encoding").is_some_and(|value| value.contains("chunked")) {
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line).ok()?;
+            let size = usize::from_str_radix(size_line.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        let content_length = find_header("content-length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+
+    Some(ReceivedRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
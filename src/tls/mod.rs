@@ -0,0 +1,363 @@
+//! TLS settings for the connections a `Domain` makes.
+//!
+//! Connections use native-tls by default. Build with the `rustls` feature
+//! to use rustls instead. When both features are on, rustls is used.
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("burgundy needs either the `native-tls` or `rustls` feature turned on");
+
+#[cfg(feature = "rustls")]
+mod rustls_connector;
+
+use error;
+use proxy;
+use std::fmt;
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+use hyper_tls;
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+use native_tls;
+#[cfg(feature = "rustls")]
+use rustls;
+#[cfg(feature = "rustls")]
+use std::sync;
+#[cfg(feature = "rustls")]
+use webpki_roots;
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub(crate) type HttpsConnector = hyper_tls::HttpsConnector<proxy::ProxyConnector>;
+
+#[cfg(feature = "rustls")]
+pub(crate) type HttpsConnector = rustls_connector::RustlsConnector;
+
+/// The oldest version of TLS a connection may use.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+/// Extra certificates to trust, a client certificate to present, and the
+/// oldest TLS version to allow.
+///
+/// The certificates trusted by default are still trusted. With native-tls
+/// these come from the system, and with rustls from `webpki-roots`.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    root_certificates: Vec<Vec<u8>>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+    min_version: Option<TlsVersion>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the PEM encoded certificates given, such as an internal CA.
+    pub fn root_certificate_pem(
+        mut self,
+        pem: &[u8],
+    ) -> Self {
+        self.root_certificates.push(pem.to_vec());
+
+        self
+    }
+
+    /// Presents a client certificate, for servers which use mutual TLS.
+    ///
+    /// The chain is PEM encoded, with the leaf certificate first. The
+    /// private key is a PEM encoded PKCS #8 key.
+    pub fn identity_pem(
+        mut self,
+        certificate_chain: &[u8],
+        private_key: &[u8],
+    ) -> Self {
+        self.identity = Some((certificate_chain.to_vec(), private_key.to_vec()));
+
+        self
+    }
+
+    /// The oldest version of TLS to allow. rustls never goes below TLS 1.2.
+    pub fn min_version(
+        mut self,
+        version: TlsVersion,
+    ) -> Self {
+        self.min_version = Some(version);
+
+        self
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("root_certificates", &self.root_certificates.len())
+            .field("identity", &self.identity.as_ref().map(|_| "REDACTED"))
+            .field("min_version", &self.min_version)
+            .finish()
+    }
+}
+
+/// Builds a connector for both HTTP and HTTPS urls, using the config.
+/// Connections go through the proxies given, where they apply.
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub(crate) fn https_connector(
+    config: &TlsConfig,
+    proxies: &[proxy::Proxy],
+) -> Result<HttpsConnector, error::Error> {
+    let mut builder = native_tls::TlsConnector::builder();
+
+    for pem in &config.root_certificates {
+        let certificates = certificate_pems(pem)?;
+        for certificate in certificates {
+            let certificate =
+                native_tls::Certificate::from_pem(certificate.as_bytes()).map_err(|err| {
+                    error::Error::new_tls_error(format!("invalid root certificate, {}", err))
+                })?;
+            builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some((certificate_chain, private_key)) = &config.identity {
+        let identity =
+            native_tls::Identity::from_pkcs8(certificate_chain, private_key).map_err(|err| {
+                error::Error::new_tls_error(format!("invalid client identity, {}", err))
+            })?;
+        builder.identity(identity);
+    }
+
+    if let Some(version) = config.min_version {
+        let protocol = match version {
+            TlsVersion::Tls10 => native_tls::Protocol::Tlsv10,
+            TlsVersion::Tls11 => native_tls::Protocol::Tlsv11,
+            TlsVersion::Tls12 => native_tls::Protocol::Tlsv12,
+            TlsVersion::Tls13 => native_tls::Protocol::Tlsv13,
+        };
+        builder.min_protocol_version(Some(protocol));
+    }
+
+    let tls = builder.build().map_err(|err| error::Error::new_tls_error(err.to_string()))?;
+
+    Ok(hyper_tls::HttpsConnector::from((proxy::ProxyConnector::new(proxies), tls)))
+}
+
+/// Builds a connector for both HTTP and HTTPS urls, using the config.
+/// Connections go through the proxies given, where they apply.
+#[cfg(feature = "rustls")]
+pub(crate) fn https_connector(
+    config: &TlsConfig,
+    proxies: &[proxy::Proxy],
+) -> Result<HttpsConnector, error::Error> {
+    let mut tls = rustls::ClientConfig::new();
+    tls.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+    for pem in &config.root_certificates {
+        match tls.root_store.add_pem_file(&mut pem.as_slice()) {
+            Ok((valid, 0)) if valid > 0 => {},
+            _ => return Err(error::Error::new_tls_error("invalid root certificate")),
+        }
+    }
+
+    if let Some((certificate_chain, private_key)) = &config.identity {
+        let certificates = rustls::internal::pemfile::certs(&mut certificate_chain.as_slice())
+            .ok()
+            .filter(|certificates| !certificates.is_empty())
+            .ok_or_else(|| {
+                error::Error::new_tls_error("invalid client identity, no certificates found")
+            })?;
+        let private_key =
+            rustls::internal::pemfile::pkcs8_private_keys(&mut private_key.as_slice())
+                .ok()
+                .and_then(|keys| keys.into_iter().next())
+                .ok_or_else(|| {
+                    error::Error::new_tls_error("invalid client identity, no PKCS #8 key found")
+                })?;
+
+        tls.set_single_client_cert(certificates, private_key).map_err(|err| {
+            error::Error::new_tls_error(format!("invalid client identity, {}", err))
+        })?;
+    }
+
+    if config.min_version == Some(TlsVersion::Tls13) {
+        tls.versions = vec![rustls::ProtocolVersion::TLSv1_3];
+    }
+
+    Ok(rustls_connector::RustlsConnector::new(
+        proxy::ProxyConnector::new(proxies),
+        sync::Arc::new(tls),
+    ))
+}
+
+/// Splits PEM text into each of the certificates it holds.
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+fn certificate_pems(pem: &[u8]) -> Result<Vec<String>, error::Error> {
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let text = String::from_utf8_lossy(pem);
+    let certificates = text
+        .split_inclusive(END)
+        .filter(|block| block.ends_with(END))
+        .map(|block| block.trim().to_string())
+        .collect::<Vec<_>>();
+
+    if certificates.is_empty() {
+        return Err(error::Error::new_tls_error("invalid root certificate, no certificates found"));
+    }
+
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use domain::Domain;
+    use rcgen;
+    use rustls as rustls_server;
+    use std::sync::Arc;
+    use test_server;
+
+    /// A certificate authority, and a certificate for `localhost` it signed.
+    struct Certificates {
+        ca_pem: String,
+        ca_der: Vec<u8>,
+        leaf_pem: String,
+        leaf_key_pem: String,
+    }
+
+    fn certificates(name: &str) -> Certificates {
+        let mut ca_params = rcgen::CertificateParams::new(Vec::new());
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(rcgen::DnType::CommonName, format!("{} CA", name));
+        let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+
+        let mut leaf_params = rcgen::CertificateParams::new(vec!["localhost".to_string()]);
+        leaf_params.distinguished_name.push(rcgen::DnType::CommonName, "localhost");
+        let leaf = rcgen::Certificate::from_params(leaf_params).unwrap();
+
+        Certificates {
+            ca_pem: ca.serialize_pem().unwrap(),
+            ca_der: ca.serialize_der().unwrap(),
+            leaf_pem: leaf.serialize_pem_with_signer(&ca).unwrap(),
+            leaf_key_pem: leaf.serialize_private_key_pem(),
+        }
+    }
+
+    fn server_config(
+        server: &Certificates,
+        client_ca: Option<&Certificates>,
+    ) -> rustls_server::ServerConfig {
+        let verifier = match client_ca {
+            Some(client_ca) => {
+                let mut roots = rustls_server::RootCertStore::empty();
+                roots.add(&rustls_server::Certificate(client_ca.ca_der.clone())).unwrap();
+                rustls_server::AllowAnyAuthenticatedClient::new(roots)
+            },
+            None => rustls_server::NoClientAuth::new(),
+        };
+
+        let certificates =
+            rustls_server::internal::pemfile::certs(&mut server.leaf_pem.as_bytes()).unwrap();
+        let mut keys = rustls_server::internal::pemfile::pkcs8_private_keys(
+            &mut server.leaf_key_pem.as_bytes(),
+        )
+        .unwrap();
+
+        let mut config = rustls_server::ServerConfig::new(verifier);
+        config.set_single_cert(certificates, keys.remove(0)).unwrap();
+        config
+    }
+
+    fn get(
+        server: &test_server::TestServer,
+        config: Option<TlsConfig>,
+    ) -> Result<String, error::Error> {
+        let mut domain = Domain::new(&server.url());
+        if let Some(config) = config {
+            domain.tls(config)?;
+        }
+
+        domain.get().push(&"pets").execute_as_string::<()>(None)
+    }
+
+    #[test]
+    fn untrusted_certificates_are_rejected() {
+        let server_certificates = certificates("Server");
+        let config = server_config(&server_certificates, None);
+        let server = test_server::TestServer::with_tls(Arc::new(config), 200, "rex");
+
+        let err = get(&server, None).unwrap_err();
+
+        assert!(err.is_connect(), "expected a connect error, got {}", err);
+    }
+
+    #[test]
+    fn trusts_extra_root_certificates() {
+        let server_certificates = certificates("Server");
+        let config = server_config(&server_certificates, None);
+        let server = test_server::TestServer::with_tls(Arc::new(config), 200, "rex");
+
+        let tls = TlsConfig::new().root_certificate_pem(server_certificates.ca_pem.as_bytes());
+        let body = get(&server, Some(tls)).unwrap();
+
+        assert_eq!(body, "rex");
+        assert_eq!(server.requests()[0].path, "/pets");
+    }
+
+    #[test]
+    fn presents_client_certificate() {
+        let server_certificates = certificates("Server");
+        let client_certificates = certificates("Client");
+        let config = server_config(&server_certificates, Some(&client_certificates));
+        let server = test_server::TestServer::with_tls(Arc::new(config), 200, "rex");
+
+        let tls = TlsConfig::new().root_certificate_pem(server_certificates.ca_pem.as_bytes());
+        assert!(get(&server, Some(tls.clone())).is_err());
+
+        let tls = tls.identity_pem(
+            client_certificates.leaf_pem.as_bytes(),
+            client_certificates.leaf_key_pem.as_bytes(),
+        );
+        let body = get(&server, Some(tls)).unwrap();
+
+        assert_eq!(body, "rex");
+    }
+
+    #[test]
+    fn min_version_above_server_fails() {
+        let server_certificates = certificates("Server");
+        let mut config = server_config(&server_certificates, None);
+        config.versions = vec![rustls_server::ProtocolVersion::TLSv1_2];
+        let server = test_server::TestServer::with_tls(Arc::new(config), 200, "rex");
+
+        let tls = TlsConfig::new().root_certificate_pem(server_certificates.ca_pem.as_bytes());
+        assert_eq!(get(&server, Some(tls.clone().min_version(TlsVersion::Tls12))).unwrap(), "rex");
+
+        let err = get(&server, Some(tls.min_version(TlsVersion::Tls13))).unwrap_err();
+        assert!(err.is_connect(), "expected a connect error, got {}", err);
+    }
+
+    #[test]
+    fn invalid_pem_is_an_error() {
+        let mut domain = Domain::new("https://api.example.com");
+
+        let err =
+            domain.tls(TlsConfig::new().root_certificate_pem(b"not a certificate")).unwrap_err();
+        assert_eq!(err.kind(), error::ErrorKind::Request);
+
+        let tls = TlsConfig::new().identity_pem(b"not a certificate", b"not a key");
+        assert!(domain.tls(tls).is_err());
+    }
+
+    #[test]
+    fn debug_hides_private_key() {
+        let tls = TlsConfig::new().identity_pem(b"certificate", b"private key");
+
+        assert!(!format!("{:?}", tls).contains("private key"));
+    }
+}
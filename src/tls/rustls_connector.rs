@@ -0,0 +1,214 @@
+//! A hyper connector using rustls, for when the `rustls` feature is on.
+//!
+//! The TLS stream is the synchronous `rustls::Stream`, run over a
+//! non-blocking tokio socket. A socket which is not ready returns
+//! `WouldBlock`, which tokio turns into `NotReady`.
+
+use futures::future;
+use futures::Async;
+use futures::Future;
+use futures::Poll;
+use hyper::client::connect::Connect;
+use hyper::client::connect::Connected;
+use hyper::client::connect::Destination;
+use proxy;
+use rustls;
+use rustls::Session;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::sync;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpStream;
+use webpki;
+
+#[derive(Clone)]
+pub(crate) struct RustlsConnector {
+    http: proxy::ProxyConnector,
+    config: sync::Arc<rustls::ClientConfig>,
+}
+
+impl RustlsConnector {
+    pub(crate) fn new(
+        http: proxy::ProxyConnector,
+        config: sync::Arc<rustls::ClientConfig>,
+    ) -> Self {
+        Self {
+            http,
+            config,
+        }
+    }
+}
+
+impl Connect for RustlsConnector {
+    type Transport = MaybeTlsStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (MaybeTlsStream, Connected), Error = io::Error> + Send>;
+
+    fn connect(
+        &self,
+        destination: Destination,
+    ) -> Self::Future {
+        let is_https = destination.scheme() == "https";
+        let host = destination.host().trim_start_matches('[').trim_end_matches(']').to_string();
+        let config = sync::Arc::clone(&self.config);
+        let connecting = self.http.connect(destination);
+
+        if !is_https {
+            return Box::new(
+                connecting.map(|(socket, connected)| (MaybeTlsStream::Plain(socket), connected)),
+            );
+        }
+
+        Box::new(connecting.and_then(move |(socket, connected)| {
+            let name = match webpki::DNSNameRef::try_from_ascii_str(&host) {
+                Ok(name) => name,
+                Err(_) => {
+                    let err = io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("'{}' is not a valid DNS name, which rustls needs", host),
+                    );
+                    return future::Either::A(future::err(err));
+                },
+            };
+
+            let stream = TlsStream {
+                session: rustls::ClientSession::new(&config, name),
+                socket,
+                is_shutdown: false,
+            };
+            let handshake = Handshake {
+                stream: Some(stream),
+            };
+
+            future::Either::B(
+                handshake.map(move |stream| (MaybeTlsStream::Tls(Box::new(stream)), connected)),
+            )
+        }))
+    }
+}
+
+/// A connection, which is only encrypted for `https` urls.
+pub(crate) enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream>),
+}
+
+impl Read for MaybeTlsStream {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        match self {
+            MaybeTlsStream::Plain(socket) => socket.read(buf),
+            MaybeTlsStream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for MaybeTlsStream {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> io::Result<usize> {
+        match self {
+            MaybeTlsStream::Plain(socket) => socket.write(buf),
+            MaybeTlsStream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            MaybeTlsStream::Plain(socket) => socket.flush(),
+            MaybeTlsStream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+impl AsyncRead for MaybeTlsStream {}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match self {
+            MaybeTlsStream::Plain(socket) => AsyncWrite::shutdown(socket),
+            MaybeTlsStream::Tls(stream) => stream.shutdown(),
+        }
+    }
+}
+
+pub(crate) struct TlsStream {
+    session: rustls::ClientSession,
+    socket: TcpStream,
+    is_shutdown: bool,
+}
+
+impl TlsStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        if !self.is_shutdown {
+            self.session.send_close_notify();
+            self.is_shutdown = true;
+        }
+
+        while self.session.wants_write() {
+            match self.session.write_tls(&mut self.socket) {
+                Ok(_) => {},
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(Async::NotReady)
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        AsyncWrite::shutdown(&mut self.socket)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        rustls::Stream::new(&mut self.session, &mut self.socket).read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> io::Result<usize> {
+        rustls::Stream::new(&mut self.session, &mut self.socket).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        rustls::Stream::new(&mut self.session, &mut self.socket).flush()
+    }
+}
+
+/// Drives the TLS handshake to completion.
+struct Handshake {
+    stream: Option<TlsStream>,
+}
+
+impl Future for Handshake {
+    type Item = TlsStream;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<TlsStream, io::Error> {
+        {
+            let stream = self.stream.as_mut().expect("handshake polled after it finished");
+            while stream.session.is_handshaking() || stream.session.wants_write() {
+                match stream.session.complete_io(&mut stream.socket) {
+                    Ok(_) => {},
+                    Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        return Ok(Async::NotReady)
+                    },
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Ok(Async::Ready(self.stream.take().expect("handshake polled after it finished")))
+    }
+}
@@ -0,0 +1,488 @@
+use crate::error;
+use crate::percent_encoding;
+use serde;
+use serde_json;
+use std::fmt::Write;
+
+/// A URI template, as described in RFC 6570, such as
+/// `/repos/{owner}/{repo}/issues{?state,labels}`.
+///
+/// All four levels are supported. Variables are taken from anything which
+/// serializes to a struct or map.
+#[derive(Debug, Clone)]
+pub(crate) struct UriTemplate<'a> {
+    template: &'a str,
+    parts: Vec<TemplatePart<'a>>,
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart<'a> {
+    Literal(&'a str),
+    Expression {
+        operator: Operator,
+        variables: Vec<Variable<'a>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Variable<'a> {
+    name: &'a str,
+    prefix: Option<usize>,
+    explode: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    Simple,
+    Reserved,
+    Fragment,
+    Label,
+    PathSegment,
+    PathParameter,
+    Query,
+    QueryContinuation,
+}
+
+/// The value of a variable, once serialized.
+#[derive(Debug)]
+enum Value {
+    Undefined,
+    Text(String),
+    List(Vec<String>),
+    Map(Vec<(String, String)>),
+}
+
+/// A template with its variables filled in.
+///
+/// Query expressions, such as `{?state}`, are kept apart as key/value
+/// pairs. This is so they are encoded by the `QueryBuilder`, the same as
+/// any other query parameter.
+#[derive(Debug)]
+pub(crate) struct Expansion {
+    pub(crate) path: String,
+    pub(crate) query: Vec<(String, String)>,
+}
+
+impl<'a> UriTemplate<'a> {
+    pub(crate) fn parse(template: &'a str) -> Result<Self, error::Error> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            let start = rest.find('{').unwrap_or(rest.len());
+            let literal = &rest[..start];
+            if literal.contains('}') {
+                return Err(error::Error::new_invalid_uri_template(template, "unexpected '}'"));
+            }
+            if !literal.is_empty() {
+                parts.push(TemplatePart::Literal(literal));
+            }
+
+            rest = &rest[start..];
+            if rest.is_empty() {
+                break;
+            }
+
+            let end = rest.find('}').ok_or_else(|| {
+                error::Error::new_invalid_uri_template(template, "expression is not closed")
+            })?;
+            parts.push(parse_expression(template, &rest[1..end])?);
+            rest = &rest[end + 1..];
+        }
+
+        Ok(Self {
+            template,
+            parts,
+        })
+    }
+
+    /// Fills in the template with the variables given.
+    ///
+    /// Variables which are `null`, or empty lists and maps, are left out.
+    /// Variables missing altogether are an error.
+    pub(crate) fn expand<V: serde::ser::Serialize + ?Sized>(
+        &self,
+        variables: &V,
+    ) -> Result<Expansion, error::Error> {
+        let variables = match serde_json::to_value(variables) {
+            Ok(serde_json::Value::Object(variables)) => variables,
+            Ok(_) => {
+                return Err(error::Error::new_invalid_uri_template(
+                    self.template,
+                    "variables must be a struct or map",
+                ))
+            },
+            Err(err) => {
+                return Err(error::Error::new_invalid_uri_template(self.template, err.to_string()))
+            },
+        };
+
+        let mut expansion = Expansion {
+            path: String::new(),
+            query: Vec::new(),
+        };
+
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(literal) => expansion.path.push_str(literal),
+                TemplatePart::Expression {
+                    operator,
+                    variables: expression,
+                } => {
+                    let mut is_first = true;
+
+                    for variable in expression {
+                        let value = match variables.get(variable.name) {
+                            Some(value) => self.to_value(variable, value)?,
+                            None => {
+                                return Err(error::Error::new_missing_template_variable(
+                                    self.template,
+                                    variable.name,
+                                ))
+                            },
+                        };
+
+                        if operator.is_query() {
+                            push_query(variable, value, &mut expansion.query);
+                        } else if let Some(text) = expand_value(*operator, variable, value)? {
+                            let separator = if is_first {
+                                operator.first()
+                            } else {
+                                operator.separator()
+                            };
+                            write!(expansion.path, "{}{}", separator, text)?;
+                            is_first = false;
+                        }
+                    }
+                },
+            }
+        }
+
+        Ok(expansion)
+    }
+
+    fn to_value(
+        &self,
+        variable: &Variable,
+        value: &serde_json::Value,
+    ) -> Result<Value, error::Error> {
+        let nested = || {
+            error::Error::new_invalid_uri_template(
+                self.template,
+                format!("variable '{}' is nested too deeply", variable.name),
+            )
+        };
+
+        let value = match value {
+            serde_json::Value::Null => Value::Undefined,
+            serde_json::Value::Array(items) if items.is_empty() => Value::Undefined,
+            serde_json::Value::Object(items) if items.is_empty() => Value::Undefined,
+            serde_json::Value::Array(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| to_text(item).ok_or_else(nested))
+                    .collect::<Result<_, _>>()?,
+            ),
+            serde_json::Value::Object(items) => Value::Map(
+                items
+                    .iter()
+                    .map(|(key, item)| Ok((key.clone(), to_text(item).ok_or_else(nested)?)))
+                    .collect::<Result<_, error::Error>>()?,
+            ),
+            scalar => Value::Text(to_text(scalar).ok_or_else(nested)?),
+        };
+
+        match value {
+            Value::List(_) | Value::Map(_) if variable.prefix.is_some() => {
+                Err(error::Error::new_invalid_uri_template(
+                    self.template,
+                    format!(
+                        "variable '{}' is a list or map, and cannot take a prefix",
+                        variable.name
+                    ),
+                ))
+            },
+            value => Ok(value),
+        }
+    }
+}
+
+impl Operator {
+    fn parse(operator: char) -> Option<Self> {
+        match operator {
+            '+' => Some(Operator::Reserved),
+            '#' => Some(Operator::Fragment),
+            '.' => Some(Operator::Label),
+            '/' => Some(Operator::PathSegment),
+            ';' => Some(Operator::PathParameter),
+            '?' => Some(Operator::Query),
+            '&' => Some(Operator::QueryContinuation),
+            _ => None,
+        }
+    }
+
+    fn first(self) -> &'static str {
+        match self {
+            Operator::Simple | Operator::Reserved => "",
+            Operator::Fragment => "#",
+            Operator::Label => ".",
+            Operator::PathSegment => "/",
+            Operator::PathParameter => ";",
+            Operator::Query => "?",
+            Operator::QueryContinuation => "&",
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self {
+            Operator::Simple | Operator::Reserved | Operator::Fragment => ",",
+            Operator::Label => ".",
+            Operator::PathSegment => "/",
+            Operator::PathParameter => ";",
+            Operator::Query | Operator::QueryContinuation => "&",
+        }
+    }
+
+    fn is_named(self) -> bool {
+        self == Operator::PathParameter || self.is_query()
+    }
+
+    fn is_query(self) -> bool {
+        self == Operator::Query || self == Operator::QueryContinuation
+    }
+
+    fn allows_reserved(self) -> bool {
+        self == Operator::Reserved || self == Operator::Fragment
+    }
+}
+
+fn parse_expression<'a>(
+    template: &str,
+    expression: &'a str,
+) -> Result<TemplatePart<'a>, error::Error> {
+    let (operator, variables) = match expression.chars().next().and_then(Operator::parse) {
+        Some(operator) => (operator, &expression[1..]),
+        None => (Operator::Simple, expression),
+    };
+
+    let variables = variables
+        .split(',')
+        .map(|variable| parse_variable(template, variable))
+        .collect::<Result<_, _>>()?;
+
+    Ok(TemplatePart::Expression {
+        operator,
+        variables,
+    })
+}
+
+fn parse_variable<'a>(
+    template: &str,
+    variable: &'a str,
+) -> Result<Variable<'a>, error::Error> {
+    let invalid = || {
+        error::Error::new_invalid_uri_template(template, format!("invalid variable '{}'", variable))
+    };
+
+    let (spec, explode) = match variable.strip_suffix('*') {
+        Some(spec) => (spec, true),
+        None => (variable, false),
+    };
+    let (name, prefix) = match spec.split_once(':') {
+        Some((name, length)) => {
+            let length = length.parse().ok().filter(|length| (1..10_000).contains(length));
+            (name, Some(length.ok_or_else(invalid)?))
+        },
+        None => (spec, None),
+    };
+
+    let is_valid_name = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%');
+    if !is_valid_name || (explode && prefix.is_some()) {
+        return Err(invalid());
+    }
+
+    Ok(Variable {
+        name,
+        prefix,
+        explode,
+    })
+}
+
+fn to_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(boolean) => Some(boolean.to_string()),
+        serde_json::Value::Null => Some(String::new()),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+    }
+}
+
+/// Expands one variable of a path expression. Returns `None` when the
+/// variable is undefined, and so left out.
+fn expand_value(
+    operator: Operator,
+    variable: &Variable,
+    value: Value,
+) -> Result<Option<String>, error::Error> {
+    let encode = |text: &str| {
+        if operator.allows_reserved() {
+            percent_encoding::encode_allowing_reserved(text)
+        } else {
+            percent_encoding::encode_path_segment(text)
+        }
+    };
+    let named = |text: String| {
+        if !operator.is_named() {
+            text
+        } else if text.is_empty() {
+            variable.name.to_string()
+        } else {
+            format!("{}={}", variable.name, text)
+        }
+    };
+
+    let text = match value {
+        Value::Undefined => return Ok(None),
+        Value::Text(text) => named(encode(prefix_of(&text, variable.prefix))),
+        Value::List(items) if variable.explode => items
+            .iter()
+            .map(|item| named(encode(item)))
+            .collect::<Vec<_>>()
+            .join(operator.separator()),
+        Value::List(items) => {
+            named(items.iter().map(|item| encode(item)).collect::<Vec<_>>().join(","))
+        },
+        Value::Map(items) if variable.explode => items
+            .iter()
+            .map(|(key, item)| {
+                if item.is_empty() && operator.is_named() {
+                    encode(key)
+                } else {
+                    format!("{}={}", encode(key), encode(item))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(operator.separator()),
+        Value::Map(items) => named(
+            items
+                .iter()
+                .map(|(key, item)| format!("{},{}", encode(key), encode(item)))
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+    };
+
+    Ok(Some(text))
+}
+
+/// Expands one variable of a query expression into key/value pairs.
+fn push_query(
+    variable: &Variable,
+    value: Value,
+    query: &mut Vec<(String, String)>,
+) {
+    let name = variable.name.to_string();
+
+    match value {
+        Value::Undefined => {},
+        Value::Text(text) => query.push((name, prefix_of(&text, variable.prefix).to_string())),
+        Value::List(items) if variable.explode => {
+            query.extend(items.into_iter().map(|item| (name.clone(), item)))
+        },
+        Value::List(items) => query.push((name, items.join(","))),
+        Value::Map(items) if variable.explode => query.extend(items),
+        Value::Map(items) => {
+            let items = items.into_iter().map(|(key, item)| format!("{},{}", key, item));
+            query.push((name, items.collect::<Vec<_>>().join(",")))
+        },
+    }
+}
+
+fn prefix_of(
+    text: &str,
+    prefix: Option<usize>,
+) -> &str {
+    match prefix.and_then(|length| text.char_indices().nth(length)) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn expand(template: &str) -> String {
+        let variables = serde_json::json!({
+            "var": "value",
+            "hello": "Hello World!",
+            "path": "/foo/bar",
+            "empty": "",
+            "undef": null,
+            "list": ["red", "green", "blue"],
+            "keys": { "comma": ",", "dot": ".", "semi": ";" },
+        });
+
+        UriTemplate::parse(template).unwrap().expand(&variables).unwrap().path
+    }
+
+    #[test]
+    fn expands_rfc_examples() {
+        assert_eq!(expand("{var}"), "value");
+        assert_eq!(expand("{hello}"), "Hello%20World%21");
+        assert_eq!(expand("{+path}/here"), "/foo/bar/here");
+        assert_eq!(expand("{#path}"), "#/foo/bar");
+        assert_eq!(expand("X{.var:3}"), "X.val");
+        assert_eq!(expand("{/var,empty}"), "/value/");
+        assert_eq!(expand("{/list*,path:4}"), "/red/green/blue/%2Ffoo");
+        assert_eq!(expand("{;list}"), ";list=red,green,blue");
+        assert_eq!(expand("{;keys*}"), ";comma=%2C;dot=.;semi=%3B");
+        assert_eq!(expand("{;empty,undef}"), ";empty");
+        assert_eq!(expand("{keys}"), "comma,%2C,dot,.,semi,%3B");
+        assert_eq!(expand("{+keys*}"), "comma=,,dot=.,semi=;");
+    }
+
+    #[test]
+    fn query_expressions_become_pairs() {
+        let mut variables = BTreeMap::new();
+        variables.insert("state", serde_json::json!("open"));
+        variables.insert("labels", serde_json::json!(["bug", "ui"]));
+        variables.insert("page", serde_json::Value::Null);
+
+        let template = UriTemplate::parse("/issues{?state,page,labels*}").unwrap();
+        let expansion = template.expand(&variables).unwrap();
+
+        assert_eq!(expansion.path, "/issues");
+        assert_eq!(
+            expansion.query,
+            vec![
+                ("state".to_string(), "open".to_string()),
+                ("labels".to_string(), "bug".to_string()),
+                ("labels".to_string(), "ui".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_variables_are_errors() {
+        let template = UriTemplate::parse("/repos/{owner}/{repo}").unwrap();
+
+        match template.expand(&serde_json::json!({ "owner": "me" })) {
+            Err(error::Error::MissingTemplateVariable {
+                variable,
+                ..
+            }) => assert_eq!(variable, "repo"),
+            other => panic!("expected missing variable, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_templates_are_errors() {
+        for template in &["/repos/{owner", "/repos/owner}", "{}", "{var:0}", "{var:2*}", "{a b}"] {
+            assert!(UriTemplate::parse(template).is_err(), "{} should not parse", template);
+        }
+    }
+}